@@ -4,7 +4,7 @@
 set -e
 
 echo "Building WASM module..."
-wasm-pack build --target web --release --out-dir www/pkg
+wasm-pack build --target web --release --out-dir www/pkg -- --features auto-start
 
 echo "Preparing docs directory for GitHub Pages..."
 rm -rf docs
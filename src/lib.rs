@@ -1,10 +1,12 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::f32::consts::PI;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use wasm_bindgen::prelude::*;
 use web_sys::{
-    CanvasRenderingContext2d, HtmlCanvasElement, WebGlBuffer, WebGlProgram,
-    WebGlRenderingContext as GL, WebGlShader, WebGlTexture,
+    CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement, WebGlBuffer, WebGlFramebuffer,
+    WebGlProgram, WebGlRenderbuffer, WebGlRenderingContext as GL, WebGlShader, WebGlTexture,
+    WebGlUniformLocation,
 };
 
 // Math types
@@ -20,8 +22,12 @@ impl Vec3 {
         Self { x, y, z }
     }
 
+    fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
     fn normalize(self) -> Self {
-        let len = (self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        let len = self.length();
         if len > 0.0 {
             Self {
                 x: self.x / len,
@@ -52,6 +58,213 @@ impl Vec3 {
             z: self.z - other.z,
         }
     }
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+
+    fn scale(self, s: f32) -> Self {
+        Self {
+            x: self.x * s,
+            y: self.y * s,
+            z: self.z * s,
+        }
+    }
+
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self.add(other.sub(self).scale(t))
+    }
+
+    // Clamp each component to [0, 1], e.g. for colors
+    fn clamp01(self) -> Self {
+        Self {
+            x: self.x.clamp(0.0, 1.0),
+            y: self.y.clamp(0.0, 1.0),
+            z: self.z.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl std::ops::Add for Vec3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Vec3::add(self, other)
+    }
+}
+
+impl std::ops::Sub for Vec3 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Vec3::sub(self, other)
+    }
+}
+
+impl std::ops::Mul<f32> for Vec3 {
+    type Output = Self;
+
+    fn mul(self, s: f32) -> Self {
+        self.scale(s)
+    }
+}
+
+impl std::ops::Neg for Vec3 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.scale(-1.0)
+    }
+}
+
+// Homogeneous point or direction, for clip-space math
+#[derive(Clone, Copy)]
+struct Vec4 {
+    x: f32,
+    y: f32,
+    z: f32,
+    w: f32,
+}
+
+impl Vec4 {
+    // w = 1 for points, 0 for directions
+    fn from_vec3(v: Vec3, w: f32) -> Self {
+        Self {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+            w,
+        }
+    }
+
+    // Clip space to normalized device coordinates; None at w = 0
+    fn perspective_divide(self) -> Option<Vec3> {
+        (self.w != 0.0).then(|| Vec3::new(self.x / self.w, self.y / self.w, self.z / self.w))
+    }
+}
+
+// Unit quaternion orientation; interpolates rotations without gimbal or matrix drift
+#[derive(Clone, Copy)]
+struct Quat {
+    x: f32,
+    y: f32,
+    z: f32,
+    w: f32,
+}
+
+impl Quat {
+    fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let axis = axis.normalize();
+        let (s, c) = (angle / 2.0).sin_cos();
+        Self {
+            x: axis.x * s,
+            y: axis.y * s,
+            z: axis.z * s,
+            w: c,
+        }
+    }
+
+    // Hamilton product: rotates by `other` first, then `self`, like `Mat4::multiply`
+    fn mul(self, other: Self) -> Self {
+        Self {
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+        }
+    }
+
+    fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    fn normalize(self) -> Self {
+        let len = self.dot(self).sqrt();
+        if len > 0.0 {
+            Self {
+                x: self.x / len,
+                y: self.y / len,
+                z: self.z / len,
+                w: self.w / len,
+            }
+        } else {
+            Self {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 1.0,
+            }
+        }
+    }
+
+    // Constant-speed interpolation along the shorter arc; t = 0 gives `self`
+    fn slerp(self, other: Self, t: f32) -> Self {
+        let mut cos = self.dot(other);
+        // q and -q are the same rotation; pick the one on this side
+        let other = if cos < 0.0 {
+            cos = -cos;
+            Self {
+                x: -other.x,
+                y: -other.y,
+                z: -other.z,
+                w: -other.w,
+            }
+        } else {
+            other
+        };
+        let (a, b) = if cos > 0.9995 {
+            // Nearly parallel: the arc is a line, and sin below is ~0
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Self {
+            x: a * self.x + b * other.x,
+            y: a * self.y + b * other.y,
+            z: a * self.z + b * other.z,
+            w: a * self.w + b * other.w,
+        }
+        .normalize()
+    }
+
+    fn rotate(self, v: Vec3) -> Vec3 {
+        let m = self.to_mat4().data;
+        Vec3::new(
+            m[0] * v.x + m[4] * v.y + m[8] * v.z,
+            m[1] * v.x + m[5] * v.y + m[9] * v.z,
+            m[2] * v.x + m[6] * v.y + m[10] * v.z,
+        )
+    }
+
+    fn to_mat4(self) -> Mat4 {
+        let Self { x, y, z, w } = self;
+        Mat4 {
+            data: [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y + z * w),
+                2.0 * (x * z - y * w),
+                0.0,
+                2.0 * (x * y - z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z + x * w),
+                0.0,
+                2.0 * (x * z + y * w),
+                2.0 * (y * z - x * w),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ],
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -59,7 +272,195 @@ struct Mat4 {
     data: [f32; 16],
 }
 
+// `Mat4::inverse` gives up below this |determinant|. An absolute bound: scene matrices
+// have entries near unit scale, where f32 products carry about 1e-7 of rounding.
+const SINGULAR_DETERMINANT: f32 = f32::EPSILON;
+
 impl Mat4 {
+    fn identity() -> Self {
+        Self {
+            data: [
+                1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+            ],
+        }
+    }
+
+    fn translation(x: f32, y: f32, z: f32) -> Self {
+        let mut m = Self::identity();
+        m.data[12] = x;
+        m.data[13] = y;
+        m.data[14] = z;
+        m
+    }
+
+    fn scale(x: f32, y: f32, z: f32) -> Self {
+        let mut m = Self::identity();
+        m.data[0] = x;
+        m.data[5] = y;
+        m.data[10] = z;
+        m
+    }
+
+    fn scale_uniform(s: f32) -> Self {
+        Self::scale(s, s, s)
+    }
+
+    // Column-major product `self * other`: applies `other` first, then `self`
+    fn multiply(self, other: Mat4) -> Mat4 {
+        let a = &self.data;
+        let b = &other.data;
+        let mut data = [0.0; 16];
+        for col in 0..4 {
+            for row in 0..4 {
+                data[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+            }
+        }
+        Mat4 { data }
+    }
+
+    fn transform_vec4(&self, v: Vec4) -> Vec4 {
+        let d = &self.data;
+        Vec4 {
+            x: d[0] * v.x + d[4] * v.y + d[8] * v.z + d[12] * v.w,
+            y: d[1] * v.x + d[5] * v.y + d[9] * v.z + d[13] * v.w,
+            z: d[2] * v.x + d[6] * v.y + d[10] * v.z + d[14] * v.w,
+            w: d[3] * v.x + d[7] * v.y + d[11] * v.z + d[15] * v.w,
+        }
+    }
+
+    // Laplace expansion over the 2x2 minors of the first two and last two columns
+    fn determinant(&self) -> f32 {
+        let m = &self.data;
+        let b00 = m[0] * m[5] - m[1] * m[4];
+        let b01 = m[0] * m[6] - m[2] * m[4];
+        let b02 = m[0] * m[7] - m[3] * m[4];
+        let b03 = m[1] * m[6] - m[2] * m[5];
+        let b04 = m[1] * m[7] - m[3] * m[5];
+        let b05 = m[2] * m[7] - m[3] * m[6];
+        let b06 = m[8] * m[13] - m[9] * m[12];
+        let b07 = m[8] * m[14] - m[10] * m[12];
+        let b08 = m[8] * m[15] - m[11] * m[12];
+        let b09 = m[9] * m[14] - m[10] * m[13];
+        let b10 = m[9] * m[15] - m[11] * m[13];
+        let b11 = m[10] * m[15] - m[11] * m[14];
+        b00 * b11 - b01 * b10 + b02 * b09 + b03 * b08 - b04 * b07 + b05 * b06
+    }
+
+    // Full 4x4 inverse by cofactor expansion; None when |det| < SINGULAR_DETERMINANT
+    fn inverse(&self) -> Option<Mat4> {
+        let det = self.determinant();
+        if det.abs() < SINGULAR_DETERMINANT {
+            return None;
+        }
+        let m = &self.data;
+        let mut inv = [0.0; 16];
+        inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+            + m[9] * m[7] * m[14]
+            + m[13] * m[6] * m[11]
+            - m[13] * m[7] * m[10];
+        inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+            - m[8] * m[7] * m[14]
+            - m[12] * m[6] * m[11]
+            + m[12] * m[7] * m[10];
+        inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+            + m[8] * m[7] * m[13]
+            + m[12] * m[5] * m[11]
+            - m[12] * m[7] * m[9];
+        inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+            - m[8] * m[6] * m[13]
+            - m[12] * m[5] * m[10]
+            + m[12] * m[6] * m[9];
+        inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+            - m[9] * m[3] * m[14]
+            - m[13] * m[2] * m[11]
+            + m[13] * m[3] * m[10];
+        inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+            + m[8] * m[3] * m[14]
+            + m[12] * m[2] * m[11]
+            - m[12] * m[3] * m[10];
+        inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+            - m[8] * m[3] * m[13]
+            - m[12] * m[1] * m[11]
+            + m[12] * m[3] * m[9];
+        inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+            + m[8] * m[2] * m[13]
+            + m[12] * m[1] * m[10]
+            - m[12] * m[2] * m[9];
+        inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+            + m[5] * m[3] * m[14]
+            + m[13] * m[2] * m[7]
+            - m[13] * m[3] * m[6];
+        inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+            - m[4] * m[3] * m[14]
+            - m[12] * m[2] * m[7]
+            + m[12] * m[3] * m[6];
+        inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+            + m[4] * m[3] * m[13]
+            + m[12] * m[1] * m[7]
+            - m[12] * m[3] * m[5];
+        inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+            - m[4] * m[2] * m[13]
+            - m[12] * m[1] * m[6]
+            + m[12] * m[2] * m[5];
+        inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+            - m[5] * m[3] * m[10]
+            - m[9] * m[2] * m[7]
+            + m[9] * m[3] * m[6];
+        inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+            + m[4] * m[3] * m[10]
+            + m[8] * m[2] * m[7]
+            - m[8] * m[3] * m[6];
+        inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+            - m[4] * m[3] * m[9]
+            - m[8] * m[1] * m[7]
+            + m[8] * m[3] * m[5];
+        inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+            + m[4] * m[2] * m[9]
+            + m[8] * m[1] * m[6]
+            - m[8] * m[2] * m[5];
+        Some(Mat4 {
+            data: inv.map(|v| v / det),
+        })
+    }
+
+    // Inverse-transpose of the upper 3x3, column-major, for transforming normals.
+    // Equals the upper 3x3 itself for rotations; unlike it, stays correct under
+    // non-uniform scale.
+    fn normal_matrix(&self) -> [f32; 9] {
+        let d = &self.data;
+        let (a00, a10, a20) = (d[0], d[1], d[2]);
+        let (a01, a11, a21) = (d[4], d[5], d[6]);
+        let (a02, a12, a22) = (d[8], d[9], d[10]);
+
+        // Cofactors; the inverse-transpose is the cofactor matrix over the determinant
+        let c00 = a11 * a22 - a12 * a21;
+        let c01 = a12 * a20 - a10 * a22;
+        let c02 = a10 * a21 - a11 * a20;
+        let c10 = a02 * a21 - a01 * a22;
+        let c11 = a00 * a22 - a02 * a20;
+        let c12 = a01 * a20 - a00 * a21;
+        let c20 = a01 * a12 - a02 * a11;
+        let c21 = a02 * a10 - a00 * a12;
+        let c22 = a00 * a11 - a01 * a10;
+
+        let det = a00 * c00 + a01 * c01 + a02 * c02;
+        if det.abs() < 1e-8 {
+            return [a00, a10, a20, a01, a11, a21, a02, a12, a22];
+        }
+        let inv = 1.0 / det;
+        [
+            c00 * inv,
+            c10 * inv,
+            c20 * inv,
+            c01 * inv,
+            c11 * inv,
+            c21 * inv,
+            c02 * inv,
+            c12 * inv,
+            c22 * inv,
+        ]
+    }
+
     fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
         let f = 1.0 / (fov_y / 2.0).tan();
         let nf = 1.0 / (near - far);
@@ -85,6 +486,22 @@ impl Mat4 {
         }
     }
 
+    // Maps the view-space box to the NDC cube; the camera looks down -Z, so `near` and
+    // `far` are distances in front of it
+    fn ortho(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        let rl = 1.0 / (right - left);
+        let tb = 1.0 / (top - bottom);
+        let nf = 1.0 / (near - far);
+        let mut m = Self::identity();
+        m.data[0] = 2.0 * rl;
+        m.data[5] = 2.0 * tb;
+        m.data[10] = 2.0 * nf;
+        m.data[12] = -(right + left) * rl;
+        m.data[13] = -(top + bottom) * tb;
+        m.data[14] = (far + near) * nf;
+        m
+    }
+
     fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
         let z = eye.sub(target).normalize();
         let x = up.cross(z).normalize();
@@ -122,10 +539,63 @@ impl Mat4 {
         }
     }
 
+    fn rotation_x(angle: f32) -> Self {
+        let c = angle.cos();
+        let s = angle.sin();
+        Self {
+            data: [
+                1.0, 0.0, 0.0, 0.0, 0.0, c, s, 0.0, 0.0, -s, c, 0.0, 0.0, 0.0, 0.0, 1.0,
+            ],
+        }
+    }
+
+    fn rotation_z(angle: f32) -> Self {
+        let c = angle.cos();
+        let s = angle.sin();
+        Self {
+            data: [
+                c, s, 0.0, 0.0, -s, c, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+            ],
+        }
+    }
+
+    // Rodrigues' rotation about `axis`, which need not be normalized; counterclockwise
+    // looking down the axis toward the origin, like the single-axis rotations
+    fn rotation_axis(axis: Vec3, angle: f32) -> Self {
+        let k = axis.normalize();
+        let c = angle.cos();
+        let s = angle.sin();
+        let t = 1.0 - c;
+        Self {
+            data: [
+                t * k.x * k.x + c,
+                t * k.x * k.y + s * k.z,
+                t * k.x * k.z - s * k.y,
+                0.0,
+                t * k.x * k.y - s * k.z,
+                t * k.y * k.y + c,
+                t * k.y * k.z + s * k.x,
+                0.0,
+                t * k.x * k.z + s * k.y,
+                t * k.y * k.z - s * k.x,
+                t * k.z * k.z + c,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ],
+        }
+    }
+
     fn billboard(position: Vec3, camera_pos: Vec3, scale: f32) -> Self {
+        Self::billboard_with_up(position, camera_pos, Vec3::new(0.0, 1.0, 0.0), scale)
+    }
+
+    // Billboard that keeps its up axis as close as possible to `up_hint`
+    fn billboard_with_up(position: Vec3, camera_pos: Vec3, up_hint: Vec3, scale: f32) -> Self {
         let forward = camera_pos.sub(position).normalize();
-        let world_up = Vec3::new(0.0, 1.0, 0.0);
-        let right = world_up.cross(forward).normalize();
+        let right = up_hint.cross(forward).normalize();
         let up = forward.cross(right);
 
         Self {
@@ -152,14 +622,30 @@ impl Mat4 {
 }
 
 // Sphere geometry
+// UVs map longitude to u and latitude to v, top of the image at the north pole. The
+// seam column is duplicated (u = 0 and u = 1), and each pole vertex takes the u of its
+// segment's middle so the fan of pole triangles doesn't shear the texture.
+// u16 indices need (lat + 1) * (lon + 1) <= MAX_U16_VERTICES; see `generate_sphere_u32`.
 fn generate_sphere(
     radius: f32,
     lat_segments: u32,
     lon_segments: u32,
-) -> (Vec<f32>, Vec<f32>, Vec<u16>) {
+) -> (Vec<f32>, Vec<f32>, Vec<u16>, Vec<f32>) {
+    let (vertices, normals, indices, uvs) = generate_sphere_u32(radius, lat_segments, lon_segments);
+    let indices = indices.into_iter().map(|i| i as u16).collect();
+    (vertices, normals, indices, uvs)
+}
+
+// The same sphere with 32-bit indices, for tessellations past the u16 limit
+fn generate_sphere_u32(
+    radius: f32,
+    lat_segments: u32,
+    lon_segments: u32,
+) -> (Vec<f32>, Vec<f32>, Vec<u32>, Vec<f32>) {
     let mut vertices = Vec::new();
     let mut normals = Vec::new();
     let mut indices = Vec::new();
+    let mut uvs = Vec::new();
 
     for lat in 0..=lat_segments {
         let theta = lat as f32 * PI / lat_segments as f32;
@@ -177,6 +663,14 @@ fn generate_sphere(
 
             vertices.extend_from_slice(&[x * radius, y * radius, z * radius]);
             normals.extend_from_slice(&[x, y, z]);
+
+            let pole = lat == 0 || lat == lat_segments;
+            let u = if pole && lon < lon_segments {
+                (lon as f32 + 0.5) / lon_segments as f32
+            } else {
+                lon as f32 / lon_segments as f32
+            };
+            uvs.extend_from_slice(&[u, lat as f32 / lat_segments as f32]);
         }
     }
 
@@ -185,484 +679,8711 @@ fn generate_sphere(
             let first = lat * (lon_segments + 1) + lon;
             let second = first + lon_segments + 1;
 
-            indices.extend_from_slice(&[
-                first as u16,
-                second as u16,
-                (first + 1) as u16,
-                second as u16,
-                (second + 1) as u16,
-                (first + 1) as u16,
-            ]);
+            indices.extend_from_slice(&[first, second, first + 1, second, second + 1, first + 1]);
         }
     }
 
-    (vertices, normals, indices)
+    (vertices, normals, indices, uvs)
 }
 
-// Textured quad for billboards
-fn generate_quad() -> (Vec<f32>, Vec<f32>, Vec<u16>) {
-    let vertices = vec![
-        -0.5, -0.5, 0.0, 0.5, -0.5, 0.0, 0.5, 0.5, 0.0, -0.5, 0.5, 0.0,
+// Subdivided icosahedron: evenly sized triangles instead of the UV sphere's crowded
+// poles. Wound clockwise from outside, like `generate_sphere`.
+fn generate_icosphere(radius: f32, subdivisions: u32) -> (Vec<f32>, Vec<f32>, Vec<u16>) {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut points: Vec<Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|&(x, y, z)| Vec3::new(x, y, z).normalize())
+    .collect();
+
+    // Listed counter-clockwise from outside; emitted reversed below
+    let faces: [[u16; 3]; 20] = [
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
     ];
-    let uvs = vec![0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0];
-    let indices = vec![0, 1, 2, 0, 2, 3];
-    (vertices, uvs, indices)
+    let mut triangles: Vec<[u16; 3]> = faces.iter().map(|&[a, b, c]| [a, c, b]).collect();
+
+    for _ in 0..subdivisions {
+        // Edges are shared by two triangles; reuse their midpoint
+        let mut midpoints: HashMap<(u16, u16), u16> = HashMap::new();
+        let mut midpoint = |a: u16, b: u16| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let mid = points[a as usize].add(points[b as usize]).normalize();
+                points.push(mid);
+                (points.len() - 1) as u16
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let vertices = points
+        .iter()
+        .flat_map(|p| [p.x * radius, p.y * radius, p.z * radius])
+        .collect();
+    let normals = points.iter().flat_map(|p| [p.x, p.y, p.z]).collect();
+    let indices = triangles.into_iter().flatten().collect();
+    (vertices, normals, indices)
 }
 
-// Create text texture using Canvas 2D with color
-fn create_text_texture(
-    gl: &GL,
-    document: &web_sys::Document,
-    letter: &str,
-    color: &str,
-) -> Result<WebGlTexture, String> {
-    let canvas = document
-        .create_element("canvas")
-        .map_err(|_| "Failed to create canvas")?
-        .dyn_into::<HtmlCanvasElement>()
-        .map_err(|_| "Failed to cast to canvas")?;
+// 6 subdivisions (40962 vertices) is the most that fits u16 indices
+const MAX_ICOSPHERE_SUBDIVISIONS: u32 = 6;
 
-    let size = 128u32;
-    canvas.set_width(size);
-    canvas.set_height(size);
+// Flat disc in the XY plane facing +Z (and, via `add_back_face`, -Z)
+fn generate_disc(radius: f32, segments: u32) -> (Vec<f32>, Vec<f32>, Vec<u16>) {
+    let mut vertices = vec![0.0, 0.0, 0.0];
+    let mut normals = vec![0.0, 0.0, 1.0];
+    let mut indices = Vec::new();
 
-    let ctx = canvas
-        .get_context("2d")
-        .map_err(|_| "Failed to get 2d context")?
-        .ok_or("No 2d context")?
-        .dyn_into::<CanvasRenderingContext2d>()
-        .map_err(|_| "Failed to cast to 2d context")?;
+    for i in 0..=segments {
+        let angle = i as f32 * 2.0 * PI / segments as f32;
+        vertices.extend_from_slice(&[radius * angle.cos(), radius * angle.sin(), 0.0]);
+        normals.extend_from_slice(&[0.0, 0.0, 1.0]);
+    }
 
-    // Clear with transparent background
-    ctx.clear_rect(0.0, 0.0, size as f64, size as f64);
+    // Clockwise seen from the normal side, matching the sphere
+    for i in 1..=segments {
+        indices.extend_from_slice(&[0, (i + 1) as u16, i as u16]);
+    }
 
-    // Draw text with color
-    ctx.set_font("bold 90px 'Outfit', sans-serif");
-    ctx.set_fill_style_str(color);
-    ctx.set_text_align("center");
-    ctx.set_text_baseline("middle");
-    ctx.fill_text(letter, size as f64 / 2.0, size as f64 / 2.0)
-        .map_err(|_| "Failed to draw text")?;
+    add_back_face(&mut vertices, &mut normals, &mut indices);
+    (vertices, normals, indices)
+}
 
-    // Create WebGL texture
-    let texture = gl.create_texture().ok_or("Failed to create texture")?;
-    gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+// Flat annulus in the XY plane facing +Z (and -Z)
+fn generate_ring(inner: f32, outer: f32, segments: u32) -> (Vec<f32>, Vec<f32>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::new();
 
-    gl.tex_image_2d_with_u32_and_u32_and_canvas(
-        GL::TEXTURE_2D,
-        0,
-        GL::RGBA as i32,
-        GL::RGBA,
-        GL::UNSIGNED_BYTE,
-        &canvas,
-    )
-    .map_err(|_| "Failed to upload texture")?;
+    for i in 0..=segments {
+        let angle = i as f32 * 2.0 * PI / segments as f32;
+        let (sin, cos) = angle.sin_cos();
+        vertices.extend_from_slice(&[inner * cos, inner * sin, 0.0]);
+        vertices.extend_from_slice(&[outer * cos, outer * sin, 0.0]);
+        normals.extend_from_slice(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+    }
 
-    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
-    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
-    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
-    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+    for i in 0..segments {
+        let inner_a = (2 * i) as u16;
+        let outer_a = inner_a + 1;
+        let inner_b = inner_a + 2;
+        let outer_b = inner_a + 3;
+        indices.extend_from_slice(&[inner_a, outer_b, outer_a, inner_a, inner_b, outer_b]);
+    }
 
-    Ok(texture)
+    add_back_face(&mut vertices, &mut normals, &mut indices);
+    (vertices, normals, indices)
 }
 
-// Orbiting letter with texture
-struct OrbitingLetter {
-    texture: WebGlTexture,
-    radius: f32,
-    inclination: f32,
-    phase: f32,
-    angular_velocity: f32,
+// Duplicate a flat mesh with flipped normals and winding so it is lit from both sides
+fn add_back_face(vertices: &mut Vec<f32>, normals: &mut Vec<f32>, indices: &mut Vec<u16>) {
+    let offset = (vertices.len() / 3) as u16;
+    vertices.extend_from_within(..);
+    let flipped: Vec<f32> = normals.iter().map(|n| -n).collect();
+    normals.extend(flipped);
+    let reversed: Vec<u16> = indices
+        .chunks(3)
+        .flat_map(|tri| [tri[0] + offset, tri[2] + offset, tri[1] + offset])
+        .collect();
+    indices.extend(reversed);
 }
 
-impl OrbitingLetter {
-    fn position_at(&self, time: f32) -> Vec3 {
-        let angle = self.phase + self.angular_velocity * time;
-        let x = self.radius * angle.cos();
-        let z = self.radius * angle.sin();
-        let y = z * self.inclination.sin();
-        let z = z * self.inclination.cos();
-        Vec3::new(x, y, z)
-    }
+// Shape drawn at the center of the scene
+#[derive(Clone, Copy)]
+enum CentralMesh {
+    Sphere,
+    Icosphere {
+        subdivisions: u32,
+    },
+    Disc {
+        radius: f32,
+        segments: u32,
+    },
+    Ring {
+        inner: f32,
+        outer: f32,
+        segments: u32,
+    },
 }
 
-// Shaders
-const SPHERE_VERTEX_SHADER: &str = r#"
-    attribute vec3 a_position;
-    attribute vec3 a_normal;
-    uniform mat4 u_model;
-    uniform mat4 u_view;
-    uniform mat4 u_projection;
-    varying vec3 v_normal;
-    varying vec3 v_position;
-    void main() {
-        vec4 worldPos = u_model * vec4(a_position, 1.0);
-        v_position = worldPos.xyz;
-        v_normal = mat3(u_model) * a_normal;
-        gl_Position = u_projection * u_view * worldPos;
-    }
-"#;
+const SPHERE_LAT_SEGMENTS: u32 = 32;
+const SPHERE_LON_SEGMENTS: u32 = 64;
 
+// Segment counts for auto tessellation: 32x64 at radius 1, scaling with sqrt(radius)
+// so facet size on screen stays roughly constant, clamped to 12..=128 latitude
+// segments (longitude is always twice latitude, well within u16 indices).
+fn auto_sphere_segments(radius: f32) -> (u32, u32) {
+    let lat = (SPHERE_LAT_SEGMENTS as f32 * radius.max(0.0).sqrt()).round() as u32;
+    let lat = lat.clamp(12, 128);
+    (lat, lat * 2)
+}
+
+// Most vertices a mesh can have and still be addressed by u16 indices
+const MAX_U16_VERTICES: u32 = u16::MAX as u32 + 1;
+// Cap for spheres with 32-bit indices: about a million vertices, 32 MB of attributes
+const MAX_U32_SPHERE_VERTICES: u32 = 1 << 20;
+
+// Index data for the central mesh; only a sphere past the u16 limit needs u32
+enum MeshIndices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl MeshIndices {
+    fn len(&self) -> usize {
+        match self {
+            Self::U16(indices) => indices.len(),
+            Self::U32(indices) => indices.len(),
+        }
+    }
+
+    fn get(&self, i: usize) -> usize {
+        match self {
+            Self::U16(indices) => indices[i] as usize,
+            Self::U32(indices) => indices[i] as usize,
+        }
+    }
+}
+
+// Faceted copy of an indexed mesh: three unshared vertices per triangle, all carrying
+// the face normal, turned to agree with the smooth normals so winding doesn't matter.
+// Indices come out sequential, in u16 when they fit.
+fn flatten_mesh(
+    vertices: &[f32],
+    normals: &[f32],
+    indices: &MeshIndices,
+    uvs: Option<&[f32]>,
+) -> (Vec<f32>, Vec<f32>, MeshIndices, Option<Vec<f32>>) {
+    let vec3 = |data: &[f32], i: usize| Vec3::new(data[i * 3], data[i * 3 + 1], data[i * 3 + 2]);
+    let count = indices.len() / 3 * 3;
+    let mut flat_vertices = Vec::with_capacity(count * 3);
+    let mut flat_normals = Vec::with_capacity(count * 3);
+    let mut flat_uvs = uvs.map(|_| Vec::with_capacity(count * 2));
+    for triangle in 0..count / 3 {
+        let corners = [0, 1, 2].map(|k| indices.get(triangle * 3 + k));
+        let [a, b, c] = corners.map(|i| vec3(vertices, i));
+        let smooth = corners.iter().fold(Vec3::new(0.0, 0.0, 0.0), |sum, &i| {
+            sum.add(vec3(normals, i))
+        });
+        let mut normal = b.sub(a).cross(c.sub(a)).normalize();
+        if normal.dot(smooth) < 0.0 {
+            normal = normal.scale(-1.0);
+        }
+        for (&i, position) in corners.iter().zip([a, b, c]) {
+            flat_vertices.extend_from_slice(&[position.x, position.y, position.z]);
+            flat_normals.extend_from_slice(&[normal.x, normal.y, normal.z]);
+            if let (Some(flat_uvs), Some(uvs)) = (&mut flat_uvs, uvs) {
+                flat_uvs.extend_from_slice(&uvs[i * 2..i * 2 + 2]);
+            }
+        }
+    }
+    let flat_indices = if count as u32 <= MAX_U16_VERTICES {
+        MeshIndices::U16((0..count).map(|i| i as u16).collect())
+    } else {
+        MeshIndices::U32((0..count as u32).collect())
+    };
+    (flat_vertices, flat_normals, flat_indices, flat_uvs)
+}
+
+// Vertices, normals, indices and (sphere only) UVs for the central mesh; the (lat, lon)
+// segment counts only apply to the sphere
+fn central_mesh_geometry(
+    mesh: CentralMesh,
+    radius: f32,
+    (lat, lon): (u32, u32),
+    shading: Shading,
+) -> (Vec<f32>, Vec<f32>, MeshIndices, Option<Vec<f32>>) {
+    let (verts, normals, indices, uvs) = match mesh {
+        CentralMesh::Sphere => {
+            if (lat + 1) * (lon + 1) > MAX_U16_VERTICES {
+                let (verts, normals, indices, uvs) = generate_sphere_u32(radius, lat, lon);
+                (verts, normals, MeshIndices::U32(indices), Some(uvs))
+            } else {
+                let (verts, normals, indices, uvs) = generate_sphere(radius, lat, lon);
+                (verts, normals, MeshIndices::U16(indices), Some(uvs))
+            }
+        }
+        CentralMesh::Icosphere { subdivisions } => {
+            let (verts, normals, indices) = generate_icosphere(radius, subdivisions);
+            (verts, normals, MeshIndices::U16(indices), None)
+        }
+        CentralMesh::Disc { radius, segments } => {
+            let (verts, normals, indices) = generate_disc(radius, segments);
+            (verts, normals, MeshIndices::U16(indices), None)
+        }
+        CentralMesh::Ring {
+            inner,
+            outer,
+            segments,
+        } => {
+            let (verts, normals, indices) = generate_ring(inner, outer, segments);
+            (verts, normals, MeshIndices::U16(indices), None)
+        }
+    };
+    match shading {
+        Shading::Smooth => (verts, normals, indices, uvs),
+        Shading::Flat => flatten_mesh(&verts, &normals, &indices, uvs.as_deref()),
+    }
+}
+
+// Keep explicit segment counts usable: at least 3 each way (anything less is not a
+// solid) and few enough that the (lat + 1) * (lon + 1) vertices fit in `max_vertices`,
+// trimming longitude first.
+fn clamp_sphere_segments(lat: u32, lon: u32, max_vertices: u32) -> (u32, u32) {
+    let lat = lat.clamp(3, max_vertices / 4 - 1);
+    let lon = lon.clamp(3, max_vertices / (lat + 1) - 1);
+    (lat, lon)
+}
+
+// Textured quad for billboards
+fn generate_quad() -> (Vec<f32>, Vec<f32>, Vec<u16>) {
+    let vertices = vec![
+        -0.5, -0.5, 0.0, 0.5, -0.5, 0.0, 0.5, 0.5, 0.0, -0.5, 0.5, 0.0,
+    ];
+    let uvs = vec![0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0];
+    let indices = vec![0, 1, 2, 0, 2, 3];
+    (vertices, uvs, indices)
+}
+
+const LETTER_FONT: &str = "bold 90px 'Outfit', sans-serif";
+
+// CSS font shorthand for glyph textures. A lone family name is quoted and given a
+// generic fallback; a list (anything with a comma or quote) is used as written.
+fn css_font(family: &str, weight: &str, size_px: u32) -> String {
+    let family = family.trim();
+    let family = if family.is_empty() {
+        "sans-serif".to_string()
+    } else if family.contains([',', '\'', '"']) {
+        family.to_string()
+    } else {
+        format!("'{family}', sans-serif")
+    };
+    let size = size_px.clamp(1, GLYPH_CELL);
+    match weight.trim() {
+        "" => format!("{size}px {family}"),
+        weight => format!("{weight} {size}px {family}"),
+    }
+}
+
+// Glyphs are laid out in a 128px cell; extra canvas area (e.g. for shadows) grows the quad
+const GLYPH_CELL: u32 = 128;
+
+// Widest word label texture; longer words are drawn with a smaller font
+const MAX_LABEL_WIDTH: u32 = 1024;
+
+// Largest shadow blur and offset, in glyph canvas pixels. Together with the outline
+// and padding caps they keep `GlyphStyle::margin` within 448, so a cell plus a margin
+// on each side always fits MAX_LABEL_WIDTH.
+const MAX_SHADOW_BLUR: f32 = 80.0;
+const MAX_SHADOW_OFFSET: f32 = 80.0;
+// Widest glyph outline, in glyph canvas pixels
+const MAX_OUTLINE_WIDTH: f32 = 160.0;
+
+// Canvas (width, height) for a label `text_width` pixels wide with `margin` pixels on
+// each side, and the scale its text is drawn at. Single glyphs fit the square cell;
+// wider labels widen the canvas up to MAX_LABEL_WIDTH and shrink to fit beyond that.
+fn glyph_canvas_size(text_width: f32, margin: u32) -> (u32, u32, f32) {
+    let margin = 2 * margin;
+    let size = GLYPH_CELL + margin;
+    let needed = text_width.ceil() as u32 + margin;
+    let width = needed.clamp(size, MAX_LABEL_WIDTH);
+    let shrink = ((MAX_LABEL_WIDTH - margin) as f32 / text_width).min(1.0);
+    (width, size, shrink)
+}
+
+// Soft drop shadow baked into glyph textures
+#[derive(Clone)]
+struct GlyphShadow {
+    color: String,
+    blur: f32,
+    offset_x: f32,
+    offset_y: f32,
+}
+
+// Stroke traced around each glyph under its fill, for contrast over bright areas
+#[derive(Clone)]
+struct GlyphOutline {
+    color: String,
+    // Visible width outside the fill; the stroke itself is twice this, half hidden
+    width: f32,
+}
+
+// Rasterization options shared by all glyph textures
+#[derive(Clone)]
+struct GlyphStyle {
+    font: String,
+    shadow: Option<GlyphShadow>,
+    outline: Option<GlyphOutline>,
+    // Transparent inset around the cell so linear filtering never samples the canvas edge
+    padding: u32,
+}
+
+impl Default for GlyphStyle {
+    fn default() -> Self {
+        Self {
+            font: LETTER_FONT.to_string(),
+            shadow: None,
+            outline: None,
+            padding: 4,
+        }
+    }
+}
+
+impl GlyphStyle {
+    // Identifies textures rasterized with equivalent options
+    fn cache_key(&self) -> String {
+        let mut key = format!("{}|pad {}", self.font, self.padding);
+        if let Some(shadow) = &self.shadow {
+            key += &format!(
+                "|shadow {} {} {} {}",
+                shadow.color, shadow.blur, shadow.offset_x, shadow.offset_y
+            );
+        }
+        if let Some(outline) = &self.outline {
+            key += &format!("|outline {} {}", outline.color, outline.width);
+        }
+        key
+    }
+
+    // Extra pixels on each side of the cell so effects aren't clipped
+    fn margin(&self) -> u32 {
+        let shadow = match &self.shadow {
+            Some(shadow) => (shadow.blur.max(0.0)
+                + shadow.offset_x.abs().max(shadow.offset_y.abs()))
+            .ceil() as u32,
+            None => 0,
+        };
+        let outline = self
+            .outline
+            .as_ref()
+            .map_or(0, |outline| outline.width.max(0.0).ceil() as u32);
+        shadow + outline + self.padding
+    }
+}
+
+// Glyph texture plus its canvas size relative to the base cell
+struct GlyphTexture {
+    texture: WebGlTexture,
+    extent: f32,
+    // Width over height; image sprites only learn theirs once loaded
+    aspect: Cell<f32>,
+    // Text labels keep the glyph height and widen with the aspect; images fit the square
+    widen: bool,
+    // Canvas size of rasterized text, which the glyph atlas packs; images and sprites
+    // keep drawing from their own texture
+    size: Option<(u32, u32)>,
+}
+
+// Create text texture using Canvas 2D with color
+fn create_text_texture(
+    gl: &GL,
+    document: &web_sys::Document,
+    letter: &str,
+    color: &str,
+    style: &GlyphStyle,
+    anisotropy: Option<f32>,
+) -> Result<GlyphTexture, RenderError> {
+    let canvas = document
+        .create_element("canvas")
+        .map_err(|_| RenderError::CanvasCreation("Failed to create canvas".into()))?
+        .dyn_into::<HtmlCanvasElement>()
+        .map_err(|_| RenderError::CanvasCreation("Failed to cast to canvas".into()))?;
+
+    let ctx = canvas
+        .get_context("2d")
+        .map_err(|_| RenderError::CanvasCreation("Failed to get 2d context".into()))?
+        .ok_or_else(|| RenderError::CanvasCreation("No 2d context".into()))?
+        .dyn_into::<CanvasRenderingContext2d>()
+        .map_err(|_| RenderError::CanvasCreation("Failed to cast to 2d context".into()))?;
+
+    ctx.set_font(&style.font);
+    let text_width = ctx
+        .measure_text(letter)
+        .map_err(|_| RenderError::CanvasCreation("Failed to measure text".into()))?
+        .width() as f32;
+    let (width, size, shrink) = glyph_canvas_size(text_width, style.margin());
+
+    // Resizing resets the context state, so style it afterwards
+    canvas.set_width(width);
+    canvas.set_height(size);
+
+    // Clear with transparent background
+    ctx.clear_rect(0.0, 0.0, width as f64, size as f64);
+
+    if let Some(shadow) = &style.shadow {
+        ctx.set_shadow_color(&shadow.color);
+        ctx.set_shadow_blur(shadow.blur as f64);
+        ctx.set_shadow_offset_x(shadow.offset_x as f64);
+        ctx.set_shadow_offset_y(shadow.offset_y as f64);
+    }
+
+    // Draw text with color
+    ctx.set_font(&style.font);
+    ctx.set_fill_style_str(color);
+    ctx.set_text_align("center");
+    ctx.set_text_baseline("middle");
+    ctx.translate(width as f64 / 2.0, size as f64 / 2.0)
+        .map_err(|_| RenderError::CanvasCreation("Failed to scale canvas".into()))?;
+    ctx.scale(shrink as f64, shrink as f64)
+        .map_err(|_| RenderError::CanvasCreation("Failed to scale canvas".into()))?;
+    if let Some(outline) = &style.outline {
+        ctx.set_stroke_style_str(&outline.color);
+        ctx.set_line_width(2.0 * outline.width as f64);
+        ctx.set_line_join("round");
+        ctx.stroke_text(letter, 0.0, 0.0)
+            .map_err(|_| RenderError::CanvasCreation("Failed to draw text outline".into()))?;
+    }
+    ctx.fill_text(letter, 0.0, 0.0)
+        .map_err(|_| RenderError::CanvasCreation("Failed to draw text".into()))?;
+
+    // Create WebGL texture
+    let texture = gl
+        .create_texture()
+        .ok_or_else(|| RenderError::ContextLost("Failed to create texture".into()))?;
+    gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+
+    gl.tex_image_2d_with_u32_and_u32_and_canvas(
+        GL::TEXTURE_2D,
+        0,
+        GL::RGBA as i32,
+        GL::RGBA,
+        GL::UNSIGNED_BYTE,
+        &canvas,
+    )
+    .map_err(|_| RenderError::TextureUpload("Failed to upload texture".into()))?;
+
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+    set_minification(gl, width, size, anisotropy);
+
+    Ok(GlyphTexture {
+        texture,
+        extent: size as f32 / GLYPH_CELL as f32,
+        aspect: Cell::new(width as f32 / size as f32),
+        widen: true,
+        size: Some((width, size)),
+    })
+}
+
+// Trilinear filtering for the bound texture, so receding glyphs don't shimmer. WebGL 1
+// only mipmaps power-of-two sizes, which padding and wide labels usually break; those
+// keep plain linear filtering there. The transparent margin keeps coarse levels from
+// bleeding neighbors in. Anisotropic filtering, where available, sharpens glyphs seen
+// edge-on on top of that; the two together look best.
+fn set_minification(gl: &GL, width: u32, height: u32, anisotropy: Option<f32>) {
+    if let Some(anisotropy) = anisotropy {
+        gl.tex_parameterf(GL::TEXTURE_2D, TEXTURE_MAX_ANISOTROPY_EXT, anisotropy);
+    }
+    let webgl2 = gl.is_instance_of::<web_sys::WebGl2RenderingContext>();
+    if webgl2 || (width.is_power_of_two() && height.is_power_of_two()) {
+        gl.generate_mipmap(GL::TEXTURE_2D);
+        gl.tex_parameteri(
+            GL::TEXTURE_2D,
+            GL::TEXTURE_MIN_FILTER,
+            GL::LINEAR_MIPMAP_LINEAR as i32,
+        );
+    } else {
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+    }
+}
+
+const TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FF;
+
+// Highest anisotropy the GPU supports, or None without EXT_texture_filter_anisotropic
+fn max_anisotropy(gl: &GL) -> Option<f32> {
+    gl.get_extension("EXT_texture_filter_anisotropic").ok()??;
+    let max = gl
+        .get_parameter(MAX_TEXTURE_MAX_ANISOTROPY_EXT)
+        .ok()?
+        .as_f64()?;
+    (max > 1.0).then_some(max as f32)
+}
+
+// Sprite texture filled in from an image URL once it loads; until then it is a single
+// transparent texel, so the sprite orbits invisibly
+fn load_image_texture(gl: &GL, url: &str) -> Result<Rc<GlyphTexture>, RenderError> {
+    let texture = gl
+        .create_texture()
+        .ok_or_else(|| RenderError::ContextLost("Failed to create texture".into()))?;
+    gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+    gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+        GL::TEXTURE_2D,
+        0,
+        GL::RGBA as i32,
+        1,
+        1,
+        0,
+        GL::RGBA,
+        GL::UNSIGNED_BYTE,
+        Some(&[0, 0, 0, 0]),
+    )
+    .map_err(|_| RenderError::TextureUpload("Failed to upload placeholder texture".into()))?;
+    // Images are rarely power-of-two sized, which WebGL 1 only samples with these settings
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+
+    let sprite = Rc::new(GlyphTexture {
+        texture,
+        extent: 1.0,
+        aspect: Cell::new(1.0),
+        widen: false,
+        size: None,
+    });
+
+    let image = HtmlImageElement::new()
+        .map_err(|_| RenderError::TextureUpload("Failed to create image".into()))?;
+    // Lets CORS-enabled images from other origins be uploaded without tainting
+    image.set_cross_origin(Some("anonymous"));
+
+    let onload = {
+        let gl = gl.clone();
+        let sprite = sprite.clone();
+        let image = image.clone();
+        let url = url.to_string();
+        Closure::once_into_js(move || {
+            gl.bind_texture(GL::TEXTURE_2D, Some(&sprite.texture));
+            let uploaded = gl.tex_image_2d_with_u32_and_u32_and_image(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA as i32,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                &image,
+            );
+            if let Err(err) = uploaded {
+                let message = format!("Failed to upload sprite image {url}:");
+                web_sys::console::error_2(&message.into(), &err);
+                return;
+            }
+            let (width, height) = (image.natural_width(), image.natural_height());
+            if width > 0 && height > 0 {
+                sprite.aspect.set(width as f32 / height as f32);
+            }
+        })
+    };
+    let onerror = {
+        let url = url.to_string();
+        Closure::once_into_js(move || {
+            web_sys::console::error_1(&format!("Failed to load sprite image {url}").into());
+        })
+    };
+    image.set_onload(Some(onload.unchecked_ref()));
+    image.set_onerror(Some(onerror.unchecked_ref()));
+    image.set_src(url);
+
+    Ok(sprite)
+}
+
+// What a free-standing sprite shows: a loaded image, or a flat square of a CSS hex color
+enum SpriteSource<'a> {
+    Image(&'a HtmlImageElement),
+    Color(&'a str),
+}
+
+// Billboard at a fixed world position, drawn and depth sorted with the letters
+struct Sprite {
+    texture: GlyphTexture,
+    position: Vec3,
+    // World units tall, like the 0.6 of a letter cell
+    scale: f32,
+    // Shown when level of detail reduces the sprite to a dot
+    color: String,
+}
+
+fn create_sprite_texture(gl: &GL, source: &SpriteSource) -> Result<GlyphTexture, RenderError> {
+    let texture = gl
+        .create_texture()
+        .ok_or_else(|| RenderError::ContextLost("Failed to create texture".into()))?;
+    gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+    let (uploaded, aspect) = match source {
+        SpriteSource::Image(image) => {
+            let (width, height) = (image.natural_width(), image.natural_height());
+            let aspect = if width > 0 && height > 0 {
+                width as f32 / height as f32
+            } else {
+                1.0
+            };
+            let uploaded = gl.tex_image_2d_with_u32_and_u32_and_image(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA as i32,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                image,
+            );
+            (uploaded, aspect)
+        }
+        SpriteSource::Color(color) => {
+            let Some([r, g, b]) = parse_hex_color(color) else {
+                gl.delete_texture(Some(&texture));
+                return Err(RenderError::InvalidArgument(format!(
+                    "Invalid sprite color {color}"
+                )));
+            };
+            let texel = [r, g, b].map(|c| (c * 255.0).round() as u8);
+            let uploaded = gl
+                .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                    GL::TEXTURE_2D,
+                    0,
+                    GL::RGBA as i32,
+                    1,
+                    1,
+                    0,
+                    GL::RGBA,
+                    GL::UNSIGNED_BYTE,
+                    Some(&[texel[0], texel[1], texel[2], 255]),
+                );
+            (uploaded, 1.0)
+        }
+    };
+    if uploaded.is_err() {
+        gl.delete_texture(Some(&texture));
+        return Err(RenderError::TextureUpload(
+            "Failed to upload sprite texture".into(),
+        ));
+    }
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+
+    Ok(GlyphTexture {
+        texture,
+        extent: 1.0,
+        aspect: Cell::new(aspect),
+        widen: false,
+        size: None,
+    })
+}
+
+// (glyph, color, style) a glyph texture is shared under
+type GlyphKey = (String, String, String);
+
+fn glyph_key(glyph: &str, color: &str, style: &GlyphStyle) -> GlyphKey {
+    (glyph.to_string(), color.to_string(), style.cache_key())
+}
+
+// Glyph textures shared by every letter with the same (glyph, color, style).
+// Letters hold an Rc, so a texture is only deleted once nothing references it.
+// Repeated characters only share when they also share a color: the built-in palette
+// gives each position its own color, so set a shorter palette (`set_colors`) to share.
+#[derive(Default)]
+struct TextureCache {
+    textures: HashMap<GlyphKey, Rc<GlyphTexture>>,
+    // Image sprites keyed by URL
+    images: HashMap<String, Rc<GlyphTexture>>,
+    // Max anisotropy applied to glyph textures, looked up once; None without the extension
+    anisotropy: Option<f32>,
+}
+
+impl TextureCache {
+    fn get_or_create(
+        &mut self,
+        gl: &GL,
+        document: &web_sys::Document,
+        glyph: &str,
+        color: &str,
+        style: &GlyphStyle,
+    ) -> Result<Rc<GlyphTexture>, RenderError> {
+        let key = glyph_key(glyph, color, style);
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(texture.clone());
+        }
+        let texture = Rc::new(create_text_texture(
+            gl,
+            document,
+            glyph,
+            color,
+            style,
+            self.anisotropy,
+        )?);
+        self.textures.insert(key, texture.clone());
+        Ok(texture)
+    }
+
+    fn get_or_load_image(&mut self, gl: &GL, url: &str) -> Result<Rc<GlyphTexture>, RenderError> {
+        if let Some(texture) = self.images.get(url) {
+            return Ok(texture.clone());
+        }
+        let texture = load_image_texture(gl, url)?;
+        self.images.insert(url.to_string(), texture.clone());
+        Ok(texture)
+    }
+
+    // Drop every glyph texture rasterized with `style_key`, so the next lookup redraws
+    // it; the caller deletes the returned textures once letters stop using them
+    fn take_style(&mut self, style_key: &str) -> Vec<Rc<GlyphTexture>> {
+        let keys: Vec<_> = self
+            .textures
+            .keys()
+            .filter(|(_, _, style)| style == style_key)
+            .cloned()
+            .collect();
+        keys.iter()
+            .filter_map(|key| self.textures.remove(key))
+            .collect()
+    }
+
+    // Delete textures no longer used by any letter
+    fn purge(&mut self, gl: &GL) {
+        let keep = |glyph: &Rc<GlyphTexture>| {
+            let in_use = Rc::strong_count(glyph) > 1;
+            if !in_use {
+                gl.delete_texture(Some(&glyph.texture));
+            }
+            in_use
+        };
+        self.textures.retain(|_, glyph| keep(glyph));
+        self.images.retain(|_, glyph| keep(glyph));
+    }
+}
+
+// Distance along the ray from `origin` in unit direction `dir` to where it crosses the
+// unit quad placed by `model`, if it does
+fn ray_hits_quad(model: &Mat4, origin: Vec3, dir: Vec3) -> Option<f32> {
+    let d = &model.data;
+    let right = Vec3::new(d[0], d[1], d[2]);
+    let up = Vec3::new(d[4], d[5], d[6]);
+    let center = Vec3::new(d[12], d[13], d[14]);
+    let normal = right.cross(up);
+    let facing = normal.dot(dir);
+    if facing.abs() < 1e-6 {
+        return None;
+    }
+    let distance = normal.dot(center.sub(origin)) / facing;
+    if distance < 0.0 {
+        return None;
+    }
+    let local = origin.add(dir.scale(distance)).sub(center);
+    let u = local.dot(right) / right.dot(right);
+    let v = local.dot(up) / up.dot(up);
+    (u.abs() <= 0.5 && v.abs() <= 0.5).then_some(distance)
+}
+
+// Orbiting letter with texture
+#[derive(Clone)]
+struct OrbitingLetter {
+    glyph: String,
+    color: String,
+    texture: Rc<GlyphTexture>,
+    radius: f32,
+    inclination: f32,
+    phase: f32,
+    angular_velocity: f32,
+    // Orbit ellipse with `radius` as semi-major axis along X; 0 is a circle
+    eccentricity: f32,
+    // Shift toward (+) or away from (-) the camera, applied after the orbit
+    z_offset: f32,
+    // Shows an image instead of `glyph`; color only tints its LOD dot
+    sprite: bool,
+    // Inclination comes from the ring-wide distribution rather than an explicit value
+    inherits_inclination: bool,
+}
+
+// Orbit parameters from JS must be finite; NaN would reach the depth sort
+fn check_orbit_value(name: &str, value: f32) -> Result<(), RenderError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(RenderError::InvalidArgument(format!(
+            "Letter {name} must be finite, got {value}"
+        )))
+    }
+}
+
+fn check_orbit_radius(radius: f32) -> Result<(), RenderError> {
+    if radius.is_finite() && radius >= 0.0 {
+        Ok(())
+    } else {
+        Err(RenderError::InvalidArgument(format!(
+            "Letter radius must be non-negative, got {radius}"
+        )))
+    }
+}
+
+// Flattening beyond this collapses the orbit onto a line
+fn clamp_eccentricity(eccentricity: f32) -> f32 {
+    eccentricity.clamp(0.0, 0.99)
+}
+
+// Re-tilt the letters that follow the distribution; explicit inclinations are kept
+fn apply_inclination_distribution(letters: &mut [OrbitingLetter], base: f32, variation: f32) {
+    for letter in letters.iter_mut().filter(|l| l.inherits_inclination) {
+        letter.inclination = base + variation * letter.phase.sin();
+    }
+}
+
+// Flat [radius, inclination, phase, angular_velocity] per letter
+fn orbit_params(letters: &[OrbitingLetter]) -> Vec<f32> {
+    letters
+        .iter()
+        .flat_map(|l| [l.radius, l.inclination, l.phase, l.angular_velocity])
+        .collect()
+}
+
+// Inverse of `orbit_params`. Every value is checked before any letter changes, and only
+// a changed inclination detaches a letter from the inclination distribution, so writing
+// back what `orbit_params` returned leaves the scene as it was.
+fn apply_orbit_params(letters: &mut [OrbitingLetter], data: &[f32]) -> Result<(), RenderError> {
+    let expected = letters.len() * ORBIT_PARAM_STRIDE;
+    if data.len() != expected {
+        return Err(RenderError::InvalidArgument(format!(
+            "Orbit params length {} does not match {} letters ({expected} floats)",
+            data.len(),
+            letters.len()
+        )));
+    }
+    for params in data.chunks(ORBIT_PARAM_STRIDE) {
+        check_orbit_radius(params[0])?;
+        check_orbit_value("inclination", params[1])?;
+        check_orbit_value("phase", params[2])?;
+        check_orbit_value("angular velocity", params[3])?;
+    }
+    for (letter, params) in letters.iter_mut().zip(data.chunks(ORBIT_PARAM_STRIDE)) {
+        letter.radius = params[0];
+        if letter.inclination != params[1] {
+            letter.inclination = params[1];
+            letter.inherits_inclination = false;
+        }
+        letter.phase = params[2];
+        letter.angular_velocity = params[3];
+    }
+    Ok(())
+}
+
+// Default tilt of the letter ring's orbital plane, in radians
+const LETTER_INCLINATION: f32 = 0.12;
+
+// Floats per letter in the bulk orbit parameter arrays
+const ORBIT_PARAM_STRIDE: usize = 4;
+
+// Per-letter overrides for `WasmSphere::set_letter`; unset fields are left unchanged
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Default)]
+pub struct LetterParams {
+    pub radius: Option<f32>,
+    pub inclination: Option<f32>,
+    pub angular_velocity: Option<f32>,
+    pub phase: Option<f32>,
+    pub eccentricity: Option<f32>,
+    pub color: Option<String>,
+    pub z_offset: Option<f32>,
+}
+
+#[wasm_bindgen]
+impl LetterParams {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// Built-in palette, cycled through by rings that don't bring their own colors
+const LETTER_COLORS: [&str; 18] = [
+    "#FF6B6B", // Red
+    "#4ECDC4", // Teal
+    "#45B7D1", // Blue
+    "#96CEB4", // Green
+    "#FFEAA7", // Yellow
+    "#DDA0DD", // Plum
+    "#98D8C8", // Mint
+    "#F7DC6F", // Gold
+    "#BB8FCE", // Purple
+    "#85C1E9", // Light blue
+    "#F8B500", // Orange
+    "#00CED1", // Dark cyan
+    "#FF69B4", // Hot pink
+    "#7FFF00", // Chartreuse
+    "#FFB6C1", // Light pink
+    "#40E0D0", // Turquoise
+    "#FF6347", // Tomato
+    "#9370DB", // Medium purple
+];
+
+// A user palette with blank entries replaced by the built-in color for their slot
+fn resolve_palette(colors: Vec<String>) -> Vec<String> {
+    colors
+        .into_iter()
+        .enumerate()
+        .map(|(i, color)| {
+            let color = color.trim();
+            if color.is_empty() {
+                LETTER_COLORS[i % LETTER_COLORS.len()].to_string()
+            } else {
+                color.to_string()
+            }
+        })
+        .collect()
+}
+
+// New color for each letter when recoloring with `palette`: letters take it in order,
+// cycling, while sprites (None) keep theirs and don't use up a color
+fn assign_colors<'a>(palette: &[&'a str], sprites: &[bool]) -> Vec<Option<&'a str>> {
+    let mut colors = palette.iter().cycle();
+    sprites
+        .iter()
+        .map(|&sprite| if sprite { None } else { colors.next().copied() })
+        .collect()
+}
+
+// One ring of text for `WasmSphere::set_rings`; unset fields use the built-in defaults
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Default)]
+pub struct RingConfig {
+    pub text: String,
+    pub radius: Option<f32>,
+    pub inclination: Option<f32>,
+    // Angular velocity in radians per second; negative rings orbit the other way
+    pub speed: Option<f32>,
+    pub colors: Option<Vec<String>>,
+}
+
+#[wasm_bindgen]
+impl RingConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            ..Self::default()
+        }
+    }
+}
+
+// One letter for `WasmSphere::set_letters`; unset fields match the built-in ring.
+// Orbits are circles around the origin in the XZ plane (Y up), tilted about the X axis
+// by `inclination` radians so positive values lift the +Z (camera-facing) side. Phase
+// and angles are in radians from +X toward +Z; velocity is radians per second.
+// A nonzero `eccentricity` (below 1) makes the orbit an ellipse centered on the
+// origin, `radius` along X and `radius * sqrt(1 - e^2)` along Z.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Default)]
+pub struct OrbitingLetterConfig {
+    pub text: String,
+    pub color: Option<String>,
+    pub radius: Option<f32>,
+    pub inclination: Option<f32>,
+    pub phase: Option<f32>,
+    pub angular_velocity: Option<f32>,
+    pub eccentricity: Option<f32>,
+}
+
+#[wasm_bindgen]
+impl OrbitingLetterConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            ..Self::default()
+        }
+    }
+}
+
+// Letters of one ring, evenly spaced starting at the top of the orbit. Rings without
+// an inclination take `default_inclination(phase)` per letter.
+fn build_ring(
+    gl: &GL,
+    document: &web_sys::Document,
+    cache: &mut TextureCache,
+    style: &GlyphStyle,
+    ring: &RingConfig,
+    label_mode: LabelMode,
+    default_inclination: impl Fn(f32) -> f32,
+) -> Result<Vec<OrbitingLetter>, RenderError> {
+    let colors: Vec<&str> = match &ring.colors {
+        Some(colors) if !colors.is_empty() => colors.iter().map(String::as_str).collect(),
+        _ => LETTER_COLORS.to_vec(),
+    };
+    let labels: Vec<String> = match label_mode {
+        LabelMode::Glyphs => ring.text.chars().map(String::from).collect(),
+        LabelMode::Words => ring.text.split_whitespace().map(String::from).collect(),
+    };
+    let configs: Vec<OrbitingLetterConfig> = labels
+        .into_iter()
+        .enumerate()
+        .map(|(i, text)| OrbitingLetterConfig {
+            text,
+            color: Some(colors[i % colors.len()].to_string()),
+            radius: ring.radius,
+            inclination: ring.inclination,
+            phase: None,
+            angular_velocity: ring.speed,
+            eccentricity: None,
+        })
+        .collect();
+    build_letters(gl, document, cache, style, &configs, default_inclination)
+}
+
+// Letters from per-letter configs; unset phases space the letters evenly from the top
+fn build_letters(
+    gl: &GL,
+    document: &web_sys::Document,
+    cache: &mut TextureCache,
+    style: &GlyphStyle,
+    configs: &[OrbitingLetterConfig],
+    default_inclination: impl Fn(f32) -> f32,
+) -> Result<Vec<OrbitingLetter>, RenderError> {
+    let count = configs.len();
+    configs
+        .iter()
+        .enumerate()
+        .map(|(i, config)| {
+            let phase = config
+                .phase
+                .unwrap_or(-PI / 2.0 - (i as f32 * 2.0 * PI / count as f32));
+            let color = config
+                .color
+                .clone()
+                .unwrap_or_else(|| LETTER_COLORS[i % LETTER_COLORS.len()].to_string());
+            let texture = cache.get_or_create(gl, document, &config.text, &color, style)?;
+            Ok(OrbitingLetter {
+                glyph: config.text.clone(),
+                color,
+                texture,
+                radius: config.radius.unwrap_or(2.2),
+                inclination: config
+                    .inclination
+                    .unwrap_or_else(|| default_inclination(phase)),
+                phase,
+                angular_velocity: config.angular_velocity.unwrap_or(0.3),
+                eccentricity: config.eccentricity.map_or(0.0, clamp_eccentricity),
+                z_offset: 0.0,
+                sprite: false,
+                inherits_inclination: config.inclination.is_none(),
+            })
+        })
+        .collect()
+}
+
+// Ring-wide modulations applied on top of each letter's own orbit
+#[derive(Clone, Copy, Default)]
+struct OrbitEffects {
+    // Breathing eccentricity: stretches x and squashes z by amplitude * sin(time * rate)
+    eccentricity_amplitude: f32,
+    eccentricity_rate: f32,
+    // Resonance: angle shift of amplitude * sin(clusters * phase) * sin(2pi * time / period)
+    resonance_amplitude: f32,
+    resonance_period: f32,
+    // Radius pulse: blends every orbit radius toward `pulse_radius` by `pulse_level` (0-1)
+    pulse_radius: f32,
+    pulse_level: f32,
+    // Bounce: every `bounce_interval` seconds each letter drops up to `bounce_height`
+    // toward the sphere and bounces back, never deeper than `bounce_floor` from the center
+    bounce_interval: f32,
+    bounce_height: f32,
+    bounce_floor: f32,
+    // Starting pose: turns every orbit about Y, matching the sphere's `initial_rotation`
+    rotation_offset: f32,
+}
+
+// Longest a single drop-and-bounce takes, in seconds
+const BOUNCE_DURATION: f32 = 1.2;
+// Share of a bounce spent falling in; the rest is the bouncing return
+const BOUNCE_DROP: f32 = 0.2;
+
+impl OrbitEffects {
+    // How far inward a letter with `phase` currently is; staggered around the ring
+    fn bounce_drop(&self, time: f32, phase: f32) -> f32 {
+        if self.bounce_height <= 0.0 || self.bounce_interval <= 0.0 {
+            return 0.0;
+        }
+        let stagger = phase.rem_euclid(2.0 * PI) / (2.0 * PI) * self.bounce_interval;
+        let local = (time + stagger).rem_euclid(self.bounce_interval);
+        let u = local / BOUNCE_DURATION.min(self.bounce_interval);
+        if u >= 1.0 {
+            0.0
+        } else if u < BOUNCE_DROP {
+            let fall = u / BOUNCE_DROP;
+            self.bounce_height * fall * fall
+        } else {
+            self.bounce_height * (1.0 - ease_out_bounce((u - BOUNCE_DROP) / (1.0 - BOUNCE_DROP)))
+        }
+    }
+}
+
+// Number of groups letters gather into at the peak of the resonance
+const RESONANCE_CLUSTERS: f32 = 4.0;
+
+impl OrbitingLetter {
+    fn position_at(&self, time: f32, effects: &OrbitEffects) -> Vec3 {
+        let mut angle = self.phase + self.angular_velocity * time;
+        if effects.resonance_amplitude != 0.0 && effects.resonance_period > 0.0 {
+            // Keyed on the fixed phase so the clusters travel with the ring
+            let pulse = (2.0 * PI * time / effects.resonance_period).sin();
+            angle -= effects.resonance_amplitude * (RESONANCE_CLUSTERS * self.phase).sin() * pulse;
+        }
+        let point = self.orbit_point(angle, time, effects);
+
+        // Bounces move straight toward the center, stopping short of the surface
+        let drop = effects.bounce_drop(time, self.phase);
+        let length = point.length();
+        // A letter at the center has no inward direction to move along
+        if drop > 0.0 && length > 0.0 {
+            let floor = effects.bounce_floor.min(length);
+            return point.scale((length - drop).max(floor) / length);
+        }
+        point
+    }
+
+    // Point on this letter's orbit at `angle`
+    fn orbit_point(&self, angle: f32, time: f32, effects: &OrbitEffects) -> Vec3 {
+        let stretch = effects.eccentricity_amplitude * (time * effects.eccentricity_rate).sin();
+        let radius = self.radius + (effects.pulse_radius - self.radius) * effects.pulse_level;
+        // rotation_y turns the sphere the opposite way to increasing orbit angles
+        let angle = angle - effects.rotation_offset;
+        let semi_minor = radius * (1.0 - self.eccentricity * self.eccentricity).sqrt();
+        let x = radius * (1.0 + stretch) * angle.cos();
+        let z = semi_minor * (1.0 - stretch) * angle.sin();
+        let y = z * self.inclination.sin();
+        let z = z * self.inclination.cos();
+        Vec3::new(x, y, z)
+    }
+}
+
+// How the text is drawn around the sphere
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TextMode {
+    // One camera-facing billboard per glyph
+    Glyphs,
+    // The whole string on one texture wrapped around a curved band
+    Ribbon,
+}
+
+// How `set_text` and rings split their text into billboards
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LabelMode {
+    // One billboard per character
+    Glyphs,
+    // One billboard per whitespace-separated word, sized to its width
+    Words,
+}
+
+const RIBBON_SEGMENTS: u32 = 128;
+const RIBBON_HEIGHT: f32 = 0.6;
+
+// Text ribbon: one wide texture on a band that follows the first letter's orbit
+struct Ribbon {
+    texture: WebGlTexture,
+    position_buffer: WebGlBuffer,
+    uv_buffer: WebGlBuffer,
+    index_buffer: WebGlBuffer,
+    index_count: i32,
+}
+
+// Rasterize the letters side by side, one square cell each, so glyph i is centered
+// at u = (i + 0.5) / n just like the evenly spaced billboards
+fn create_ribbon_texture(
+    gl: &GL,
+    document: &web_sys::Document,
+    letters: &[OrbitingLetter],
+    style: &GlyphStyle,
+) -> Result<WebGlTexture, RenderError> {
+    let canvas = document
+        .create_element("canvas")
+        .map_err(|_| RenderError::CanvasCreation("Failed to create canvas".into()))?
+        .dyn_into::<HtmlCanvasElement>()
+        .map_err(|_| RenderError::CanvasCreation("Failed to cast to canvas".into()))?;
+
+    // Stay within the texture size every WebGL implementation supports
+    let count = letters.len().max(1) as u32;
+    let cell = GLYPH_CELL.min(4096 / count).max(1);
+    canvas.set_width(cell * count);
+    canvas.set_height(cell);
+
+    let ctx = canvas
+        .get_context("2d")
+        .map_err(|_| RenderError::CanvasCreation("Failed to get 2d context".into()))?
+        .ok_or_else(|| RenderError::CanvasCreation("No 2d context".into()))?
+        .dyn_into::<CanvasRenderingContext2d>()
+        .map_err(|_| RenderError::CanvasCreation("Failed to cast to 2d context".into()))?;
+
+    ctx.clear_rect(0.0, 0.0, (cell * count) as f64, cell as f64);
+
+    // Lay out in base-cell units so the font size matches the billboards
+    let scale = cell as f64 / GLYPH_CELL as f64;
+    ctx.scale(scale, scale)
+        .map_err(|_| RenderError::CanvasCreation("Failed to scale canvas".into()))?;
+    ctx.set_font(&style.font);
+    ctx.set_text_align("center");
+    ctx.set_text_baseline("middle");
+    let half_cell = GLYPH_CELL as f64 / 2.0;
+    for (i, letter) in letters.iter().enumerate() {
+        ctx.set_fill_style_str(&letter.color);
+        let x = (i as f64 * 2.0 + 1.0) * half_cell;
+        ctx.fill_text(&letter.glyph, x, half_cell)
+            .map_err(|_| RenderError::CanvasCreation("Failed to draw text".into()))?;
+    }
+
+    let texture = gl
+        .create_texture()
+        .ok_or_else(|| RenderError::ContextLost("Failed to create texture".into()))?;
+    gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+    gl.tex_image_2d_with_u32_and_u32_and_canvas(
+        GL::TEXTURE_2D,
+        0,
+        GL::RGBA as i32,
+        GL::RGBA,
+        GL::UNSIGNED_BYTE,
+        &canvas,
+    )
+    .map_err(|_| RenderError::TextureUpload("Failed to upload texture".into()))?;
+
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+    gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+
+    Ok(texture)
+}
+
+// UVs and indices for a closed band of `segments` quads; positions come from `ribbon_positions`
+fn generate_ribbon_topology(segments: u32) -> (Vec<f32>, Vec<u16>) {
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+
+    for i in 0..=segments {
+        let u = i as f32 / segments as f32;
+        uvs.extend_from_slice(&[u, 1.0, u, 0.0]);
+    }
+
+    for i in 0..segments {
+        let bottom = (2 * i) as u16;
+        indices.extend_from_slice(&[
+            bottom,
+            bottom + 2,
+            bottom + 3,
+            bottom,
+            bottom + 3,
+            bottom + 1,
+        ]);
+    }
+
+    (uvs, indices)
+}
+
+// Band vertices (bottom, top per step) along `letter`'s orbit. Text runs toward
+// decreasing orbit angle, the same order the billboards are laid out in.
+fn ribbon_positions(
+    letter: &OrbitingLetter,
+    count: usize,
+    time: f32,
+    effects: &OrbitEffects,
+    segments: u32,
+) -> Vec<f32> {
+    let spacing = 2.0 * PI / count.max(1) as f32;
+    let start = letter.phase + letter.angular_velocity * time + spacing / 2.0;
+    // Normal of the inclined orbital plane, so the band stands upright on it
+    let normal = Vec3::new(0.0, letter.inclination.cos(), -letter.inclination.sin());
+    let half = RIBBON_HEIGHT / 2.0;
+
+    let mut positions = Vec::with_capacity((segments as usize + 1) * 6);
+    for i in 0..=segments {
+        let u = i as f32 / segments as f32;
+        let p = letter.orbit_point(start - 2.0 * PI * u, time, effects);
+        positions.extend_from_slice(&[
+            p.x - normal.x * half,
+            p.y - normal.y * half,
+            p.z - normal.z * half,
+            p.x + normal.x * half,
+            p.y + normal.y * half,
+            p.z + normal.z * half,
+        ]);
+    }
+    positions
+}
+
+// Shaders
+const SPHERE_VERTEX_SHADER: &str = r#"
+    attribute vec3 a_position;
+    attribute vec3 a_normal;
+    attribute vec2 a_uv;
+    uniform mat4 u_model;
+    uniform mat4 u_view;
+    uniform mat4 u_projection;
+    uniform mat3 u_normalMatrix;
+    varying vec3 v_normal;
+    varying vec3 v_position;
+    varying vec2 v_uv;
+    varying float v_viewDepth;
+    void main() {
+        vec4 worldPos = u_model * vec4(a_position, 1.0);
+        vec4 viewPos = u_view * worldPos;
+        v_position = worldPos.xyz;
+        v_uv = a_uv;
+        v_normal = u_normalMatrix * a_normal;
+        v_viewDepth = -viewPos.z;
+        gl_Position = u_projection * viewPos;
+    }
+"#;
+
+// Compiled through `sphere_fragment_shader`, which defines SPHERE_LIGHTS
 const SPHERE_FRAGMENT_SHADER: &str = r#"
     precision mediump float;
     varying vec3 v_normal;
     varying vec3 v_position;
-    uniform vec3 u_lightPos;
-    uniform vec3 u_color;
+    uniform vec3 u_lightPos[SPHERE_LIGHTS];
+    uniform vec3 u_lightColor[SPHERE_LIGHTS];
+    uniform vec3 u_colorPole;
+    uniform vec3 u_colorEquator;
     uniform vec3 u_viewPos;
+    uniform float u_edgeFade;
+    uniform float u_ambient;
+    uniform float u_diffuseStrength;
+    uniform float u_specularStrength;
+    uniform float u_shininess;
+    uniform sampler2D u_texture;
+    // 1 samples u_texture for the base color, 0 uses the gradient
+    uniform float u_textured;
+    varying vec2 v_uv;
+    // Color approaches u_fogColor as exp(-density * view depth) falls; 0 density is off
+    uniform vec3 u_fogColor;
+    uniform float u_fogDensity;
+    varying float v_viewDepth;
+    // 1 lights in linear space: sRGB inputs are decoded and the result re-encoded
+    uniform float u_srgb;
+    vec3 toLinear(vec3 c) {
+        return mix(c, pow(c, vec3(2.2)), u_srgb);
+    }
     void main() {
         vec3 normal = normalize(v_normal);
-        vec3 lightDir = normalize(u_lightPos - v_position);
         vec3 viewDir = normalize(u_viewPos - v_position);
-        vec3 halfDir = normalize(lightDir + viewDir);
-        float ambient = 0.15;
-        float diff = max(dot(normal, lightDir), 0.0);
-        float spec = pow(max(dot(normal, halfDir), 0.0), 32.0);
-        vec3 color = (ambient + diff * 0.7 + spec * 0.3) * u_color;
-        gl_FragColor = vec4(color, 1.0);
+        vec3 lighting = vec3(u_ambient);
+        for (int i = 0; i < SPHERE_LIGHTS; i++) {
+            vec3 lightDir = normalize(u_lightPos[i] - v_position);
+            vec3 halfDir = normalize(lightDir + viewDir);
+            float diff = max(dot(normal, lightDir), 0.0);
+            float spec = pow(max(dot(normal, halfDir), 0.0), u_shininess);
+            lighting += (diff * u_diffuseStrength + spec * u_specularStrength) * u_lightColor[i];
+        }
+        vec3 gradient = mix(toLinear(u_colorEquator), toLinear(u_colorPole), abs(normal.y));
+        vec3 baseColor = mix(gradient, toLinear(texture2D(u_texture, v_uv).rgb), u_textured);
+        vec3 color = lighting * baseColor;
+        color = mix(color, pow(color, vec3(1.0 / 2.2)), u_srgb);
+        float grazing = 1.0 - max(dot(normal, viewDir), 0.0);
+        float alpha = 1.0 - u_edgeFade * grazing * grazing;
+        color = mix(u_fogColor, color, exp(-u_fogDensity * v_viewDepth));
+        gl_FragColor = vec4(color, alpha);
+    }
+"#;
+
+// The sphere shader with its light arrays sized from the Rust constant
+fn sphere_fragment_shader() -> String {
+    format!("#define SPHERE_LIGHTS {SPHERE_LIGHTS}\n{SPHERE_FRAGMENT_SHADER}")
+}
+
+const TEXT_VERTEX_SHADER: &str = r#"
+    attribute vec3 a_position;
+    attribute vec2 a_uv;
+    uniform mat4 u_model;
+    uniform mat4 u_view;
+    uniform mat4 u_projection;
+    varying vec2 v_uv;
+    varying float v_viewDepth;
+    void main() {
+        vec4 viewPos = u_view * u_model * vec4(a_position, 1.0);
+        v_uv = a_uv;
+        v_viewDepth = -viewPos.z;
+        gl_Position = u_projection * viewPos;
+    }
+"#;
+
+const TEXT_FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    varying vec2 v_uv;
+    uniform sampler2D u_texture;
+    uniform float u_alphaThreshold;
+    uniform float u_opacity;
+    uniform vec3 u_fogColor;
+    uniform float u_fogDensity;
+    varying float v_viewDepth;
+    void main() {
+        vec4 texColor = texture2D(u_texture, v_uv);
+        texColor.a *= u_opacity;
+        // Discard first so fog never fills in a glyph's transparent margin
+        if (texColor.a < u_alphaThreshold) discard;
+        float fog = exp(-u_fogDensity * v_viewDepth);
+        gl_FragColor = vec4(mix(u_fogColor, texColor.rgb, fog), texColor.a);
+    }
+"#;
+
+// Text billboards drawn many per call, with the model matrix, opacity and glyph UV rect
+// per instance
+const TEXT_INSTANCED_VERTEX_SHADER: &str = r#"
+    attribute vec3 a_position;
+    attribute vec2 a_uv;
+    attribute vec4 a_model0;
+    attribute vec4 a_model1;
+    attribute vec4 a_model2;
+    attribute vec4 a_model3;
+    attribute float a_opacity;
+    // Where the glyph sits in the sampled texture: (left, top, width, height) in UVs
+    attribute vec4 a_uvRect;
+    uniform mat4 u_view;
+    uniform mat4 u_projection;
+    varying vec2 v_uv;
+    varying float v_opacity;
+    varying float v_viewDepth;
+    void main() {
+        mat4 model = mat4(a_model0, a_model1, a_model2, a_model3);
+        vec4 viewPos = u_view * model * vec4(a_position, 1.0);
+        v_uv = a_uvRect.xy + a_uv * a_uvRect.zw;
+        v_opacity = a_opacity;
+        v_viewDepth = -viewPos.z;
+        gl_Position = u_projection * viewPos;
+    }
+"#;
+
+const TEXT_INSTANCED_FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    varying vec2 v_uv;
+    varying float v_opacity;
+    uniform sampler2D u_texture;
+    uniform float u_alphaThreshold;
+    uniform vec3 u_fogColor;
+    uniform float u_fogDensity;
+    varying float v_viewDepth;
+    void main() {
+        vec4 texColor = texture2D(u_texture, v_uv);
+        texColor.a *= v_opacity;
+        if (texColor.a < u_alphaThreshold) discard;
+        float fog = exp(-u_fogDensity * v_viewDepth);
+        gl_FragColor = vec4(mix(u_fogColor, texColor.rgb, fog), texColor.a);
+    }
+"#;
+
+// Round colored dots standing in for letters too small to read
+const DOT_VERTEX_SHADER: &str = r#"
+    attribute vec3 a_position;
+    attribute vec3 a_color;
+    attribute float a_size;
+    uniform mat4 u_view;
+    uniform mat4 u_projection;
+    varying vec3 v_color;
+    varying float v_viewDepth;
+    void main() {
+        vec4 viewPos = u_view * vec4(a_position, 1.0);
+        v_color = a_color;
+        v_viewDepth = -viewPos.z;
+        gl_Position = u_projection * viewPos;
+        gl_PointSize = a_size;
+    }
+"#;
+
+const DOT_FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    varying vec3 v_color;
+    uniform vec3 u_fogColor;
+    uniform float u_fogDensity;
+    varying float v_viewDepth;
+    void main() {
+        vec2 d = gl_PointCoord - vec2(0.5);
+        if (dot(d, d) > 0.25) discard;
+        float fog = exp(-u_fogDensity * v_viewDepth);
+        gl_FragColor = vec4(mix(u_fogColor, v_color, fog), 1.0);
+    }
+"#;
+
+// Colored debug lines
+const LINE_VERTEX_SHADER: &str = r#"
+    attribute vec3 a_position;
+    attribute vec3 a_color;
+    uniform mat4 u_view;
+    uniform mat4 u_projection;
+    varying vec3 v_color;
+    void main() {
+        v_color = a_color;
+        gl_Position = u_projection * u_view * vec4(a_position, 1.0);
+    }
+"#;
+
+const LINE_FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    varying vec3 v_color;
+    void main() {
+        gl_FragColor = vec4(v_color, 1.0);
+    }
+"#;
+
+// Stars sit at infinity: w = 0 drops the view translation, so they only turn with the
+// camera and never get closer
+const STAR_VERTEX_SHADER: &str = r#"
+    attribute vec3 a_position;
+    attribute float a_brightness;
+    uniform mat4 u_view;
+    uniform mat4 u_projection;
+    uniform float u_pointScale;
+    varying float v_brightness;
+    void main() {
+        vec4 viewPos = u_view * vec4(a_position, 0.0);
+        v_brightness = a_brightness;
+        gl_Position = u_projection * vec4(viewPos.xyz, 1.0);
+        gl_PointSize = u_pointScale * (1.0 + a_brightness);
+    }
+"#;
+
+const STAR_FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    varying float v_brightness;
+    void main() {
+        vec2 d = gl_PointCoord - vec2(0.5);
+        if (dot(d, d) > 0.25) discard;
+        gl_FragColor = vec4(vec3(v_brightness), 1.0);
+    }
+"#;
+
+// Fullscreen pass over the unit quad
+const FULLSCREEN_VERTEX_SHADER: &str = r#"
+    attribute vec3 a_position;
+    varying vec2 v_ndc;
+    void main() {
+        v_ndc = a_position.xy * 2.0;
+        gl_Position = vec4(v_ndc, 0.0, 1.0);
+    }
+"#;
+
+// Outputs a darkening factor multiplied into the frame
+const VIGNETTE_FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    varying vec2 v_ndc;
+    uniform float u_strength;
+    uniform float u_radius;
+    void main() {
+        // 0 at the center, 1 in the corners
+        float d = length(v_ndc) * 0.7071;
+        float falloff = smoothstep(u_radius, 1.0, d);
+        gl_FragColor = vec4(vec3(1.0 - u_strength * falloff), 1.0);
+    }
+"#;
+
+// Keeps the part of each pixel brighter than the threshold, preserving its hue
+const BLOOM_BRIGHT_FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    uniform sampler2D u_color;
+    uniform vec2 u_resolution;
+    uniform float u_threshold;
+    void main() {
+        vec4 color = texture2D(u_color, gl_FragCoord.xy / u_resolution);
+        float brightness = max(color.r, max(color.g, color.b));
+        float excess = max(brightness - u_threshold, 0.0);
+        gl_FragColor = vec4(color.rgb * (excess / max(brightness, 0.0001)), 1.0);
+    }
+"#;
+
+// One axis of a 9-tap Gaussian; `u_direction` is the tap spacing in pixels
+const BLOOM_BLUR_FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    uniform sampler2D u_color;
+    uniform vec2 u_resolution;
+    uniform vec2 u_direction;
+    void main() {
+        vec2 uv = gl_FragCoord.xy / u_resolution;
+        vec2 step = u_direction / u_resolution;
+        vec3 sum = texture2D(u_color, uv).rgb * 0.227027;
+        sum += (texture2D(u_color, uv + step).rgb + texture2D(u_color, uv - step).rgb) * 0.1945946;
+        sum += (texture2D(u_color, uv + step * 2.0).rgb + texture2D(u_color, uv - step * 2.0).rgb) * 0.1216216;
+        sum += (texture2D(u_color, uv + step * 3.0).rgb + texture2D(u_color, uv - step * 3.0).rgb) * 0.054054;
+        sum += (texture2D(u_color, uv + step * 4.0).rgb + texture2D(u_color, uv - step * 4.0).rgb) * 0.016216;
+        gl_FragColor = vec4(sum, 1.0);
+    }
+"#;
+
+// Adds the blurred glow onto the scene. Output stays premultiplied, so glow over a
+// transparent background raises alpha with it.
+const BLOOM_COMPOSITE_FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    uniform sampler2D u_color;
+    uniform sampler2D u_bloom;
+    uniform vec2 u_resolution;
+    uniform float u_intensity;
+    void main() {
+        vec2 uv = gl_FragCoord.xy / u_resolution;
+        vec4 scene = texture2D(u_color, uv);
+        vec3 color = min(scene.rgb + texture2D(u_bloom, uv).rgb * u_intensity, 1.0);
+        gl_FragColor = vec4(color, max(scene.a, max(color.r, max(color.g, color.b))));
+    }
+"#;
+
+// Samples the offscreen scene, blurring each pixel by how far its depth is from focus
+const DOF_FRAGMENT_SHADER: &str = r#"
+    precision highp float;
+    uniform sampler2D u_color;
+    uniform sampler2D u_depth;
+    uniform vec2 u_resolution;
+    uniform float u_near;
+    uniform float u_far;
+    uniform float u_focus;
+    uniform float u_strength;
+    const float MAX_BLUR_PX = 8.0;
+    float linearDepth(vec2 uv) {
+        float z = texture2D(u_depth, uv).r * 2.0 - 1.0;
+        return 2.0 * u_near * u_far / (u_far + u_near - z * (u_far - u_near));
+    }
+    void main() {
+        vec2 uv = gl_FragCoord.xy / u_resolution;
+        float coc = clamp(abs(linearDepth(uv) - u_focus) * u_strength, 0.0, 1.0);
+        vec2 radius = coc * MAX_BLUR_PX / u_resolution;
+        vec4 sum = texture2D(u_color, uv);
+        float weight = 1.0;
+        // Two rings of 8 taps at full and half radius
+        for (int i = 0; i < 8; i++) {
+            float angle = float(i) * 0.7854;
+            vec2 dir = vec2(cos(angle), sin(angle)) * radius;
+            sum += texture2D(u_color, uv + dir) + texture2D(u_color, uv + dir * 0.5);
+            weight += 2.0;
+        }
+        gl_FragColor = sum / weight;
+    }
+"#;
+
+// Which context `main` obtained. A WebGL 2 context is driven through the WebGL 1
+// bindings, which it fully supports, with shaders upgraded to GLSL ES 3.00.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum GlVersion {
+    WebGl1,
+    WebGl2,
+}
+
+// Rewrite a GLSL ES 1.00 shader from this file as GLSL ES 3.00
+fn glsl_es3(shader_type: u32, source: &str) -> String {
+    let mut out = String::from("#version 300 es\n");
+    let body = if shader_type == GL::VERTEX_SHADER {
+        source
+            .replace("attribute ", "in ")
+            .replace("varying ", "out ")
+    } else {
+        out.push_str("out mediump vec4 fragColor;\n");
+        source
+            .replace("varying ", "in ")
+            .replace("gl_FragColor", "fragColor")
+    };
+    out + &body.replace("texture2D(", "texture(")
+}
+
+// Instanced drawing: core in WebGL 2, the ANGLE_instanced_arrays extension on WebGL 1
+enum Instancing {
+    WebGl2(web_sys::WebGl2RenderingContext),
+    Angle(web_sys::AngleInstancedArrays),
+}
+
+impl Instancing {
+    fn detect(gl: &GL, version: GlVersion) -> Option<Self> {
+        match version {
+            GlVersion::WebGl2 => Some(Self::WebGl2(gl.clone().unchecked_into())),
+            GlVersion::WebGl1 => gl
+                .get_extension("ANGLE_instanced_arrays")
+                .ok()
+                .flatten()
+                .map(|ext| Self::Angle(ext.unchecked_into())),
+        }
+    }
+
+    fn divisor(&self, index: u32, divisor: u32) {
+        match self {
+            Self::WebGl2(gl) => gl.vertex_attrib_divisor(index, divisor),
+            Self::Angle(ext) => ext.vertex_attrib_divisor_angle(index, divisor),
+        }
+    }
+
+    fn draw_quads(&self, instances: i32) {
+        match self {
+            Self::WebGl2(gl) => gl.draw_elements_instanced_with_i32(
+                GL::TRIANGLES,
+                6,
+                GL::UNSIGNED_SHORT,
+                0,
+                instances,
+            ),
+            Self::Angle(ext) => ext.draw_elements_instanced_angle_with_i32(
+                GL::TRIANGLES,
+                6,
+                GL::UNSIGNED_SHORT,
+                0,
+                instances,
+            ),
+        }
+    }
+}
+
+// Letter time between trail samples, and opacity of the nearest one
+const TRAIL_SPACING: f32 = 0.06;
+const TRAIL_OPACITY: f32 = 0.5;
+// Samples per trail when trails are switched on without a length
+const DEFAULT_TRAIL_LENGTH: u32 = 8;
+const MAX_TRAIL_LENGTH: u32 = 32;
+
+// (start, length) of each run of neighbors with the same key, in their original order
+fn consecutive_runs<T>(items: &[T], key: impl Fn(&T) -> usize) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        match runs.last_mut() {
+            Some((start, len)) if key(&items[*start]) == key(item) => *len += 1,
+            _ => runs.push((i, 1)),
+        }
+    }
+    runs
+}
+
+// Floats per instance: a column-major model matrix, opacity, then the glyph's UV rect
+const INSTANCE_STRIDE: usize = 21;
+
+// UV rect of a glyph that fills its own texture
+const FULL_UV_RECT: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+// Texels left empty between glyphs in the atlas, so filtering doesn't pull in neighbors
+const GLYPH_ATLAS_GAP: u32 = 4;
+// Largest atlas side, kept under the texture size limit most GPUs report
+const MAX_GLYPH_ATLAS_SIZE: u32 = 4096;
+
+// Width and height, or x and y, in texels
+type Texels = (u32, u32);
+
+// Shelf-pack glyph canvases of `sizes`, in order, into rows of a power-of-two atlas no
+// larger than `max` a side. Returns the atlas size and each glyph's top-left texel, or
+// None for glyphs that didn't fit; those keep drawing from their own texture.
+fn pack_glyph_atlas(sizes: &[Texels], max: u32) -> (Texels, Vec<Option<Texels>>) {
+    let padded = |size: u32| size + GLYPH_ATLAS_GAP;
+    let area: u64 = sizes
+        .iter()
+        .map(|&(w, h)| u64::from(padded(w)) * u64::from(padded(h)))
+        .sum();
+    let widest = sizes.iter().map(|&(w, _)| padded(w)).max().unwrap_or(1);
+    let width = ((area as f64).sqrt().ceil() as u32)
+        .max(widest)
+        .next_power_of_two()
+        .min(max);
+
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    let origins = sizes
+        .iter()
+        .map(|&(w, h)| {
+            let (w, h) = (padded(w), padded(h));
+            if w > width {
+                return None;
+            }
+            if x + w > width {
+                (x, y, row_height) = (0, y + row_height, 0);
+            }
+            if y + h > max {
+                return None;
+            }
+            let origin = (x, y);
+            x += w;
+            row_height = row_height.max(h);
+            Some(origin)
+        })
+        .collect();
+    let height = (y + row_height).max(1).next_power_of_two().min(max);
+    ((width, height), origins)
+}
+
+fn glyph_address(glyph: &GlyphTexture) -> usize {
+    std::ptr::from_ref(glyph) as usize
+}
+
+// Letter glyphs copied into one texture, so the instanced pass draws them all in a
+// single call rather than one per glyph texture. Entries are keyed by glyph texture
+// address; the Weak keeps that allocation alive, so a glyph made later can't take over
+// a stale entry's address.
+struct GlyphAtlas {
+    // None when nothing fit or the copy failed
+    texture: Option<WebGlTexture>,
+    // UV rect of each glyph the atlas was built from, None where it didn't fit
+    rects: HashMap<usize, (Option<[f32; 4]>, Weak<GlyphTexture>)>,
+}
+
+impl GlyphAtlas {
+    // An atlas that covers `glyphs` without holding any of them, so the same set isn't
+    // retried every frame
+    fn empty(glyphs: &[Rc<GlyphTexture>]) -> Self {
+        let rects = glyphs
+            .iter()
+            .map(|glyph| (glyph_address(glyph), (None, Rc::downgrade(glyph))))
+            .collect();
+        Self {
+            texture: None,
+            rects,
+        }
+    }
+
+    // Copy `glyphs` into a fresh atlas on the GPU: each one is attached to a scratch
+    // framebuffer and read into its packed spot
+    fn build(
+        gl: &GL,
+        glyphs: &[Rc<GlyphTexture>],
+        anisotropy: Option<f32>,
+    ) -> Result<Self, RenderError> {
+        let max = gl
+            .get_parameter(GL::MAX_TEXTURE_SIZE)
+            .ok()
+            .and_then(|max| max.as_f64())
+            .map_or(MAX_GLYPH_ATLAS_SIZE, |max| {
+                (max as u32).min(MAX_GLYPH_ATLAS_SIZE)
+            });
+        let sizes: Vec<_> = glyphs
+            .iter()
+            .map(|glyph| glyph.size.unwrap_or_default())
+            .collect();
+        let ((width, height), origins) = pack_glyph_atlas(&sizes, max);
+        let mut atlas = Self::empty(glyphs);
+        if origins.iter().all(Option::is_none) {
+            return Ok(atlas);
+        }
+
+        let texture = gl
+            .create_texture()
+            .ok_or_else(|| RenderError::ContextLost("Failed to create texture".into()))?;
+        let framebuffer = gl.create_framebuffer();
+        let delete = |gl: &GL| {
+            gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+            gl.delete_framebuffer(framebuffer.as_ref());
+            gl.delete_texture(Some(&texture));
+        };
+        gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+        let allocated = gl
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA as i32,
+                width as i32,
+                height as i32,
+                0,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                None,
+            );
+        if allocated.is_err() {
+            delete(gl);
+            return Err(RenderError::TextureUpload(
+                "Failed to allocate glyph atlas".into(),
+            ));
+        }
+        if framebuffer.is_none() {
+            delete(gl);
+            return Err(RenderError::ContextLost(
+                "Failed to create glyph atlas framebuffer".into(),
+            ));
+        }
+
+        gl.bind_framebuffer(GL::FRAMEBUFFER, framebuffer.as_ref());
+        for (glyph, origin) in glyphs.iter().zip(origins) {
+            let (Some((x, y)), Some((w, h))) = (origin, glyph.size) else {
+                continue;
+            };
+            gl.framebuffer_texture_2d(
+                GL::FRAMEBUFFER,
+                GL::COLOR_ATTACHMENT0,
+                GL::TEXTURE_2D,
+                Some(&glyph.texture),
+                0,
+            );
+            let status = gl.check_framebuffer_status(GL::FRAMEBUFFER);
+            if status != GL::FRAMEBUFFER_COMPLETE {
+                delete(gl);
+                return Err(RenderError::Unsupported(format!(
+                    "Glyph atlas framebuffer incomplete (status {status:#x})"
+                )));
+            }
+            gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+            gl.copy_tex_sub_image_2d(
+                GL::TEXTURE_2D,
+                0,
+                x as i32,
+                y as i32,
+                0,
+                0,
+                w as i32,
+                h as i32,
+            );
+            let rect = [
+                x as f32 / width as f32,
+                y as f32 / height as f32,
+                w as f32 / width as f32,
+                h as f32 / height as f32,
+            ];
+            if let Some(entry) = atlas.rects.get_mut(&glyph_address(glyph)) {
+                entry.0 = Some(rect);
+            }
+        }
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+        gl.delete_framebuffer(framebuffer.as_ref());
+
+        gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+        // Power-of-two sides, so this mipmaps on WebGL 1 too
+        set_minification(gl, width, height, anisotropy);
+        atlas.texture = Some(texture);
+        Ok(atlas)
+    }
+
+    fn covers(&self, glyph: &GlyphTexture) -> bool {
+        self.rects.contains_key(&glyph_address(glyph))
+    }
+
+    // Where `glyph` sits in the atlas texture, if it was packed
+    fn rect(&self, glyph: &GlyphTexture) -> Option<[f32; 4]> {
+        self.rects.get(&glyph_address(glyph))?.0
+    }
+
+    fn delete(&self, gl: &GL) {
+        gl.delete_texture(self.texture.as_ref());
+    }
+}
+
+// Why building or updating the scene failed; surfaces in JS as an `Error` whose
+// `name` is the variant, so callers can tell a shader failure from a lost context
+#[derive(Debug)]
+enum RenderError {
+    ShaderCompile(String),
+    ProgramLink(String),
+    // WebGL hands out null objects once the context is lost
+    ContextLost(String),
+    CanvasCreation(String),
+    TextureUpload(String),
+    // The browser lacks a feature the requested effect needs
+    Unsupported(String),
+    InvalidArgument(String),
+}
+
+impl RenderError {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ShaderCompile(_) => "ShaderCompile",
+            Self::ProgramLink(_) => "ProgramLink",
+            Self::ContextLost(_) => "ContextLost",
+            Self::CanvasCreation(_) => "CanvasCreation",
+            Self::TextureUpload(_) => "TextureUpload",
+            Self::Unsupported(_) => "Unsupported",
+            Self::InvalidArgument(_) => "InvalidArgument",
+        }
+    }
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (Self::ShaderCompile(message)
+        | Self::ProgramLink(message)
+        | Self::ContextLost(message)
+        | Self::CanvasCreation(message)
+        | Self::TextureUpload(message)
+        | Self::Unsupported(message)
+        | Self::InvalidArgument(message)) = self;
+        write!(f, "{}: {message}", self.name())
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<RenderError> for JsValue {
+    fn from(err: RenderError) -> Self {
+        let error = js_sys::Error::new(&err.to_string());
+        error.set_name(err.name());
+        error.into()
+    }
+}
+
+// WebGL helpers
+fn compile_shader(
+    gl: &GL,
+    version: GlVersion,
+    shader_type: u32,
+    source: &str,
+) -> Result<WebGlShader, RenderError> {
+    let shader = gl
+        .create_shader(shader_type)
+        .ok_or_else(|| RenderError::ContextLost("Unable to create shader".into()))?;
+    match version {
+        GlVersion::WebGl1 => gl.shader_source(&shader, source),
+        GlVersion::WebGl2 => gl.shader_source(&shader, &glsl_es3(shader_type, source)),
+    }
+    gl.compile_shader(&shader);
+    if gl
+        .get_shader_parameter(&shader, GL::COMPILE_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(shader)
+    } else {
+        let log = gl
+            .get_shader_info_log(&shader)
+            .unwrap_or_else(|| "Unknown shader error".into());
+        gl.delete_shader(Some(&shader));
+        Err(RenderError::ShaderCompile(log))
+    }
+}
+
+// The shaders are detached once linked, so deleting them frees them right away
+fn link_program(
+    gl: &GL,
+    vert: &WebGlShader,
+    frag: &WebGlShader,
+) -> Result<WebGlProgram, RenderError> {
+    let program = gl
+        .create_program()
+        .ok_or_else(|| RenderError::ContextLost("Unable to create program".into()))?;
+    gl.attach_shader(&program, vert);
+    gl.attach_shader(&program, frag);
+    gl.link_program(&program);
+    gl.detach_shader(&program, vert);
+    gl.detach_shader(&program, frag);
+    if gl
+        .get_program_parameter(&program, GL::LINK_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(program)
+    } else {
+        let log = gl
+            .get_program_info_log(&program)
+            .unwrap_or_else(|| "Unknown program error".into());
+        gl.delete_program(Some(&program));
+        Err(RenderError::ProgramLink(log))
+    }
+}
+
+// Compile, link and release the shaders of a program that shares neither
+fn build_program(
+    gl: &GL,
+    version: GlVersion,
+    vertex: &str,
+    fragment: &str,
+) -> Result<WebGlProgram, RenderError> {
+    let vert = compile_shader(gl, version, GL::VERTEX_SHADER, vertex)?;
+    let program = compile_shader(gl, version, GL::FRAGMENT_SHADER, fragment).and_then(|frag| {
+        let program = link_program(gl, &vert, &frag);
+        gl.delete_shader(Some(&frag));
+        program
+    });
+    gl.delete_shader(Some(&vert));
+    program
+}
+
+// Locations for the sphere program, looked up once after linking
+struct SphereLocations {
+    u_model: Option<WebGlUniformLocation>,
+    u_normal_matrix: Option<WebGlUniformLocation>,
+    u_view: Option<WebGlUniformLocation>,
+    u_projection: Option<WebGlUniformLocation>,
+    u_light_pos: Option<WebGlUniformLocation>,
+    u_light_color: Option<WebGlUniformLocation>,
+    u_color_pole: Option<WebGlUniformLocation>,
+    u_color_equator: Option<WebGlUniformLocation>,
+    u_view_pos: Option<WebGlUniformLocation>,
+    u_edge_fade: Option<WebGlUniformLocation>,
+    u_texture: Option<WebGlUniformLocation>,
+    u_textured: Option<WebGlUniformLocation>,
+    u_fog_color: Option<WebGlUniformLocation>,
+    u_fog_density: Option<WebGlUniformLocation>,
+    u_srgb: Option<WebGlUniformLocation>,
+    u_ambient: Option<WebGlUniformLocation>,
+    u_diffuse_strength: Option<WebGlUniformLocation>,
+    u_specular_strength: Option<WebGlUniformLocation>,
+    u_shininess: Option<WebGlUniformLocation>,
+    a_position: u32,
+    a_normal: u32,
+    a_uv: u32,
+}
+
+impl SphereLocations {
+    fn new(gl: &GL, program: &WebGlProgram) -> Self {
+        let uniform = |name: &str| gl.get_uniform_location(program, name);
+        let attrib = |name: &str| gl.get_attrib_location(program, name) as u32;
+        Self {
+            u_model: uniform("u_model"),
+            u_normal_matrix: uniform("u_normalMatrix"),
+            u_view: uniform("u_view"),
+            u_projection: uniform("u_projection"),
+            u_light_pos: uniform("u_lightPos[0]"),
+            u_light_color: uniform("u_lightColor[0]"),
+            u_color_pole: uniform("u_colorPole"),
+            u_color_equator: uniform("u_colorEquator"),
+            u_view_pos: uniform("u_viewPos"),
+            u_edge_fade: uniform("u_edgeFade"),
+            u_texture: uniform("u_texture"),
+            u_textured: uniform("u_textured"),
+            u_fog_color: uniform("u_fogColor"),
+            u_fog_density: uniform("u_fogDensity"),
+            u_srgb: uniform("u_srgb"),
+            u_ambient: uniform("u_ambient"),
+            u_diffuse_strength: uniform("u_diffuseStrength"),
+            u_specular_strength: uniform("u_specularStrength"),
+            u_shininess: uniform("u_shininess"),
+            a_position: attrib("a_position"),
+            a_normal: attrib("a_normal"),
+            a_uv: attrib("a_uv"),
+        }
+    }
+}
+
+// Locations shared by the per-letter and instanced text programs; the
+// instanced program has no u_model or u_opacity, so those stay None there
+struct TextLocations {
+    u_model: Option<WebGlUniformLocation>,
+    u_view: Option<WebGlUniformLocation>,
+    u_projection: Option<WebGlUniformLocation>,
+    u_texture: Option<WebGlUniformLocation>,
+    u_alpha_threshold: Option<WebGlUniformLocation>,
+    u_opacity: Option<WebGlUniformLocation>,
+    u_fog_color: Option<WebGlUniformLocation>,
+    u_fog_density: Option<WebGlUniformLocation>,
+    a_position: u32,
+    a_uv: u32,
+    // Per-instance model columns, opacity and UV rect with their component counts
+    instance_attribs: [(u32, i32); 6],
+}
+
+impl TextLocations {
+    fn new(gl: &GL, program: &WebGlProgram) -> Self {
+        let uniform = |name: &str| gl.get_uniform_location(program, name);
+        let attrib = |name: &str| gl.get_attrib_location(program, name) as u32;
+        Self {
+            u_model: uniform("u_model"),
+            u_view: uniform("u_view"),
+            u_projection: uniform("u_projection"),
+            u_texture: uniform("u_texture"),
+            u_alpha_threshold: uniform("u_alphaThreshold"),
+            u_opacity: uniform("u_opacity"),
+            u_fog_color: uniform("u_fogColor"),
+            u_fog_density: uniform("u_fogDensity"),
+            a_position: attrib("a_position"),
+            a_uv: attrib("a_uv"),
+            instance_attribs: [
+                (attrib("a_model0"), 4),
+                (attrib("a_model1"), 4),
+                (attrib("a_model2"), 4),
+                (attrib("a_model3"), 4),
+                (attrib("a_opacity"), 1),
+                (attrib("a_uvRect"), 4),
+            ],
+        }
+    }
+}
+
+// Locations for the dot program
+struct DotLocations {
+    u_view: Option<WebGlUniformLocation>,
+    u_projection: Option<WebGlUniformLocation>,
+    u_fog_color: Option<WebGlUniformLocation>,
+    u_fog_density: Option<WebGlUniformLocation>,
+    a_position: u32,
+    a_color: u32,
+    a_size: u32,
+}
+
+impl DotLocations {
+    fn new(gl: &GL, program: &WebGlProgram) -> Self {
+        Self {
+            u_view: gl.get_uniform_location(program, "u_view"),
+            u_projection: gl.get_uniform_location(program, "u_projection"),
+            u_fog_color: gl.get_uniform_location(program, "u_fogColor"),
+            u_fog_density: gl.get_uniform_location(program, "u_fogDensity"),
+            a_position: gl.get_attrib_location(program, "a_position") as u32,
+            a_color: gl.get_attrib_location(program, "a_color") as u32,
+            a_size: gl.get_attrib_location(program, "a_size") as u32,
+        }
+    }
+}
+
+// Locations for the vignette program
+struct VignetteLocations {
+    u_strength: Option<WebGlUniformLocation>,
+    u_radius: Option<WebGlUniformLocation>,
+    a_position: u32,
+}
+
+impl VignetteLocations {
+    fn new(gl: &GL, program: &WebGlProgram) -> Self {
+        Self {
+            u_strength: gl.get_uniform_location(program, "u_strength"),
+            u_radius: gl.get_uniform_location(program, "u_radius"),
+            a_position: gl.get_attrib_location(program, "a_position") as u32,
+        }
+    }
+}
+
+// Locations shared by the bloom bright-pass, blur and composite programs; each leaves the
+// uniforms it lacks as None
+struct BloomLocations {
+    u_color: Option<WebGlUniformLocation>,
+    u_bloom: Option<WebGlUniformLocation>,
+    u_resolution: Option<WebGlUniformLocation>,
+    u_threshold: Option<WebGlUniformLocation>,
+    u_direction: Option<WebGlUniformLocation>,
+    u_intensity: Option<WebGlUniformLocation>,
+    a_position: u32,
+}
+
+impl BloomLocations {
+    fn new(gl: &GL, program: &WebGlProgram) -> Self {
+        let uniform = |name: &str| gl.get_uniform_location(program, name);
+        Self {
+            u_color: uniform("u_color"),
+            u_bloom: uniform("u_bloom"),
+            u_resolution: uniform("u_resolution"),
+            u_threshold: uniform("u_threshold"),
+            u_direction: uniform("u_direction"),
+            u_intensity: uniform("u_intensity"),
+            a_position: gl.get_attrib_location(program, "a_position") as u32,
+        }
+    }
+}
+
+// Locations for the depth-of-field composite program
+struct DofLocations {
+    u_color: Option<WebGlUniformLocation>,
+    u_depth: Option<WebGlUniformLocation>,
+    u_resolution: Option<WebGlUniformLocation>,
+    u_near: Option<WebGlUniformLocation>,
+    u_far: Option<WebGlUniformLocation>,
+    u_focus: Option<WebGlUniformLocation>,
+    u_strength: Option<WebGlUniformLocation>,
+    a_position: u32,
+}
+
+impl DofLocations {
+    fn new(gl: &GL, program: &WebGlProgram) -> Self {
+        let uniform = |name: &str| gl.get_uniform_location(program, name);
+        Self {
+            u_color: uniform("u_color"),
+            u_depth: uniform("u_depth"),
+            u_resolution: uniform("u_resolution"),
+            u_near: uniform("u_near"),
+            u_far: uniform("u_far"),
+            u_focus: uniform("u_focus"),
+            u_strength: uniform("u_strength"),
+            a_position: gl.get_attrib_location(program, "a_position") as u32,
+        }
+    }
+}
+
+fn create_buffer(gl: &GL, data: &[f32]) -> Result<WebGlBuffer, RenderError> {
+    let buffer = gl
+        .create_buffer()
+        .ok_or_else(|| RenderError::ContextLost("Failed to create buffer".into()))?;
+    gl.bind_buffer(GL::ARRAY_BUFFER, Some(&buffer));
+    unsafe {
+        let array = js_sys::Float32Array::view(data);
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &array, GL::STATIC_DRAW);
+    }
+    Ok(buffer)
+}
+
+// Re-upload per-frame vertex data into an existing buffer
+fn update_buffer(gl: &GL, buffer: &WebGlBuffer, data: &[f32]) {
+    gl.bind_buffer(GL::ARRAY_BUFFER, Some(buffer));
+    unsafe {
+        let array = js_sys::Float32Array::view(data);
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &array, GL::DYNAMIC_DRAW);
+    }
+}
+
+// "#RGB" or "#RRGGBB" to 0-1 components
+fn parse_hex_color(color: &str) -> Option<[f32; 3]> {
+    let hex = color.strip_prefix('#')?;
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok().map(|v| v as f32 / 255.0);
+    match hex.len() {
+        3 => {
+            let mut rgb = [0.0; 3];
+            for (i, c) in hex.char_indices() {
+                let digit = c.to_digit(16)? as f32;
+                rgb[i] = digit * 17.0 / 255.0;
+            }
+            Some(rgb)
+        }
+        6 => Some([
+            channel(hex.get(0..2)?)?,
+            channel(hex.get(2..4)?)?,
+            channel(hex.get(4..6)?)?,
+        ]),
+        _ => None,
+    }
+}
+
+fn create_index_buffer(gl: &GL, data: &[u16]) -> Result<WebGlBuffer, RenderError> {
+    let buffer = gl
+        .create_buffer()
+        .ok_or_else(|| RenderError::ContextLost("Failed to create index buffer".into()))?;
+    gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&buffer));
+    unsafe {
+        let array = js_sys::Uint16Array::view(data);
+        gl.buffer_data_with_array_buffer_view(GL::ELEMENT_ARRAY_BUFFER, &array, GL::STATIC_DRAW);
+    }
+    Ok(buffer)
+}
+
+fn create_index_buffer_u32(gl: &GL, data: &[u32]) -> Result<WebGlBuffer, RenderError> {
+    let buffer = gl
+        .create_buffer()
+        .ok_or_else(|| RenderError::ContextLost("Failed to create index buffer".into()))?;
+    gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&buffer));
+    unsafe {
+        let array = js_sys::Uint32Array::view(data);
+        gl.buffer_data_with_array_buffer_view(GL::ELEMENT_ARRAY_BUFFER, &array, GL::STATIC_DRAW);
+    }
+    Ok(buffer)
+}
+
+// 32-bit indices (`UNSIGNED_INT`) are core in WebGL 2 but need OES_element_index_uint
+// on WebGL 1; asking for the extension enables it
+fn supports_uint_indices(gl: &GL, version: GlVersion) -> bool {
+    match version {
+        GlVersion::WebGl2 => true,
+        GlVersion::WebGl1 => matches!(gl.get_extension("OES_element_index_uint"), Ok(Some(_))),
+    }
+}
+
+// What a multi-step setup like `App::new` has created so far. Dropping it deletes the
+// shaders, which the linked programs no longer need, and, unless `keep` was called, the
+// programs, buffers and textures too, so failing partway through leaks nothing.
+struct PendingResources {
+    gl: GL,
+    shaders: Vec<WebGlShader>,
+    programs: Vec<WebGlProgram>,
+    buffers: Vec<WebGlBuffer>,
+    textures: Vec<WebGlTexture>,
+}
+
+impl PendingResources {
+    fn new(gl: &GL) -> Self {
+        Self {
+            gl: gl.clone(),
+            shaders: Vec::new(),
+            programs: Vec::new(),
+            buffers: Vec::new(),
+            textures: Vec::new(),
+        }
+    }
+
+    fn shader(
+        &mut self,
+        version: GlVersion,
+        shader_type: u32,
+        source: &str,
+    ) -> Result<WebGlShader, RenderError> {
+        let shader = compile_shader(&self.gl, version, shader_type, source)?;
+        self.shaders.push(shader.clone());
+        Ok(shader)
+    }
+
+    fn program(&mut self, program: WebGlProgram) -> WebGlProgram {
+        self.programs.push(program.clone());
+        program
+    }
+
+    fn buffer(&mut self, buffer: WebGlBuffer) -> WebGlBuffer {
+        self.buffers.push(buffer.clone());
+        buffer
+    }
+
+    fn texture(&mut self, texture: WebGlTexture) -> WebGlTexture {
+        self.textures.push(texture.clone());
+        texture
+    }
+
+    // The scene owns the programs, buffers and textures from here on
+    fn keep(mut self) {
+        self.programs.clear();
+        self.buffers.clear();
+        self.textures.clear();
+    }
+}
+
+impl Drop for PendingResources {
+    fn drop(&mut self) {
+        for shader in &self.shaders {
+            self.gl.delete_shader(Some(shader));
+        }
+        for program in &self.programs {
+            self.gl.delete_program(Some(program));
+        }
+        for buffer in &self.buffers {
+            self.gl.delete_buffer(Some(buffer));
+        }
+        for texture in &self.textures {
+            self.gl.delete_texture(Some(texture));
+        }
+    }
+}
+
+// Most samples asked for when antialiasing offscreen passes
+const MAX_MSAA_SAMPLES: i32 = 4;
+
+// Samples for multisampled offscreen rendering: only WebGL 2 can render to multisampled
+// renderbuffers and resolve them, and only when antialiasing is wanted. 0 draws
+// offscreen passes without multisampling.
+fn msaa_samples(gl: &GL, version: GlVersion, antialias: bool) -> i32 {
+    if version != GlVersion::WebGl2 || !antialias {
+        return 0;
+    }
+    let max = gl
+        .get_parameter(web_sys::WebGl2RenderingContext::MAX_SAMPLES)
+        .ok()
+        .and_then(|max| max.as_f64())
+        .unwrap_or(0.0) as i32;
+    max.min(MAX_MSAA_SAMPLES)
+}
+
+// Multisampled color and depth an offscreen pass draws into on WebGL 2, so it keeps the
+// antialiasing the default framebuffer gets. Resolved into a sampleable target with
+// blitFramebuffer before that target is read.
+struct MultisampleBuffer {
+    framebuffer: WebGlFramebuffer,
+    color: WebGlRenderbuffer,
+    depth: WebGlRenderbuffer,
+    width: u32,
+    height: u32,
+}
+
+impl MultisampleBuffer {
+    fn new(gl: &GL, samples: i32, width: u32, height: u32) -> Result<Self, RenderError> {
+        let gl2 = gl
+            .dyn_ref::<web_sys::WebGl2RenderingContext>()
+            .ok_or_else(|| RenderError::Unsupported("Multisampling needs WebGL 2".into()))?;
+        let (Some(framebuffer), Some(color), Some(depth)) = (
+            gl.create_framebuffer(),
+            gl.create_renderbuffer(),
+            gl.create_renderbuffer(),
+        ) else {
+            return Err(RenderError::ContextLost(
+                "Failed to create multisampled framebuffer".into(),
+            ));
+        };
+        let buffer = Self {
+            framebuffer,
+            color,
+            depth,
+            width,
+            height,
+        };
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(&buffer.framebuffer));
+        for (renderbuffer, format, attachment) in [
+            (
+                &buffer.color,
+                web_sys::WebGl2RenderingContext::RGBA8,
+                GL::COLOR_ATTACHMENT0,
+            ),
+            (&buffer.depth, GL::DEPTH_COMPONENT16, GL::DEPTH_ATTACHMENT),
+        ] {
+            gl.bind_renderbuffer(GL::RENDERBUFFER, Some(renderbuffer));
+            gl2.renderbuffer_storage_multisample(
+                GL::RENDERBUFFER,
+                samples,
+                format,
+                width as i32,
+                height as i32,
+            );
+            gl.framebuffer_renderbuffer(
+                GL::FRAMEBUFFER,
+                attachment,
+                GL::RENDERBUFFER,
+                Some(renderbuffer),
+            );
+        }
+        let status = gl.check_framebuffer_status(GL::FRAMEBUFFER);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+        if status != GL::FRAMEBUFFER_COMPLETE {
+            buffer.delete(gl);
+            return Err(RenderError::Unsupported(format!(
+                "Multisampled framebuffer incomplete (status {status:#x})"
+            )));
+        }
+        Ok(buffer)
+    }
+
+    // Copy the `mask` buffers (COLOR_BUFFER_BIT and/or DEPTH_BUFFER_BIT) down to one
+    // sample each in `into`, leaving `into` bound
+    fn resolve(&self, gl: &GL, into: &WebGlFramebuffer, mask: u32) {
+        let Some(gl2) = gl.dyn_ref::<web_sys::WebGl2RenderingContext>() else {
+            return;
+        };
+        let (width, height) = (self.width as i32, self.height as i32);
+        gl.bind_framebuffer(
+            web_sys::WebGl2RenderingContext::READ_FRAMEBUFFER,
+            Some(&self.framebuffer),
+        );
+        gl.bind_framebuffer(
+            web_sys::WebGl2RenderingContext::DRAW_FRAMEBUFFER,
+            Some(into),
+        );
+        gl2.blit_framebuffer(0, 0, width, height, 0, 0, width, height, mask, GL::NEAREST);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(into));
+    }
+
+    fn delete(&self, gl: &GL) {
+        gl.delete_framebuffer(Some(&self.framebuffer));
+        gl.delete_renderbuffer(Some(&self.color));
+        gl.delete_renderbuffer(Some(&self.depth));
+    }
+}
+
+// Color plus sampleable depth for post-processing passes. Depth textures are core in
+// WebGL 2 but need the WEBGL_depth_texture extension on WebGL 1. With `samples` above
+// 0 the scene is drawn multisampled and resolved into the textures.
+struct OffscreenTarget {
+    framebuffer: WebGlFramebuffer,
+    color: WebGlTexture,
+    depth: WebGlTexture,
+    msaa: Option<MultisampleBuffer>,
+    width: u32,
+    height: u32,
+}
+
+impl OffscreenTarget {
+    fn new(
+        gl: &GL,
+        version: GlVersion,
+        width: u32,
+        height: u32,
+        samples: i32,
+    ) -> Result<Self, RenderError> {
+        let texture = |internal: u32,
+                       format: u32,
+                       data_type: u32,
+                       filter: u32|
+         -> Result<WebGlTexture, RenderError> {
+            let texture = gl
+                .create_texture()
+                .ok_or_else(|| RenderError::ContextLost("Failed to create texture".into()))?;
+            gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+            gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                GL::TEXTURE_2D,
+                0,
+                internal as i32,
+                width as i32,
+                height as i32,
+                0,
+                format,
+                data_type,
+                None,
+            )
+            .map_err(|_| {
+                RenderError::TextureUpload("Failed to allocate offscreen texture".into())
+            })?;
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, filter as i32);
+            gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, filter as i32);
+            Ok(texture)
+        };
+        let color = texture(GL::RGBA, GL::RGBA, GL::UNSIGNED_BYTE, GL::LINEAR)?;
+        // WebGL 2 only accepts sized depth formats
+        let depth_internal = match version {
+            GlVersion::WebGl1 => GL::DEPTH_COMPONENT,
+            GlVersion::WebGl2 => GL::DEPTH_COMPONENT16,
+        };
+        let depth = texture(
+            depth_internal,
+            GL::DEPTH_COMPONENT,
+            GL::UNSIGNED_SHORT,
+            GL::NEAREST,
+        )?;
+
+        let framebuffer = gl
+            .create_framebuffer()
+            .ok_or_else(|| RenderError::ContextLost("Failed to create framebuffer".into()))?;
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(&framebuffer));
+        gl.framebuffer_texture_2d(
+            GL::FRAMEBUFFER,
+            GL::COLOR_ATTACHMENT0,
+            GL::TEXTURE_2D,
+            Some(&color),
+            0,
+        );
+        gl.framebuffer_texture_2d(
+            GL::FRAMEBUFFER,
+            GL::DEPTH_ATTACHMENT,
+            GL::TEXTURE_2D,
+            Some(&depth),
+            0,
+        );
+        let status = gl.check_framebuffer_status(GL::FRAMEBUFFER);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+
+        let mut target = Self {
+            framebuffer,
+            color,
+            depth,
+            msaa: None,
+            width,
+            height,
+        };
+        if status != GL::FRAMEBUFFER_COMPLETE {
+            target.delete(gl);
+            return Err(RenderError::Unsupported(format!(
+                "Offscreen framebuffer incomplete (status {status:#x})"
+            )));
+        }
+        if samples > 0 {
+            target.msaa = Some(
+                MultisampleBuffer::new(gl, samples, width, height)
+                    .inspect_err(|_| target.delete(gl))?,
+            );
+        }
+        Ok(target)
+    }
+
+    // Where the scene is drawn: the multisampled buffer when there is one
+    fn scene_framebuffer(&self) -> &WebGlFramebuffer {
+        self.msaa
+            .as_ref()
+            .map_or(&self.framebuffer, |msaa| &msaa.framebuffer)
+    }
+
+    // Make color and depth readable after drawing to `scene_framebuffer`
+    fn resolve(&self, gl: &GL) {
+        if let Some(msaa) = &self.msaa {
+            msaa.resolve(
+                gl,
+                &self.framebuffer,
+                GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT,
+            );
+        }
+    }
+
+    fn delete(&self, gl: &GL) {
+        gl.delete_framebuffer(Some(&self.framebuffer));
+        gl.delete_texture(Some(&self.color));
+        gl.delete_texture(Some(&self.depth));
+        if let Some(msaa) = &self.msaa {
+            msaa.delete(gl);
+        }
+    }
+}
+
+// Color texture with an optional depth renderbuffer, for passes that never sample depth
+struct ColorTarget {
+    framebuffer: WebGlFramebuffer,
+    color: WebGlTexture,
+    depth: Option<WebGlRenderbuffer>,
+}
+
+impl ColorTarget {
+    fn new(gl: &GL, width: u32, height: u32, with_depth: bool) -> Result<Self, RenderError> {
+        let color = gl
+            .create_texture()
+            .ok_or_else(|| RenderError::ContextLost("Failed to create texture".into()))?;
+        let depth = if with_depth {
+            gl.create_renderbuffer()
+        } else {
+            None
+        };
+        let framebuffer = match gl.create_framebuffer() {
+            Some(framebuffer) if depth.is_some() == with_depth => framebuffer,
+            framebuffer => {
+                gl.delete_framebuffer(framebuffer.as_ref());
+                gl.delete_renderbuffer(depth.as_ref());
+                gl.delete_texture(Some(&color));
+                return Err(RenderError::ContextLost(
+                    "Failed to create framebuffer".into(),
+                ));
+            }
+        };
+        let target = Self {
+            framebuffer,
+            color,
+            depth,
+        };
+
+        gl.bind_texture(GL::TEXTURE_2D, Some(&target.color));
+        let allocated = gl
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA as i32,
+                width as i32,
+                height as i32,
+                0,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                None,
+            );
+        if allocated.is_err() {
+            target.delete(gl);
+            return Err(RenderError::TextureUpload(
+                "Failed to allocate offscreen texture".into(),
+            ));
+        }
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(&target.framebuffer));
+        gl.framebuffer_texture_2d(
+            GL::FRAMEBUFFER,
+            GL::COLOR_ATTACHMENT0,
+            GL::TEXTURE_2D,
+            Some(&target.color),
+            0,
+        );
+        if let Some(depth) = &target.depth {
+            gl.bind_renderbuffer(GL::RENDERBUFFER, Some(depth));
+            gl.renderbuffer_storage(
+                GL::RENDERBUFFER,
+                GL::DEPTH_COMPONENT16,
+                width as i32,
+                height as i32,
+            );
+            gl.framebuffer_renderbuffer(
+                GL::FRAMEBUFFER,
+                GL::DEPTH_ATTACHMENT,
+                GL::RENDERBUFFER,
+                Some(depth),
+            );
+        }
+        let status = gl.check_framebuffer_status(GL::FRAMEBUFFER);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+        if status != GL::FRAMEBUFFER_COMPLETE {
+            target.delete(gl);
+            return Err(RenderError::Unsupported(format!(
+                "Offscreen framebuffer incomplete (status {status:#x})"
+            )));
+        }
+        Ok(target)
+    }
+
+    fn delete(&self, gl: &GL) {
+        gl.delete_framebuffer(Some(&self.framebuffer));
+        gl.delete_texture(Some(&self.color));
+        gl.delete_renderbuffer(self.depth.as_ref());
+    }
+}
+
+// The scene, drawn offscreen while bloom is on, plus two buffers the bright pass and
+// both blur passes ping-pong between. All match the canvas so viewports carry over.
+// With `samples` above 0 the scene is drawn multisampled and resolved into `scene`.
+struct BloomTargets {
+    scene: ColorTarget,
+    scene_msaa: Option<MultisampleBuffer>,
+    ping: ColorTarget,
+    pong: ColorTarget,
+    width: u32,
+    height: u32,
+}
+
+impl BloomTargets {
+    fn new(gl: &GL, width: u32, height: u32, samples: i32) -> Result<Self, RenderError> {
+        let scene = ColorTarget::new(gl, width, height, true)?;
+        let ping = ColorTarget::new(gl, width, height, false).inspect_err(|_| scene.delete(gl))?;
+        let pong = ColorTarget::new(gl, width, height, false).inspect_err(|_| {
+            scene.delete(gl);
+            ping.delete(gl);
+        })?;
+        let scene_msaa = if samples > 0 {
+            Some(
+                MultisampleBuffer::new(gl, samples, width, height).inspect_err(|_| {
+                    scene.delete(gl);
+                    ping.delete(gl);
+                    pong.delete(gl);
+                })?,
+            )
+        } else {
+            None
+        };
+        Ok(Self {
+            scene,
+            scene_msaa,
+            ping,
+            pong,
+            width,
+            height,
+        })
+    }
+
+    // Where the scene is drawn when nothing comes before bloom
+    fn scene_framebuffer(&self) -> &WebGlFramebuffer {
+        self.scene_msaa
+            .as_ref()
+            .map_or(&self.scene.framebuffer, |msaa| &msaa.framebuffer)
+    }
+
+    // Make the scene color readable after drawing to `scene_framebuffer`
+    fn resolve(&self, gl: &GL) {
+        if let Some(msaa) = &self.scene_msaa {
+            msaa.resolve(gl, &self.scene.framebuffer, GL::COLOR_BUFFER_BIT);
+        }
+    }
+
+    fn delete(&self, gl: &GL) {
+        self.scene.delete(gl);
+        self.ping.delete(gl);
+        self.pong.delete(gl);
+        if let Some(msaa) = &self.scene_msaa {
+            msaa.delete(gl);
+        }
+    }
+}
+
+// Half-length of each debug axis, reaching past the default orbits
+const AXIS_LENGTH: f32 = 3.0;
+
+// X, Y and Z axes through the origin in red, green and blue, for orientation while
+// tuning orbits. Built on first use, so it costs nothing until shown.
+struct AxesHelper {
+    program: WebGlProgram,
+    buffer: WebGlBuffer,
+    u_view: Option<WebGlUniformLocation>,
+    u_projection: Option<WebGlUniformLocation>,
+    a_position: u32,
+    a_color: u32,
+}
+
+impl AxesHelper {
+    fn new(gl: &GL, version: GlVersion) -> Result<Self, RenderError> {
+        let program = build_program(gl, version, LINE_VERTEX_SHADER, LINE_FRAGMENT_SHADER)?;
+        let l = AXIS_LENGTH;
+        // Interleaved position and color per vertex, one segment per axis
+        #[rustfmt::skip]
+        let vertices = [
+            -l, 0.0, 0.0, 1.0, 0.0, 0.0,   l, 0.0, 0.0, 1.0, 0.0, 0.0,
+            0.0, -l, 0.0, 0.0, 1.0, 0.0,   0.0, l, 0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, -l, 0.0, 0.0, 1.0,   0.0, 0.0, l, 0.0, 0.0, 1.0,
+        ];
+        let buffer =
+            create_buffer(gl, &vertices).inspect_err(|_| gl.delete_program(Some(&program)))?;
+        Ok(Self {
+            u_view: gl.get_uniform_location(&program, "u_view"),
+            u_projection: gl.get_uniform_location(&program, "u_projection"),
+            a_position: gl.get_attrib_location(&program, "a_position") as u32,
+            a_color: gl.get_attrib_location(&program, "a_color") as u32,
+            program,
+            buffer,
+        })
+    }
+
+    fn delete(&self, gl: &GL) {
+        gl.delete_program(Some(&self.program));
+        gl.delete_buffer(Some(&self.buffer));
+    }
+}
+
+// Distance of the star sphere from the camera, well inside the far plane
+const STAR_RADIUS: f32 = 50.0;
+// Upper bound for `set_stars`
+const MAX_STARS: u32 = 100_000;
+
+// `count` random points spread evenly over a sphere of `radius`, each followed by a
+// brightness in 0.3-1.0. The same seed always gives the same sky.
+fn generate_stars(count: u32, radius: f32, seed: u32) -> Vec<f32> {
+    let mut rng = Rng::new(seed);
+    let mut stars = Vec::with_capacity(count as usize * 4);
+    for _ in 0..count {
+        // Uniform height and angle give a uniform spread over the sphere
+        let y = rng.range(-1.0, 1.0);
+        let phi = rng.range(0.0, 2.0 * PI);
+        let r = (1.0 - y * y).sqrt();
+        let brightness = rng.range(0.3, 1.0);
+        stars.extend_from_slice(&[
+            r * phi.cos() * radius,
+            y * radius,
+            r * phi.sin() * radius,
+            brightness,
+        ]);
+    }
+    stars
+}
+
+// Point-sprite star field behind everything else, uploaded once per `set_stars`
+struct StarField {
+    program: WebGlProgram,
+    buffer: WebGlBuffer,
+    count: i32,
+    u_view: Option<WebGlUniformLocation>,
+    u_projection: Option<WebGlUniformLocation>,
+    u_point_scale: Option<WebGlUniformLocation>,
+    a_position: u32,
+    a_brightness: u32,
+}
+
+impl StarField {
+    fn new(gl: &GL, version: GlVersion, count: u32, seed: u32) -> Result<Self, RenderError> {
+        let program = build_program(gl, version, STAR_VERTEX_SHADER, STAR_FRAGMENT_SHADER)?;
+        let stars = generate_stars(count, STAR_RADIUS, seed);
+        let buffer =
+            create_buffer(gl, &stars).inspect_err(|_| gl.delete_program(Some(&program)))?;
+        Ok(Self {
+            count: count as i32,
+            u_view: gl.get_uniform_location(&program, "u_view"),
+            u_projection: gl.get_uniform_location(&program, "u_projection"),
+            u_point_scale: gl.get_uniform_location(&program, "u_pointScale"),
+            a_position: gl.get_attrib_location(&program, "a_position") as u32,
+            a_brightness: gl.get_attrib_location(&program, "a_brightness") as u32,
+            program,
+            buffer,
+        })
+    }
+
+    fn delete(&self, gl: &GL) {
+        gl.delete_program(Some(&self.program));
+        gl.delete_buffer(Some(&self.buffer));
+    }
+}
+
+// Pixels between blur taps; wider spreads the glow at the cost of banding
+const BLOOM_SPREAD: f32 = 2.0;
+
+// How the camera projects the scene
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProjectionMode {
+    // 45-degree field of view; things shrink with distance
+    Perspective,
+    // Parallel projection framing what the perspective view shows at the camera target
+    Orthographic,
+}
+
+// Whether the central mesh shades smoothly or shows its facets
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Shading {
+    // Normals interpolated across triangles
+    Smooth,
+    // One normal per triangle, for a low-poly look
+    Flat,
+}
+
+// Where the sphere's first light sits
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LightMode {
+    // At its world position (see `set_light_position`)
+    Fixed,
+    // At the camera, so the side facing the viewer is always lit
+    Headlamp,
+}
+
+// How letter billboards choose their up direction
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BillboardMode {
+    // Upright relative to the world Y axis
+    WorldUp,
+    // Upright on screen, following the camera's roll (see `set_camera_roll`)
+    ScreenUp,
+}
+
+// Easing curves over t in [0, 1]
+fn ease_in_out_cubic(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
+fn ease_out_cubic(t: f32) -> f32 {
+    1.0 - (1.0 - t.clamp(0.0, 1.0)).powi(3)
+}
+
+// Ball dropped on the floor: reaches 1 and rebounds three times, shrinking, never past 1
+fn ease_out_bounce(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    let t = t.clamp(0.0, 1.0);
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+// Azimuth (about Y, from +Z toward +X) and elevation of `offset`, in radians
+fn orbit_angles(offset: Vec3) -> (f32, f32) {
+    let distance = offset.length().max(1e-6);
+    (
+        offset.x.atan2(offset.z),
+        (offset.y / distance).clamp(-1.0, 1.0).asin(),
+    )
+}
+
+// Up vector for look_at: world Y turned `roll` radians about the line of sight
+fn rolled_up(eye: Vec3, target: Vec3, roll: f32) -> Vec3 {
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    if roll == 0.0 {
+        return up;
+    }
+    Quat::from_axis_angle(target.sub(eye), roll).rotate(up)
+}
+
+// Orbit controls stop just short of the poles so look_at never flips
+const MAX_CAMERA_ELEVATION: f32 = 89.0 * PI / 180.0;
+
+// Zoom range, kept outside the sphere and well inside the far plane
+const MIN_CAMERA_DISTANCE_FACTOR: f32 = 1.5;
+const MAX_CAMERA_DISTANCE: f32 = 50.0;
+// Distance multiplier per wheel notch
+const ZOOM_STEP: f32 = 1.1;
+// Camera rotation per arrow key press
+const KEY_ORBIT_STEP: f32 = 5.0 * PI / 180.0;
+// A focused letter is watched from this far outside it, looking back toward the origin
+const FOCUS_DISTANCE: f32 = 2.5;
+// How quickly the camera catches up with a focused letter, per second
+const FOCUS_FOLLOW_RATE: f32 = 8.0;
+
+// Perspective clip distances
+const CAMERA_NEAR: f32 = 0.1;
+const CAMERA_FAR: f32 = 100.0;
+
+// Load-time camera fly-in from far away to the resting position
+struct CameraIntro {
+    from: Vec3,
+    to: Vec3,
+    elapsed: f32,
+    duration: f32,
+}
+
+// Camera swing around the target between two orientations, keeping its distance
+struct CameraFlight {
+    from: Quat,
+    to: Quat,
+    elapsed: f32,
+    duration: f32,
+}
+
+// Orientation that turns +Z into the orbit direction at `azimuth` and `elevation`
+fn orbit_orientation(azimuth: f32, elevation: f32) -> Quat {
+    let yaw = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), azimuth);
+    let pitch = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), -elevation);
+    yaw.mul(pitch)
+}
+
+// How many times farther from the target than its resting distance the intro starts
+const CAMERA_INTRO_DISTANCE: f32 = 8.0;
+
+// One-shot swell of the ring to a target radius and back, eased both ways
+struct RadiusPulse {
+    target: f32,
+    // Level the pulse started from, so re-triggering mid-pulse doesn't jump
+    from_level: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl RadiusPulse {
+    // 0 leaves letters on their own orbits, 1 puts them all at `target`
+    fn level(&self) -> f32 {
+        let t = (self.elapsed / self.duration).clamp(0.0, 1.0);
+        if t < 0.5 {
+            let rise = ease_in_out_cubic(t * 2.0);
+            self.from_level + (1.0 - self.from_level) * rise
+        } else {
+            1.0 - ease_in_out_cubic(t * 2.0 - 1.0)
+        }
+    }
+}
+
+// Letters flying out from the ring and back; level 0 is assembled, 1 fully scattered
+struct Explosion {
+    from_level: f32,
+    to_level: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl Explosion {
+    fn level(&self) -> f32 {
+        let t = (self.elapsed / self.duration).clamp(0.0, 1.0);
+        self.from_level + (self.to_level - self.from_level) * ease_out_cubic(t)
+    }
+}
+
+// Farthest a scattered letter travels outward from its orbit, in world units
+const EXPLODE_DISTANCE: f32 = 4.0;
+
+// Small deterministic PRNG (xorshift32) so randomized effects repeat exactly
+struct Rng(u32);
+
+impl Rng {
+    fn new(seed: u32) -> Self {
+        // Xorshift never leaves zero
+        Self(seed.max(1))
+    }
+
+    // Uniform in [0, 1)
+    fn next_f32(&mut self) -> f32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        (x >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+const DEFAULT_SEED: u32 = 0x9E37_79B9;
+
+// Animation speed multiplier that eases toward a new target instead of snapping
+struct SpeedRamp {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl SpeedRamp {
+    fn new(speed: f32) -> Self {
+        Self {
+            from: speed,
+            to: speed,
+            elapsed: 0.0,
+            duration: 0.0,
+        }
+    }
+
+    fn current(&self) -> f32 {
+        if self.elapsed >= self.duration {
+            self.to
+        } else {
+            let t = ease_in_out_cubic(self.elapsed / self.duration);
+            self.from + (self.to - self.from) * t
+        }
+    }
+
+    // Start easing from the current speed, so retargeting mid-ramp is continuous
+    fn retarget(&mut self, to: f32, duration: f32) {
+        self.from = self.current();
+        self.to = to;
+        self.elapsed = 0.0;
+        self.duration = duration.max(0.0);
+    }
+
+    fn advance(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+}
+
+// Seconds a speed or time scale change takes to ease in, unless the host sets another
+const DEFAULT_SPEED_RAMP_DURATION: f32 = 0.5;
+
+// Every multiplier between real time and the letter and sphere clocks
+struct AnimationSpeeds {
+    // Master speed, eased by `set_time_scale` (and its alias `set_speed`)
+    time_scale: SpeedRamp,
+    // Extra multipliers on letter and sphere time; negative runs them backwards
+    orbit: f32,
+    sphere_spin: f32,
+}
+
+impl AnimationSpeeds {
+    fn new() -> Self {
+        Self {
+            time_scale: SpeedRamp::new(1.0),
+            orbit: 1.0,
+            sphere_spin: 1.0,
+        }
+    }
+
+    // Letter and sphere clock steps for `dt` real seconds. Speeds scale each step rather
+    // than the total, so changing them never jumps.
+    fn step(&mut self, dt: f32) -> (f32, f32) {
+        self.time_scale.advance(dt);
+        let scaled = dt * self.time_scale.current();
+        (scaled * self.orbit, scaled * self.sphere_spin)
+    }
+}
+
+// Animation clock that only accumulates time while running, so pausing and
+// resuming never makes the animation jump
+struct Clock {
+    elapsed: f32,
+    running: bool,
+}
+
+impl Clock {
+    fn new() -> Self {
+        Self {
+            elapsed: 0.0,
+            running: true,
+        }
+    }
+
+    fn advance(&mut self, dt: f32) {
+        if self.running {
+            self.elapsed += dt;
+        }
+    }
+}
+
+// Frames in the rolling FPS average
+const FRAME_STATS_WINDOW: usize = 60;
+
+// Wall-clock frame times, independent of the animation speed
+struct FrameStats {
+    // Ring buffer of recent frame times in milliseconds
+    frame_ms: [f64; FRAME_STATS_WINDOW],
+    len: usize,
+    next: usize,
+}
+
+impl FrameStats {
+    fn new() -> Self {
+        Self {
+            frame_ms: [0.0; FRAME_STATS_WINDOW],
+            len: 0,
+            next: 0,
+        }
+    }
+
+    fn record(&mut self, frame_ms: f64) {
+        self.frame_ms[self.next] = frame_ms;
+        self.next = (self.next + 1) % FRAME_STATS_WINDOW;
+        self.len = (self.len + 1).min(FRAME_STATS_WINDOW);
+    }
+
+    fn last_frame_ms(&self) -> f64 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        self.frame_ms[(self.next + FRAME_STATS_WINDOW - 1) % FRAME_STATS_WINDOW]
+    }
+
+    fn average_fps(&self) -> f64 {
+        let total: f64 = self.frame_ms[..self.len].iter().sum();
+        if total > 0.0 {
+            self.len as f64 * 1000.0 / total
+        } else {
+            0.0
+        }
+    }
+}
+
+const SPHERE_COLOR: Vec3 = Vec3 {
+    x: 0.25,
+    y: 0.45,
+    z: 0.75,
+};
+
+// Backing store pixels per CSS pixel never exceed this unless raised
+const DEFAULT_MAX_PIXEL_RATIO: f32 = 2.0;
+
+// Clear color, RGBA
+const BACKGROUND: [f32; 4] = [0.05, 0.05, 0.1, 1.0];
+
+// A background clamped to 0-1, and its clear color: the page composites the canvas as
+// premultiplied alpha, so the clear color is too
+fn background_colors(color: [f32; 4]) -> ([f32; 4], [f32; 4]) {
+    let [r, g, b, a] = color.map(|c| c.clamp(0.0, 1.0));
+    ([r, g, b, a], [r * a, g * a, b * a, a])
+}
+
+// Point light shading the sphere; the shader sums SPHERE_LIGHTS of them
+#[derive(Clone, Copy)]
+struct Light {
+    position: Vec3,
+    color: Vec3,
+}
+
+impl Light {
+    // Anywhere in the scene, but the color stays in 0-1
+    fn new(position: Vec3, color: Vec3) -> Self {
+        Self {
+            position,
+            color: color.clamp01(),
+        }
+    }
+}
+
+const SPHERE_LIGHTS: usize = 2;
+
+// Flattened u_lightPos and u_lightColor arrays, three floats per light
+fn light_uniforms(
+    positions: &[Vec3; SPHERE_LIGHTS],
+    lights: &[Light; SPHERE_LIGHTS],
+) -> (Vec<f32>, Vec<f32>) {
+    let positions = positions.iter().flat_map(|p| [p.x, p.y, p.z]).collect();
+    let colors = lights
+        .iter()
+        .flat_map(|l| [l.color.x, l.color.y, l.color.z])
+        .collect();
+    (positions, colors)
+}
+
+// A white key light, and a second light left black (off)
+const DEFAULT_LIGHTS: [Light; SPHERE_LIGHTS] = [
+    Light {
+        position: Vec3 {
+            x: 5.0,
+            y: 5.0,
+            z: 5.0,
+        },
+        color: Vec3 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        },
+    },
+    Light {
+        position: Vec3 {
+            x: -5.0,
+            y: -2.0,
+            z: 3.0,
+        },
+        color: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+    },
+];
+
+// How the sphere responds to light: lighting is ambient plus, per light, diffuse
+// times N.L and specular times (N.H)^shininess
+struct Material {
+    ambient: f32,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+}
+
+impl Material {
+    // Strengths can't go negative and the exponent stays at or above MIN_SHININESS
+    fn set_response(&mut self, shininess: f32, specular: f32, diffuse: f32) {
+        self.shininess = shininess.max(MIN_SHININESS);
+        self.specular = specular.max(0.0);
+        self.diffuse = diffuse.max(0.0);
+    }
+
+    fn set_ambient(&mut self, ambient: f32) {
+        self.ambient = ambient.max(0.0);
+    }
+
+    // Values for u_ambient, u_diffuseStrength, u_specularStrength and u_shininess
+    fn uniforms(&self) -> [f32; 4] {
+        [self.ambient, self.diffuse, self.specular, self.shininess]
+    }
+}
+
+const DEFAULT_MATERIAL: Material = Material {
+    ambient: 0.15,
+    diffuse: 0.7,
+    specular: 0.3,
+    shininess: 32.0,
+};
+
+// pow(0, e) is undefined for e <= 0, and exponents below 1 spread the highlight over
+// the whole lit side
+const MIN_SHININESS: f32 = 1.0;
+
+// Sphere colors and lights as set from JS, kept apart from the GL state so the setters
+// can be checked without a context
+struct SphereAppearance {
+    // Base color blends from equator to poles by latitude
+    color_pole: Vec3,
+    color_equator: Vec3,
+    // World-space point lights
+    lights: [Light; SPHERE_LIGHTS],
+}
+
+impl SphereAppearance {
+    fn new() -> Self {
+        Self {
+            color_pole: SPHERE_COLOR,
+            color_equator: SPHERE_COLOR,
+            lights: DEFAULT_LIGHTS,
+        }
+    }
+
+    fn set_gradient(&mut self, pole: Vec3, equator: Vec3) {
+        self.color_pole = pole.clamp01();
+        self.color_equator = equator.clamp01();
+    }
+
+    // A uniform color is a gradient with both ends the same
+    fn set_color(&mut self, color: Vec3) {
+        self.set_gradient(color, color);
+    }
+
+    fn set_light_position(&mut self, position: Vec3) {
+        self.lights[0].position = position;
+    }
+
+    fn set_light(&mut self, index: usize, position: Vec3, color: Vec3) -> Result<(), RenderError> {
+        let light = self.lights.get_mut(index).ok_or_else(|| {
+            RenderError::InvalidArgument(format!(
+                "Light index {index} out of range ({SPHERE_LIGHTS} lights)"
+            ))
+        })?;
+        *light = Light::new(position, color);
+        Ok(())
+    }
+}
+
+// Per-frame draw data for one letter
+struct LetterDraw<'a> {
+    position: Vec3,
+    glyph: &'a GlyphTexture,
+    color: &'a str,
+    opacity: f32,
+    // World units tall
+    size: f32,
+}
+
+// A letter billboard ready to draw
+struct LetterQuad<'a> {
+    model: Mat4,
+    opacity: f32,
+    glyph: &'a GlyphTexture,
+}
+
+// Application state
+struct App {
+    gl: GL,
+    gl_version: GlVersion,
+    canvas: HtmlCanvasElement,
+    document: web_sys::Document,
+    sphere_program: WebGlProgram,
+    text_program: WebGlProgram,
+    dot_program: WebGlProgram,
+    vignette_program: WebGlProgram,
+    dof_program: WebGlProgram,
+    bloom_bright_program: WebGlProgram,
+    bloom_blur_program: WebGlProgram,
+    bloom_composite_program: WebGlProgram,
+    dot_buffer: WebGlBuffer,
+    text_instanced_program: WebGlProgram,
+    sphere_locations: SphereLocations,
+    text_locations: TextLocations,
+    text_instanced_locations: TextLocations,
+    dot_locations: DotLocations,
+    vignette_locations: VignetteLocations,
+    dof_locations: DofLocations,
+    bloom_bright_locations: BloomLocations,
+    bloom_blur_locations: BloomLocations,
+    bloom_composite_locations: BloomLocations,
+    instance_buffer: WebGlBuffer,
+    // None when neither WebGL 2 nor ANGLE_instanced_arrays is available
+    instancing: Option<Instancing>,
+    // Letter glyphs for the instanced pass, rebuilt before a frame when a letter's
+    // glyph is missing from it; None until the first instanced frame
+    glyph_atlas: Option<GlyphAtlas>,
+    // Whether the sphere may use 32-bit indices, lifting the 65536-vertex cap
+    uint_indices: bool,
+    sphere_vertex_buffer: WebGlBuffer,
+    sphere_normal_buffer: WebGlBuffer,
+    // Only the UV sphere has texture coordinates
+    sphere_uv_buffer: Option<WebGlBuffer>,
+    // Image wrapped around the sphere instead of its gradient
+    sphere_texture: Option<WebGlTexture>,
+    sphere_index_buffer: WebGlBuffer,
+    sphere_index_count: i32,
+    // UNSIGNED_SHORT, or UNSIGNED_INT for a sphere past the u16 limit
+    sphere_index_type: u32,
+    quad_vertex_buffer: WebGlBuffer,
+    quad_uv_buffer: WebGlBuffer,
+    quad_index_buffer: WebGlBuffer,
+    letters: Vec<OrbitingLetter>,
+    sprites: Vec<Sprite>,
+    texture_cache: TextureCache,
+    glyph_style: GlyphStyle,
+    camera_pos: Vec3,
+    camera_target: Vec3,
+    // Camera position around the target in spherical angles, kept in sync with
+    // `camera_pos`
+    camera_azimuth: f32,
+    camera_elevation: f32,
+    // Radians about the line of sight; 0 keeps world Y up on screen
+    camera_roll: f32,
+    camera_distance: f32,
+    // Radians of orbit per pixel of mouse drag
+    drag_sensitivity: f32,
+    view_matrix: Mat4,
+    projection_matrix: Mat4,
+    // Depth bias applied to the sphere so coplanar labels win the depth test
+    polygon_offset_factor: f32,
+    polygon_offset_units: f32,
+    // Render without depth testing; visibility comes from draw order alone
+    flat_2d: bool,
+    orbit_effects: OrbitEffects,
+    // Draw letters behind the sphere, then the sphere, then letters in front
+    interleaved_draw: bool,
+    billboard_mode: BillboardMode,
+    projection_mode: ProjectionMode,
+    light_mode: LightMode,
+    shading: Shading,
+    central_mesh: CentralMesh,
+    sphere_radius: f32,
+    // Placement of the sphere mesh alone; letters keep orbiting the origin
+    sphere_offset: Vec3,
+    sphere_scale: f32,
+    // Fixed tilt applied after the spin, so the sphere spins about a tilted axis
+    sphere_tilt: Mat4,
+    // Pick sphere segment counts from the radius instead of `sphere_detail`
+    auto_tessellation: bool,
+    // Latitude and longitude segments of the UV sphere
+    sphere_detail: (u32, u32),
+    // Fades the sphere's silhouette toward the background (0 = opaque)
+    edge_fade: f32,
+    // Light the sphere in linear space and gamma-encode the result
+    srgb: bool,
+    material: Material,
+    // Distance fog; 0 density is off, and no color follows the background
+    fog_density: f32,
+    fog_color: Option<Vec3>,
+    // Sphere spin in rad/s, used unless locked to the letters
+    sphere_spin_rate: f32,
+    // Spin the sphere with the letters at `sphere_lock_ratio` times their mean angular velocity
+    lock_sphere_to_letters: bool,
+    sphere_lock_ratio: f32,
+    // Independent clocks so letters and sphere can be paused separately
+    letter_clock: Clock,
+    sphere_clock: Clock,
+    // Previous frame's timestamp in milliseconds; None makes the next frame start the clock
+    last_timestamp: Option<f64>,
+    frame_stats: FrameStats,
+    // Speed changes ease in over `speed_ramp_duration` seconds
+    speeds: AnimationSpeeds,
+    speed_ramp_duration: f32,
+    // Each letter's inclination is base + variation * sin(phase), giving a saddle-shaped ring
+    inclination_base: f32,
+    inclination_variation: f32,
+    // Letters smaller than this many pixels on screen are drawn as colored dots
+    letter_lod: bool,
+    lod_threshold_px: f32,
+    // Ghost glyphs trailing each letter; 0 turns trails off
+    trail_length: u32,
+    text_mode: TextMode,
+    // Applies to text set after it changes
+    label_mode: LabelMode,
+    // Colors cycled across letters that don't name their own; None uses LETTER_COLORS
+    palette: Option<Vec<String>>,
+    ribbon: Option<Ribbon>,
+    // Backing store size as a fraction of the canvas' device-pixel size; the browser upscales
+    resolution_scale: f32,
+    // Device pixels per CSS pixel; None follows the display's ratio. Either way it is
+    // capped at `max_pixel_ratio`, since 3x phone displays make huge backing stores.
+    pixel_ratio: Option<f32>,
+    max_pixel_ratio: f32,
+    // Host hook called with the GL context after each frame
+    after_render: Option<js_sys::Function>,
+    sphere_appearance: SphereAppearance,
+    // Straight (not premultiplied) RGBA; alpha below 1 lets the page show through
+    background: [f32; 4],
+    // Draw letters with blending off, relying on the shader's discard alone. Cheaper on
+    // weak GPUs and order independent, at the cost of hard glyph edges.
+    alpha_test_only: bool,
+    alpha_test_threshold: f32,
+    camera_intro: Option<CameraIntro>,
+    camera_flight: Option<CameraFlight>,
+    // Letter the camera follows, and the (position, target) to go back to afterwards
+    focused_letter: Option<usize>,
+    focus_return: Option<(Vec3, Vec3)>,
+    // Edge darkening: 0 is off; radius is where it starts, as a fraction of center-to-corner
+    vignette_strength: f32,
+    vignette_radius: f32,
+    // Letters fade to nothing over this distance in front of the near plane; 0 is off
+    near_fade_band: f32,
+    // Side-by-side left/right eye views, cameras `eye_separation` world units apart
+    stereo: bool,
+    eye_separation: f32,
+    // Depth of field: the scene renders offscreen while set, then is blurred by the
+    // distance of each pixel from `dof_focus` (world units from the camera)
+    dof_target: Option<OffscreenTarget>,
+    // Multisampling for the offscreen scene passes; 0 when unavailable or not wanted
+    msaa_samples: i32,
+    dof_focus: f32,
+    dof_strength: f32,
+    // Bloom: the finished scene renders offscreen while set, then everything brighter
+    // than `bloom_threshold` (0-1) is blurred and added back scaled by `bloom_intensity`
+    bloom_targets: Option<BloomTargets>,
+    bloom_threshold: f32,
+    bloom_intensity: f32,
+    // Debug axes, drawn while `show_axes` is set
+    axes: Option<AxesHelper>,
+    show_axes: bool,
+    // Background stars, laid out from `star_seed`; None when off
+    stars: Option<StarField>,
+    star_seed: u32,
+    radius_pulse: Option<RadiusPulse>,
+    explosion: Option<Explosion>,
+    // Per-letter (outward distance, vertical lift) at full scatter, drawn from `seed`
+    scatter: Vec<(f32, f32)>,
+    seed: u32,
+}
+
+impl App {
+    fn new(
+        gl: GL,
+        gl_version: GlVersion,
+        canvas: HtmlCanvasElement,
+        document: &web_sys::Document,
+        width: u32,
+        height: u32,
+        antialias: bool,
+    ) -> Result<Self, RenderError> {
+        let msaa_samples = msaa_samples(&gl, gl_version, antialias);
+        // Compile shaders
+        let mut pending = PendingResources::new(&gl);
+        let mut program = |vertex: &str, fragment: &str| {
+            build_program(&gl, gl_version, vertex, fragment).map(|p| pending.program(p))
+        };
+        let sphere_program = program(SPHERE_VERTEX_SHADER, &sphere_fragment_shader())?;
+        let text_program = program(TEXT_VERTEX_SHADER, TEXT_FRAGMENT_SHADER)?;
+        let dot_program = program(DOT_VERTEX_SHADER, DOT_FRAGMENT_SHADER)?;
+        let text_instanced_program =
+            program(TEXT_INSTANCED_VERTEX_SHADER, TEXT_INSTANCED_FRAGMENT_SHADER)?;
+        let dot_buffer = pending.buffer(create_buffer(&gl, &[])?);
+        let instance_buffer = pending.buffer(create_buffer(&gl, &[])?);
+        let instancing = Instancing::detect(&gl, gl_version);
+        let uint_indices = supports_uint_indices(&gl, gl_version);
+
+        // The post-processing passes share one fullscreen vertex shader
+        let fullscreen_vert =
+            pending.shader(gl_version, GL::VERTEX_SHADER, FULLSCREEN_VERTEX_SHADER)?;
+        let mut fullscreen_program = |fragment: &str| -> Result<WebGlProgram, RenderError> {
+            let frag = pending.shader(gl_version, GL::FRAGMENT_SHADER, fragment)?;
+            let program = link_program(&gl, &fullscreen_vert, &frag)?;
+            Ok(pending.program(program))
+        };
+        let vignette_program = fullscreen_program(VIGNETTE_FRAGMENT_SHADER)?;
+        let dof_program = fullscreen_program(DOF_FRAGMENT_SHADER)?;
+        let bloom_bright_program = fullscreen_program(BLOOM_BRIGHT_FRAGMENT_SHADER)?;
+        let bloom_blur_program = fullscreen_program(BLOOM_BLUR_FRAGMENT_SHADER)?;
+        let bloom_composite_program = fullscreen_program(BLOOM_COMPOSITE_FRAGMENT_SHADER)?;
+
+        let sphere_locations = SphereLocations::new(&gl, &sphere_program);
+        let text_locations = TextLocations::new(&gl, &text_program);
+        let text_instanced_locations = TextLocations::new(&gl, &text_instanced_program);
+        let dot_locations = DotLocations::new(&gl, &dot_program);
+        let vignette_locations = VignetteLocations::new(&gl, &vignette_program);
+        let dof_locations = DofLocations::new(&gl, &dof_program);
+        let bloom_bright_locations = BloomLocations::new(&gl, &bloom_bright_program);
+        let bloom_blur_locations = BloomLocations::new(&gl, &bloom_blur_program);
+        let bloom_composite_locations = BloomLocations::new(&gl, &bloom_composite_program);
+
+        // Generate sphere
+        let (sphere_verts, sphere_normals, sphere_indices, sphere_uvs) =
+            generate_sphere(1.0, SPHERE_LAT_SEGMENTS, SPHERE_LON_SEGMENTS);
+        let sphere_vertex_buffer = pending.buffer(create_buffer(&gl, &sphere_verts)?);
+        let sphere_normal_buffer = pending.buffer(create_buffer(&gl, &sphere_normals)?);
+        let sphere_uv_buffer = Some(pending.buffer(create_buffer(&gl, &sphere_uvs)?));
+        let sphere_index_buffer = pending.buffer(create_index_buffer(&gl, &sphere_indices)?);
+        let sphere_index_count = sphere_indices.len() as i32;
+
+        // Generate quad for text billboards
+        let (quad_verts, quad_uvs, quad_indices) = generate_quad();
+        let quad_vertex_buffer = pending.buffer(create_buffer(&gl, &quad_verts)?);
+        let quad_uv_buffer = pending.buffer(create_buffer(&gl, &quad_uvs)?);
+        let quad_index_buffer = pending.buffer(create_index_buffer(&gl, &quad_indices)?);
+
+        // Create letter textures and orbits for "[wasm-text-sphere]"
+        let mut texture_cache = TextureCache {
+            anisotropy: max_anisotropy(&gl),
+            ..TextureCache::default()
+        };
+        let glyph_style = GlyphStyle::default();
+        let letters = build_ring(
+            &gl,
+            document,
+            &mut texture_cache,
+            &glyph_style,
+            &RingConfig::new("[wasm-text-sphere]".to_string()),
+            LabelMode::Glyphs,
+            |_| LETTER_INCLINATION,
+        )
+        // None of the textures made before the failure are in use yet
+        .inspect_err(|_| texture_cache.purge(&gl))?;
+        pending.keep();
+
+        // Camera setup
+        let camera_pos = Vec3::new(0.0, 0.5, 5.0);
+        let camera_target = Vec3::new(0.0, 0.0, 0.0);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let view_matrix = Mat4::look_at(camera_pos, camera_target, up);
+        let camera_offset = camera_pos.sub(camera_target);
+        let (camera_azimuth, camera_elevation) = orbit_angles(camera_offset);
+        let camera_distance = camera_offset.length();
+
+        // WebGL state
+        gl.enable(GL::DEPTH_TEST);
+        gl.enable(GL::BLEND);
+        gl.blend_func_separate(
+            GL::SRC_ALPHA,
+            GL::ONE_MINUS_SRC_ALPHA,
+            GL::ONE,
+            GL::ONE_MINUS_SRC_ALPHA,
+        );
+        let (_, [r, g, b, a]) = background_colors(BACKGROUND);
+        gl.clear_color(r, g, b, a);
+
+        let mut app = Self {
+            gl,
+            gl_version,
+            canvas,
+            document: document.clone(),
+            sphere_program,
+            text_program,
+            dot_program,
+            vignette_program,
+            dof_program,
+            bloom_bright_program,
+            bloom_blur_program,
+            bloom_composite_program,
+            dot_buffer,
+            sphere_locations,
+            text_locations,
+            text_instanced_locations,
+            dot_locations,
+            vignette_locations,
+            dof_locations,
+            bloom_bright_locations,
+            bloom_blur_locations,
+            bloom_composite_locations,
+            text_instanced_program,
+            instance_buffer,
+            instancing,
+            glyph_atlas: None,
+            uint_indices,
+            sphere_vertex_buffer,
+            sphere_normal_buffer,
+            sphere_uv_buffer,
+            sphere_texture: None,
+            sphere_index_buffer,
+            sphere_index_count,
+            sphere_index_type: GL::UNSIGNED_SHORT,
+            quad_vertex_buffer,
+            quad_uv_buffer,
+            quad_index_buffer,
+            letters,
+            sprites: Vec::new(),
+            texture_cache,
+            glyph_style,
+            camera_pos,
+            camera_target,
+            camera_azimuth,
+            camera_elevation,
+            camera_roll: 0.0,
+            camera_distance,
+            drag_sensitivity: 0.005,
+            view_matrix,
+            projection_matrix: Mat4::identity(),
+            polygon_offset_factor: 0.0,
+            polygon_offset_units: 0.0,
+            flat_2d: false,
+            orbit_effects: OrbitEffects::default(),
+            interleaved_draw: false,
+            billboard_mode: BillboardMode::WorldUp,
+            projection_mode: ProjectionMode::Perspective,
+            light_mode: LightMode::Fixed,
+            shading: Shading::Smooth,
+            central_mesh: CentralMesh::Sphere,
+            sphere_radius: 1.0,
+            sphere_offset: Vec3::new(0.0, 0.0, 0.0),
+            sphere_scale: 1.0,
+            sphere_tilt: Mat4::identity(),
+            auto_tessellation: false,
+            sphere_detail: (SPHERE_LAT_SEGMENTS, SPHERE_LON_SEGMENTS),
+            edge_fade: 0.0,
+            srgb: false,
+            material: DEFAULT_MATERIAL,
+            fog_density: 0.0,
+            fog_color: None,
+            sphere_spin_rate: 0.1,
+            lock_sphere_to_letters: false,
+            sphere_lock_ratio: 1.0,
+            letter_clock: Clock::new(),
+            sphere_clock: Clock::new(),
+            last_timestamp: None,
+            frame_stats: FrameStats::new(),
+            speeds: AnimationSpeeds::new(),
+            speed_ramp_duration: DEFAULT_SPEED_RAMP_DURATION,
+            inclination_base: LETTER_INCLINATION,
+            inclination_variation: 0.0,
+            letter_lod: false,
+            lod_threshold_px: 8.0,
+            trail_length: 0,
+            text_mode: TextMode::Glyphs,
+            label_mode: LabelMode::Glyphs,
+            palette: None,
+            ribbon: None,
+            resolution_scale: 1.0,
+            pixel_ratio: None,
+            max_pixel_ratio: DEFAULT_MAX_PIXEL_RATIO,
+            after_render: None,
+            sphere_appearance: SphereAppearance::new(),
+            background: BACKGROUND,
+            alpha_test_only: false,
+            alpha_test_threshold: 0.5,
+            camera_intro: None,
+            camera_flight: None,
+            focused_letter: None,
+            focus_return: None,
+            vignette_strength: 0.0,
+            vignette_radius: 0.5,
+            near_fade_band: 0.0,
+            stereo: false,
+            eye_separation: 0.1,
+            dof_target: None,
+            msaa_samples,
+            dof_focus: 0.0,
+            dof_strength: 0.0,
+            bloom_targets: None,
+            bloom_threshold: 0.7,
+            bloom_intensity: 1.0,
+            axes: None,
+            show_axes: false,
+            stars: None,
+            star_seed: DEFAULT_SEED,
+            radius_pulse: None,
+            explosion: None,
+            scatter: Vec::new(),
+            seed: DEFAULT_SEED,
+        };
+        // Set up by the same helper that later mode and size changes go through
+        app.projection_matrix = app.projection(width as f32 / height.max(1) as f32);
+        Ok(app)
+    }
+
+    // Backing-store size, which is what the scene actually renders at
+    fn canvas_size(&self) -> (u32, u32) {
+        (self.canvas.width(), self.canvas.height())
+    }
+
+    fn clear_letters(&mut self) {
+        self.letters.clear();
+        self.texture_cache.purge(&self.gl);
+        self.delete_ribbon();
+    }
+
+    // Replaces every letter with the given rings, one after another in `letters`
+    fn set_rings(&mut self, rings: &[RingConfig]) -> Result<(), RenderError> {
+        let (base, variation) = (self.inclination_base, self.inclination_variation);
+        let mut letters = Vec::new();
+        for ring in rings {
+            let ring = RingConfig {
+                colors: ring.colors.clone().or_else(|| self.palette.clone()),
+                ..ring.clone()
+            };
+            letters.extend(build_ring(
+                &self.gl,
+                &self.document,
+                &mut self.texture_cache,
+                &self.glyph_style,
+                &ring,
+                self.label_mode,
+                |phase| base + variation * phase.sin(),
+            )?);
+        }
+        self.letters = letters;
+        self.texture_cache.purge(&self.gl);
+        self.refresh_ribbon()
+    }
+
+    // Deal `text`'s labels round-robin onto `rings`, replacing their own text, so
+    // neighboring letters land on alternate rings
+    fn set_text_across_rings(
+        &mut self,
+        text: &str,
+        rings: &[RingConfig],
+    ) -> Result<(), RenderError> {
+        if rings.is_empty() {
+            return Err(RenderError::InvalidArgument(
+                "At least one ring is needed".into(),
+            ));
+        }
+        let labels: Vec<String> = match self.label_mode {
+            LabelMode::Glyphs => text.chars().map(String::from).collect(),
+            LabelMode::Words => text.split_whitespace().map(String::from).collect(),
+        };
+        let separator = match self.label_mode {
+            LabelMode::Glyphs => "",
+            LabelMode::Words => " ",
+        };
+        let rings: Vec<RingConfig> = rings
+            .iter()
+            .enumerate()
+            .map(|(r, ring)| RingConfig {
+                text: labels
+                    .iter()
+                    .skip(r)
+                    .step_by(rings.len())
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(separator),
+                ..ring.clone()
+            })
+            .collect();
+        self.set_rings(&rings)
+    }
+
+    fn set_letters(&mut self, configs: &[OrbitingLetterConfig]) -> Result<(), RenderError> {
+        let (base, variation) = (self.inclination_base, self.inclination_variation);
+        let configs: Vec<OrbitingLetterConfig> = configs
+            .iter()
+            .enumerate()
+            .map(|(i, config)| OrbitingLetterConfig {
+                color: config.color.clone().or_else(|| {
+                    let palette = self.palette.as_ref()?;
+                    Some(palette[i % palette.len()].clone())
+                }),
+                ..config.clone()
+            })
+            .collect();
+        self.letters = build_letters(
+            &self.gl,
+            &self.document,
+            &mut self.texture_cache,
+            &self.glyph_style,
+            &configs,
+            |phase| base + variation * phase.sin(),
+        )?;
+        self.texture_cache.purge(&self.gl);
+        self.refresh_ribbon()
+    }
+
+    // One default ring spelling `text`; an empty string leaves no letters
+    fn set_text(&mut self, text: &str) -> Result<(), RenderError> {
+        self.set_rings(&[RingConfig::new(text.to_string())])
+    }
+
+    fn set_label_mode(&mut self, mode: LabelMode) {
+        self.label_mode = mode;
+    }
+
+    fn set_text_mode(&mut self, mode: TextMode) -> Result<(), RenderError> {
+        self.text_mode = mode;
+        self.refresh_ribbon()
+    }
+
+    // Rebuild the ribbon from the current letters if it is in use, otherwise free it. On
+    // failure the previous ribbon stays.
+    fn refresh_ribbon(&mut self) -> Result<(), RenderError> {
+        let ribbon = self.build_ribbon()?;
+        self.delete_ribbon();
+        self.ribbon = ribbon;
+        Ok(())
+    }
+
+    fn build_ribbon(&self) -> Result<Option<Ribbon>, RenderError> {
+        if self.text_mode != TextMode::Ribbon || self.letters.is_empty() {
+            return Ok(None);
+        }
+
+        let mut pending = PendingResources::new(&self.gl);
+        let texture = pending.texture(create_ribbon_texture(
+            &self.gl,
+            &self.document,
+            &self.letters,
+            &self.glyph_style,
+        )?);
+        let (uvs, indices) = generate_ribbon_topology(RIBBON_SEGMENTS);
+        let positions = ribbon_positions(
+            &self.letters[0],
+            self.letters.len(),
+            0.0,
+            &self.orbit_effects,
+            RIBBON_SEGMENTS,
+        );
+        let position_buffer = pending.buffer(create_buffer(&self.gl, &positions)?);
+        let uv_buffer = pending.buffer(create_buffer(&self.gl, &uvs)?);
+        let index_buffer = pending.buffer(create_index_buffer(&self.gl, &indices)?);
+        pending.keep();
+        Ok(Some(Ribbon {
+            texture,
+            position_buffer,
+            uv_buffer,
+            index_buffer,
+            index_count: indices.len() as i32,
+        }))
+    }
+
+    fn delete_ribbon(&mut self) {
+        if let Some(ribbon) = self.ribbon.take() {
+            self.gl.delete_texture(Some(&ribbon.texture));
+            self.gl.delete_buffer(Some(&ribbon.position_buffer));
+            self.gl.delete_buffer(Some(&ribbon.uv_buffer));
+            self.gl.delete_buffer(Some(&ribbon.index_buffer));
+        }
+    }
+
+    fn sphere_segments(&self) -> (u32, u32) {
+        if self.auto_tessellation {
+            auto_sphere_segments(self.sphere_radius)
+        } else {
+            self.sphere_detail
+        }
+    }
+
+    // Regenerate the central mesh, releasing the previous buffers. On failure the old mesh
+    // stays and none of the new buffers are left behind.
+    fn rebuild_sphere(&mut self) -> Result<(), RenderError> {
+        let (verts, normals, indices, uvs) = central_mesh_geometry(
+            self.central_mesh,
+            self.sphere_radius,
+            self.sphere_segments(),
+            self.shading,
+        );
+        if let (MeshIndices::U32(_), false) = (&indices, self.uint_indices) {
+            return Err(RenderError::Unsupported(format!(
+                "{} vertices need 32-bit indices (OES_element_index_uint); lower the sphere detail",
+                verts.len() / 3
+            )));
+        }
+        let mut pending = PendingResources::new(&self.gl);
+        let vertex_buffer = pending.buffer(create_buffer(&self.gl, &verts)?);
+        let normal_buffer = pending.buffer(create_buffer(&self.gl, &normals)?);
+        let (index_buffer, index_count, index_type) = match &indices {
+            MeshIndices::U16(indices) => (
+                create_index_buffer(&self.gl, indices)?,
+                indices.len(),
+                GL::UNSIGNED_SHORT,
+            ),
+            MeshIndices::U32(indices) => (
+                create_index_buffer_u32(&self.gl, indices)?,
+                indices.len(),
+                GL::UNSIGNED_INT,
+            ),
+        };
+        let index_buffer = pending.buffer(index_buffer);
+        let uv_buffer = uvs
+            .map(|uvs| create_buffer(&self.gl, &uvs).map(|b| pending.buffer(b)))
+            .transpose()?;
+        pending.keep();
+        if let Some(old_uv) = std::mem::replace(&mut self.sphere_uv_buffer, uv_buffer) {
+            self.gl.delete_buffer(Some(&old_uv));
+        }
+
+        let old_vertex = std::mem::replace(&mut self.sphere_vertex_buffer, vertex_buffer);
+        let old_normal = std::mem::replace(&mut self.sphere_normal_buffer, normal_buffer);
+        let old_index = std::mem::replace(&mut self.sphere_index_buffer, index_buffer);
+        self.gl.delete_buffer(Some(&old_vertex));
+        self.gl.delete_buffer(Some(&old_normal));
+        self.gl.delete_buffer(Some(&old_index));
+        self.sphere_index_count = index_count as i32;
+        self.sphere_index_type = index_type;
+        Ok(())
+    }
+
+    // Upload a loaded image to wrap around the UV sphere; None restores the gradient
+    // Uploads into a new texture first, so a failed upload keeps the previous one
+    fn set_sphere_texture(&mut self, image: &HtmlImageElement) -> Result<(), RenderError> {
+        let gl = &self.gl;
+        let texture = gl
+            .create_texture()
+            .ok_or_else(|| RenderError::ContextLost("Failed to create texture".into()))?;
+        gl.bind_texture(GL::TEXTURE_2D, Some(&texture));
+        let uploaded = gl.tex_image_2d_with_u32_and_u32_and_image(
+            GL::TEXTURE_2D,
+            0,
+            GL::RGBA as i32,
+            GL::RGBA,
+            GL::UNSIGNED_BYTE,
+            image,
+        );
+        if uploaded.is_err() {
+            gl.delete_texture(Some(&texture));
+            return Err(RenderError::TextureUpload(
+                "Failed to upload sphere texture".into(),
+            ));
+        }
+        // Clamping keeps the seam columns from sampling across the wrap; linear without
+        // mipmaps accepts any image size on WebGL 1
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_S, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_WRAP_T, GL::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::LINEAR as i32);
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MAG_FILTER, GL::LINEAR as i32);
+        self.clear_sphere_texture();
+        self.sphere_texture = Some(texture);
+        Ok(())
+    }
+
+    fn clear_sphere_texture(&mut self) {
+        if let Some(old) = self.sphere_texture.take() {
+            self.gl.delete_texture(Some(&old));
+        }
+    }
+
+    // Rebuilds the mesh; on failure the previous radius stays
+    fn set_sphere_radius(&mut self, radius: f32) -> Result<(), RenderError> {
+        let previous = std::mem::replace(&mut self.sphere_radius, radius.max(0.01));
+        self.update_bounce_floor();
+        self.rebuild_sphere().inspect_err(|_| {
+            self.sphere_radius = previous;
+            self.update_bounce_floor();
+        })
+    }
+
+    // Unlike the radius this keeps the mesh, so it is cheap to animate
+    fn set_sphere_transform(&mut self, offset: Vec3, scale: f32) {
+        self.sphere_offset = offset;
+        self.sphere_scale = scale.max(0.0);
+    }
+
+    // Tip the spin axis toward the viewer by `x` radians, then sideways by `z`
+    fn set_sphere_tilt(&mut self, x: f32, z: f32) {
+        self.sphere_tilt = Mat4::rotation_z(z).multiply(Mat4::rotation_x(x));
+    }
+
+    fn set_sphere_tilt_axis(&mut self, axis: Vec3, angle: f32) {
+        self.sphere_tilt = if axis.length() > 0.0 {
+            Mat4::rotation_axis(axis, angle)
+        } else {
+            Mat4::identity()
+        };
+    }
+
+    fn set_central_mesh(&mut self, mesh: CentralMesh) -> Result<(), RenderError> {
+        // Keep flat meshes within u16 indices (two sides, two vertices per step for rings)
+        self.central_mesh = match mesh {
+            CentralMesh::Disc { radius, segments } => CentralMesh::Disc {
+                radius,
+                segments: segments.clamp(3, 16_000),
+            },
+            CentralMesh::Ring {
+                inner,
+                outer,
+                segments,
+            } => CentralMesh::Ring {
+                inner: inner.min(outer),
+                outer: outer.max(inner),
+                segments: segments.clamp(3, 16_000),
+            },
+            CentralMesh::Icosphere { subdivisions } => CentralMesh::Icosphere {
+                subdivisions: subdivisions.min(MAX_ICOSPHERE_SUBDIVISIONS),
+            },
+            CentralMesh::Sphere => CentralMesh::Sphere,
+        };
+        self.rebuild_sphere()
+    }
+
+    // Rebuilds the mesh; on failure the previous shading stays
+    fn set_shading(&mut self, shading: Shading) -> Result<(), RenderError> {
+        let previous = std::mem::replace(&mut self.shading, shading);
+        self.rebuild_sphere()
+            .inspect_err(|_| self.shading = previous)
+    }
+
+    fn set_auto_tessellation(&mut self, enabled: bool) -> Result<(), RenderError> {
+        let previous = std::mem::replace(&mut self.auto_tessellation, enabled);
+        self.rebuild_sphere()
+            .inspect_err(|_| self.auto_tessellation = previous)
+    }
+
+    // Fixed segment counts for the UV sphere; turns auto tessellation off
+    fn set_sphere_detail(&mut self, lat: u32, lon: u32) -> Result<(), RenderError> {
+        let max_vertices = if self.uint_indices {
+            MAX_U32_SPHERE_VERTICES
+        } else {
+            MAX_U16_VERTICES
+        };
+        let previous = (self.sphere_detail, self.auto_tessellation);
+        self.sphere_detail = clamp_sphere_segments(lat, lon, max_vertices);
+        self.auto_tessellation = false;
+        self.rebuild_sphere().inspect_err(|_| {
+            (self.sphere_detail, self.auto_tessellation) = previous;
+        })
+    }
+
+    fn set_letter(&mut self, index: usize, params: LetterParams) -> Result<(), RenderError> {
+        let count = self.letters.len();
+        let letter = self.letters.get(index).ok_or_else(|| {
+            RenderError::InvalidArgument(format!(
+                "Letter index {index} out of range ({count} letters)"
+            ))
+        })?;
+        if let Some(radius) = params.radius {
+            check_orbit_radius(radius)?;
+        }
+        for (name, value) in [
+            ("inclination", params.inclination),
+            ("angular velocity", params.angular_velocity),
+            ("phase", params.phase),
+            ("eccentricity", params.eccentricity),
+            ("z offset", params.z_offset),
+        ] {
+            if let Some(value) = value {
+                check_orbit_value(name, value)?;
+            }
+        }
+
+        // Rasterize the recolored glyph before touching the letter, so a failure leaves
+        // it as it was. Sprites keep their image; the color only tints the LOD dot.
+        let texture = match &params.color {
+            Some(color) if !letter.sprite => Some(
+                self.texture_cache
+                    .get_or_create(
+                        &self.gl,
+                        &self.document,
+                        &letter.glyph,
+                        color,
+                        &self.glyph_style,
+                    )
+                    .inspect_err(|_| self.texture_cache.purge(&self.gl))?,
+            ),
+            _ => None,
+        };
+
+        let mut updated = letter.clone();
+        if let Some(radius) = params.radius {
+            updated.radius = radius;
+        }
+        if let Some(inclination) = params.inclination {
+            updated.inclination = inclination;
+            updated.inherits_inclination = false;
+        }
+        if let Some(angular_velocity) = params.angular_velocity {
+            updated.angular_velocity = angular_velocity;
+        }
+        if let Some(phase) = params.phase {
+            updated.phase = phase;
+        }
+        if let Some(eccentricity) = params.eccentricity {
+            updated.eccentricity = clamp_eccentricity(eccentricity);
+        }
+        if let Some(z_offset) = params.z_offset {
+            updated.z_offset = z_offset;
+        }
+        if let Some(texture) = texture {
+            updated.texture = texture;
+        }
+        let recolored = params.color.is_some();
+        if let Some(color) = params.color {
+            updated.color = color;
+        }
+
+        // The ribbon is drawn from the letters, so build it with the update in place and
+        // put the old letter back if that fails
+        let previous = std::mem::replace(&mut self.letters[index], updated);
+        if recolored && let Err(err) = self.refresh_ribbon() {
+            self.letters[index] = previous;
+            self.texture_cache.purge(&self.gl);
+            return Err(err);
+        }
+        drop(previous);
+        self.texture_cache.purge(&self.gl);
+        Ok(())
+    }
+
+    // Appends an orbiting image; unset params default like the built-in letters
+    fn add_image_sprite(&mut self, url: &str, params: LetterParams) -> Result<(), RenderError> {
+        let texture = self.texture_cache.get_or_load_image(&self.gl, url)?;
+        let phase = params.phase.unwrap_or(0.0);
+        let inherits_inclination = params.inclination.is_none();
+        self.letters.push(OrbitingLetter {
+            glyph: String::new(),
+            color: params.color.unwrap_or_else(|| "#FFFFFF".to_string()),
+            texture,
+            radius: params.radius.unwrap_or(2.2),
+            inclination: params
+                .inclination
+                .unwrap_or(self.inclination_base + self.inclination_variation * phase.sin()),
+            phase,
+            angular_velocity: params.angular_velocity.unwrap_or(0.3),
+            eccentricity: params.eccentricity.map_or(0.0, clamp_eccentricity),
+            z_offset: params.z_offset.unwrap_or(0.0),
+            sprite: true,
+            inherits_inclination,
+        });
+        self.refresh_ribbon()
+    }
+
+    // Places a camera-facing sprite `scale` world units tall at `position`
+    fn add_sprite(
+        &mut self,
+        source: SpriteSource,
+        position: Vec3,
+        scale: f32,
+    ) -> Result<(), RenderError> {
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(RenderError::InvalidArgument(format!(
+                "Sprite scale must be positive, got {scale}"
+            )));
+        }
+        let texture = create_sprite_texture(&self.gl, &source)?;
+        let color = match source {
+            SpriteSource::Color(color) => color.to_string(),
+            SpriteSource::Image(_) => "#FFFFFF".to_string(),
+        };
+        self.sprites.push(Sprite {
+            texture,
+            position,
+            scale,
+            color,
+        });
+        Ok(())
+    }
+
+    fn clear_sprites(&mut self) {
+        for sprite in self.sprites.drain(..) {
+            self.gl.delete_texture(Some(&sprite.texture.texture));
+        }
+    }
+
+    fn orbit_params(&self) -> Vec<f32> {
+        orbit_params(&self.letters)
+    }
+
+    fn set_orbit_params(&mut self, data: &[f32]) -> Result<(), RenderError> {
+        apply_orbit_params(&mut self.letters, data)
+    }
+
+    // Re-rasterize every letter with the current glyph style
+    fn rebuild_letter_textures(&mut self) -> Result<(), RenderError> {
+        let colors = self.letters.iter().map(|l| l.color.clone()).collect();
+        self.recolor_letters(colors)
+    }
+
+    // Re-rasterize every letter in `colors` (one per letter). Textures and the ribbon are
+    // built before anything is committed, so on failure every letter keeps its previous
+    // color and texture.
+    fn recolor_letters(&mut self, colors: Vec<String>) -> Result<(), RenderError> {
+        let textures = self
+            .letters
+            .iter()
+            .zip(&colors)
+            .map(|(letter, color)| {
+                if letter.sprite {
+                    return Ok(None);
+                }
+                self.texture_cache
+                    .get_or_create(
+                        &self.gl,
+                        &self.document,
+                        &letter.glyph,
+                        color,
+                        &self.glyph_style,
+                    )
+                    .map(Some)
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .inspect_err(|_| self.texture_cache.purge(&self.gl))?;
+
+        let previous = self.letters.clone();
+        for ((letter, color), texture) in self.letters.iter_mut().zip(colors).zip(textures) {
+            letter.color = color;
+            if let Some(texture) = texture {
+                letter.texture = texture;
+            }
+        }
+        let refreshed = self.refresh_ribbon();
+        if refreshed.is_err() {
+            self.letters = previous;
+        } else {
+            drop(previous);
+        }
+        self.texture_cache.purge(&self.gl);
+        refreshed
+    }
+
+    // Replace the built-in palette and recolor every letter with it, cycling in order.
+    // Blank entries fall back to the built-in color for their slot; an empty list
+    // restores the built-in palette. On failure the previous palette and colors stay.
+    fn set_colors(&mut self, colors: Vec<String>) -> Result<(), RenderError> {
+        let palette = resolve_palette(colors);
+        let palette = (!palette.is_empty()).then_some(palette);
+        let slots: Vec<&str> = match &palette {
+            Some(palette) => palette.iter().map(String::as_str).collect(),
+            None => LETTER_COLORS.to_vec(),
+        };
+        let sprites: Vec<bool> = self.letters.iter().map(|l| l.sprite).collect();
+        let colors = self
+            .letters
+            .iter()
+            .zip(assign_colors(&slots, &sprites))
+            .map(|(letter, color)| color.map_or_else(|| letter.color.clone(), String::from))
+            .collect();
+        self.recolor_letters(colors)?;
+        self.palette = palette;
+        Ok(())
+    }
+
+    // Redraw glyphs in the current style, e.g. once its web font has loaded; the cache
+    // would otherwise keep serving the fallback-font textures
+    fn rerasterize_glyphs(&mut self) -> Result<(), RenderError> {
+        let stale = self.texture_cache.take_style(&self.glyph_style.cache_key());
+        let rebuilt = self.rebuild_letter_textures();
+        for glyph in stale {
+            if Rc::strong_count(&glyph) == 1 {
+                self.gl.delete_texture(Some(&glyph.texture));
+            }
+        }
+        rebuilt
+    }
+
+    // On failure the previous font stays, along with every letter's texture
+    fn set_font(&mut self, family: &str, weight: &str, size_px: u32) -> Result<(), RenderError> {
+        let font = css_font(family, weight, size_px);
+        let previous = std::mem::replace(&mut self.glyph_style.font, font);
+        self.rebuild_letter_textures()
+            .inspect_err(|_| self.glyph_style.font = previous)
+    }
+
+    fn set_glyph_shadow(&mut self, shadow: Option<GlyphShadow>) -> Result<(), RenderError> {
+        self.glyph_style.shadow = shadow.map(|shadow| GlyphShadow {
+            blur: shadow.blur.clamp(0.0, MAX_SHADOW_BLUR),
+            offset_x: shadow.offset_x.clamp(-MAX_SHADOW_OFFSET, MAX_SHADOW_OFFSET),
+            offset_y: shadow.offset_y.clamp(-MAX_SHADOW_OFFSET, MAX_SHADOW_OFFSET),
+            ..shadow
+        });
+        self.rebuild_letter_textures()
+    }
+
+    fn set_glyph_outline(&mut self, outline: Option<GlyphOutline>) -> Result<(), RenderError> {
+        self.glyph_style.outline = outline
+            .filter(|outline| outline.width > 0.0)
+            .map(|outline| GlyphOutline {
+                width: outline.width.min(MAX_OUTLINE_WIDTH),
+                ..outline
+            });
+        self.rebuild_letter_textures()
+    }
+
+    fn set_glyph_padding(&mut self, padding: u32) -> Result<(), RenderError> {
+        self.glyph_style.padding = padding.min(GLYPH_CELL);
+        self.rebuild_letter_textures()
+    }
+
+    fn set_inclination_distribution(&mut self, base: f32, variation: f32) {
+        self.inclination_base = base;
+        self.inclination_variation = variation;
+        apply_inclination_distribution(&mut self.letters, base, variation);
+    }
+
+    fn set_letter_lod(&mut self, enabled: bool, threshold_px: f32) {
+        self.letter_lod = enabled;
+        self.lod_threshold_px = threshold_px.max(0.0);
+    }
+
+    // On-screen height in pixels of something `world_size` tall at `pos`
+    fn projected_size_px(&self, pos: Vec3, world_size: f32) -> f32 {
+        let half_height = self.canvas.height() as f32 / 2.0;
+        let size = world_size * self.projection_matrix.data[5] * half_height;
+        if self.projection_mode == ProjectionMode::Orthographic {
+            return size;
+        }
+        let depth = -self.view_depth(pos);
+        if depth <= 0.0 {
+            return f32::INFINITY;
+        }
+        size / depth
+    }
+
+    // Projection for a viewport of `aspect` (width over height) in the current mode
+    fn projection(&self, aspect: f32) -> Mat4 {
+        let fov_y = PI / 4.0;
+        match self.projection_mode {
+            ProjectionMode::Perspective => {
+                Mat4::perspective(fov_y, aspect, CAMERA_NEAR, CAMERA_FAR)
+            }
+            ProjectionMode::Orthographic => {
+                let half_height = self.camera_distance * (fov_y / 2.0).tan();
+                let half_width = half_height * aspect;
+                Mat4::ortho(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    CAMERA_NEAR,
+                    CAMERA_FAR,
+                )
+            }
+        }
+    }
+
+    fn set_projection_mode(&mut self, mode: ProjectionMode) {
+        self.projection_mode = mode;
+        self.update_projection_matrix();
+    }
+
+    // CSS pixels from the canvas' top-left corner where `pos` appears, or None when it is
+    // at or behind the camera plane
+    fn project_to_screen(&self, pos: Vec3) -> Option<(f32, f32)> {
+        let clip = self
+            .projection_matrix
+            .transform_vec4(self.view_matrix.transform_vec4(Vec4::from_vec3(pos, 1.0)));
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.perspective_divide()?;
+        let width = self.canvas.client_width() as f32;
+        let height = self.canvas.client_height() as f32;
+        Some(((ndc.x + 1.0) / 2.0 * width, (1.0 - ndc.y) / 2.0 * height))
+    }
+
+    fn update_projection_matrix(&mut self) {
+        let (width, height) = self.canvas_size();
+        let aspect = width.max(1) as f32 / height.max(1) as f32;
+        self.projection_matrix = self.projection(aspect);
+    }
+
+    // Size the backing store from the canvas' CSS size, then match viewport and aspect
+    fn update_canvas_size(&mut self) {
+        let (css_width, css_height) = (self.canvas.client_width(), self.canvas.client_height());
+        if css_width > 0 && css_height > 0 {
+            self.resize(css_width as u32, css_height as u32);
+        } else {
+            // Not laid out (e.g. display: none); keep the current backing store
+            let (width, height) = self.canvas_size();
+            self.gl.viewport(0, 0, width as i32, height as i32);
+            self.update_projection_matrix();
+        }
+    }
+
+    fn pixel_ratio(&self) -> f32 {
+        let ratio = self
+            .pixel_ratio
+            .unwrap_or_else(|| web_sys::window().map_or(1.0, |w| w.device_pixel_ratio()) as f32);
+        ratio.min(self.max_pixel_ratio)
+    }
+
+    // `width` x `height` CSS pixels, rendered at the pixel ratio (times the resolution
+    // scale) so it stays crisp on high-DPI displays
+    fn resize(&mut self, width: u32, height: u32) {
+        let scale = self.pixel_ratio() * self.resolution_scale;
+        let width = (width as f32 * scale).round().max(1.0) as u32;
+        let height = (height as f32 * scale).round().max(1.0) as u32;
+        self.canvas.set_width(width);
+        self.canvas.set_height(height);
+        self.gl.viewport(0, 0, width as i32, height as i32);
+        self.update_projection_matrix();
+    }
+
+    fn set_sphere_gradient(&mut self, pole: Vec3, equator: Vec3) {
+        self.sphere_appearance.set_gradient(pole, equator);
+    }
+
+    fn set_sphere_color(&mut self, color: Vec3) {
+        self.sphere_appearance.set_color(color);
+    }
+
+    fn set_background(&mut self, color: [f32; 4]) {
+        let (background, [r, g, b, a]) = background_colors(color);
+        self.background = background;
+        self.gl.clear_color(r, g, b, a);
+    }
+
+    fn set_light_position(&mut self, position: Vec3) {
+        self.sphere_appearance.set_light_position(position);
+    }
+
+    fn set_light_mode(&mut self, mode: LightMode) {
+        self.light_mode = mode;
+    }
+
+    // Where the lights shine from this frame; a headlamp replaces the first light's position
+    fn light_positions(&self) -> [Vec3; SPHERE_LIGHTS] {
+        let mut positions = self.sphere_appearance.lights.map(|l| l.position);
+        if self.light_mode == LightMode::Headlamp {
+            positions[0] = self.camera_pos;
+        }
+        positions
+    }
+
+    fn set_light(&mut self, index: usize, position: Vec3, color: Vec3) -> Result<(), RenderError> {
+        self.sphere_appearance.set_light(index, position, color)
+    }
+
+    fn set_alpha_test_only(&mut self, enabled: bool, threshold: f32) {
+        self.alpha_test_only = enabled;
+        self.alpha_test_threshold = threshold.clamp(0.0, 1.0);
+    }
+
+    // Texels below this alpha are discarded by the text shader
+    fn text_alpha_threshold(&self) -> f32 {
+        if self.alpha_test_only {
+            self.alpha_test_threshold
+        } else {
+            0.1
+        }
+    }
+
+    fn set_after_render(&mut self, callback: Option<js_sys::Function>) {
+        self.after_render = callback;
+    }
+
+    fn set_resolution_scale(&mut self, scale: f32) {
+        self.resolution_scale = scale.clamp(0.1, 1.0);
+        self.update_canvas_size();
+    }
+
+    // Non-positive ratios go back to following the display
+    fn set_pixel_ratio(&mut self, ratio: f32) {
+        self.pixel_ratio = (ratio > 0.0).then(|| ratio.max(0.1));
+        self.update_canvas_size();
+    }
+
+    fn set_max_pixel_ratio(&mut self, max: f32) {
+        self.max_pixel_ratio = max.max(0.1);
+        self.update_canvas_size();
+    }
+
+    fn set_polygon_offset(&mut self, factor: f32, units: f32) {
+        self.polygon_offset_factor = factor;
+        self.polygon_offset_units = units;
+    }
+
+    fn set_flat_2d(&mut self, flat_2d: bool) {
+        self.flat_2d = flat_2d;
+    }
+
+    // Sphere Y rotation at `time`. A positive Y rotation turns +X toward -Z, the opposite
+    // sense to a letter's increasing orbit angle, so the locked rate is negated.
+    fn sphere_rotation(&self, time: f32) -> f32 {
+        self.orbit_effects.rotation_offset + self.sphere_spin(time)
+    }
+
+    fn sphere_spin(&self, time: f32) -> f32 {
+        if self.lock_sphere_to_letters && !self.letters.is_empty() {
+            let mean_velocity = self
+                .letters
+                .iter()
+                .map(|letter| letter.angular_velocity)
+                .sum::<f32>()
+                / self.letters.len() as f32;
+            -self.sphere_lock_ratio * mean_velocity * time
+        } else {
+            self.sphere_spin_rate * time
+        }
+    }
+
+    // Radians about the Y axis added to both the sphere and the letters
+    fn set_initial_rotation(&mut self, angle: f32) {
+        self.orbit_effects.rotation_offset = angle;
+    }
+
+    fn set_random_initial_rotation(&mut self, seed: u32) {
+        self.set_initial_rotation(Rng::new(seed).range(0.0, 2.0 * PI));
+    }
+
+    fn set_sphere_lock(&mut self, locked: bool, ratio: f32) {
+        self.lock_sphere_to_letters = locked;
+        self.sphere_lock_ratio = ratio;
+    }
+
+    fn set_edge_fade(&mut self, strength: f32) {
+        self.edge_fade = strength.clamp(0.0, 1.0);
+    }
+
+    fn set_srgb(&mut self, srgb: bool) {
+        self.srgb = srgb;
+    }
+
+    fn set_material(&mut self, shininess: f32, specular: f32, diffuse: f32) {
+        self.material.set_response(shininess, specular, diffuse);
+    }
+
+    fn set_ambient(&mut self, ambient: f32) {
+        self.material.set_ambient(ambient);
+    }
+
+    fn set_fog(&mut self, enabled: bool, color: Option<Vec3>, density: f32) {
+        self.fog_density = if enabled { density.max(0.0) } else { 0.0 };
+        self.fog_color = color;
+    }
+
+    fn set_interleaved_draw(&mut self, interleaved: bool) {
+        self.interleaved_draw = interleaved;
+    }
+
+    fn update_view_matrix(&mut self) {
+        let up = rolled_up(self.camera_pos, self.camera_target, self.camera_roll);
+        self.view_matrix = Mat4::look_at(self.camera_pos, self.camera_target, up);
+    }
+
+    fn set_camera_roll(&mut self, radians: f32) {
+        self.camera_roll = radians;
+        self.update_view_matrix();
+    }
+
+    fn set_camera_target(&mut self, target: Vec3) {
+        self.camera_target = target;
+        self.sync_camera_orbit();
+        self.update_view_matrix();
+    }
+
+    fn sync_camera_orbit(&mut self) {
+        let offset = self.camera_pos.sub(self.camera_target);
+        let (azimuth, elevation) = orbit_angles(offset);
+        self.camera_azimuth = azimuth;
+        self.camera_elevation = elevation;
+        self.camera_distance = offset.length();
+    }
+
+    // Put the camera at the orbit angles and distance around the target
+    fn place_camera(&mut self) {
+        let (sin_az, cos_az) = self.camera_azimuth.sin_cos();
+        let (sin_el, cos_el) = self.camera_elevation.sin_cos();
+        let direction = Vec3::new(cos_el * sin_az, sin_el, cos_el * cos_az);
+        self.camera_pos = self
+            .camera_target
+            .add(direction.scale(self.camera_distance));
+        self.update_view_matrix();
+        // The orthographic frame scales with distance, which is how it zooms
+        if self.projection_mode == ProjectionMode::Orthographic {
+            self.update_projection_matrix();
+        }
+    }
+
+    // Taking control ends the fly-in where it was headed, and stops following a letter
+    // from wherever the camera got to
+    fn end_camera_intro(&mut self) {
+        if let Some(intro) = self.camera_intro.take() {
+            self.camera_pos = intro.to;
+            self.sync_camera_orbit();
+        }
+        if self.focused_letter.take().is_some() {
+            self.focus_return = None;
+            self.sync_camera_orbit();
+        }
+    }
+
+    // Follow letter `index` with the camera, or with None go back to the orbit camera
+    // as it was before
+    fn focus_letter(&mut self, index: Option<usize>) -> Result<(), RenderError> {
+        match index {
+            Some(index) if index >= self.letters.len() => {
+                Err(RenderError::InvalidArgument(format!(
+                    "Letter index {index} out of range ({} letters)",
+                    self.letters.len()
+                )))
+            }
+            Some(index) => {
+                self.camera_flight = None;
+                if self.focus_return.is_none() {
+                    self.focus_return = Some((self.camera_pos, self.camera_target));
+                }
+                self.focused_letter = Some(index);
+                Ok(())
+            }
+            None => {
+                self.focused_letter = None;
+                if let Some((position, target)) = self.focus_return.take() {
+                    self.camera_pos = position;
+                    self.camera_target = target;
+                    self.sync_camera_orbit();
+                    self.update_view_matrix();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Ease the target toward the focused letter, frame-rate independently, and keep the
+    // camera a fixed distance outside it
+    fn follow_focused_letter(&mut self, dt: f32) {
+        let Some(index) = self.focused_letter.filter(|&i| i < self.letters.len()) else {
+            return;
+        };
+        let scatter_level = self.explosion.as_ref().map_or(0.0, Explosion::level);
+        let letter = self.letter_position(index, self.letter_clock.elapsed, scatter_level);
+        let t = 1.0 - (-FOCUS_FOLLOW_RATE * dt).exp();
+        self.camera_target = self.camera_target.lerp(letter, t);
+        let outward = if self.camera_target.length() > 1e-4 {
+            self.camera_target.normalize()
+        } else {
+            Vec3::new(0.0, 0.0, 1.0)
+        };
+        self.camera_pos = self.camera_target.add(outward.scale(FOCUS_DISTANCE));
+        self.update_view_matrix();
+    }
+
+    // Swing from the current viewpoint to `azimuth`/`elevation` over `duration` seconds
+    fn animate_camera_to(&mut self, azimuth: f32, elevation: f32, duration: f32) {
+        self.end_camera_intro();
+        let elevation = elevation.clamp(-MAX_CAMERA_ELEVATION, MAX_CAMERA_ELEVATION);
+        self.camera_flight = Some(CameraFlight {
+            from: orbit_orientation(self.camera_azimuth, self.camera_elevation),
+            to: orbit_orientation(azimuth, elevation),
+            elapsed: 0.0,
+            duration: duration.max(0.0),
+        });
+        self.advance_camera_flight(0.0);
+    }
+
+    // Real time, like the intro
+    fn advance_camera_flight(&mut self, dt: f32) {
+        let Some(flight) = &mut self.camera_flight else {
+            return;
+        };
+        flight.elapsed += dt;
+        let t = if flight.duration > 0.0 {
+            (flight.elapsed / flight.duration).min(1.0)
+        } else {
+            1.0
+        };
+        let orientation = flight.from.slerp(flight.to, ease_in_out_cubic(t));
+        if t >= 1.0 {
+            self.camera_flight = None;
+        }
+        let direction = orientation.rotate(Vec3::new(0.0, 0.0, 1.0));
+        self.camera_pos = self
+            .camera_target
+            .add(direction.scale(self.camera_distance));
+        self.sync_camera_orbit();
+        self.update_view_matrix();
+    }
+
+    // Multiplicative, so each notch feels the same near and far; positive zooms out
+    fn zoom(&mut self, notches: f32) {
+        self.end_camera_intro();
+        self.camera_flight = None;
+        let min = self.sphere_radius * MIN_CAMERA_DISTANCE_FACTOR;
+        self.camera_distance =
+            (self.camera_distance * ZOOM_STEP.powf(notches)).clamp(min, MAX_CAMERA_DISTANCE);
+        self.place_camera();
+    }
+
+    // Rotate the camera around the target, keeping its distance
+    fn orbit_camera(&mut self, d_azimuth: f32, d_elevation: f32) {
+        self.end_camera_intro();
+        self.camera_flight = None;
+        self.camera_azimuth += d_azimuth;
+        self.camera_elevation = (self.camera_elevation + d_elevation)
+            .clamp(-MAX_CAMERA_ELEVATION, MAX_CAMERA_ELEVATION);
+        self.place_camera();
+    }
+
+    fn on_pointer_drag(&mut self, dx: f32, dy: f32) {
+        self.orbit_camera(-dx * self.drag_sensitivity, dy * self.drag_sensitivity);
+    }
+
+    // Keyboard bindings, named by `KeyboardEvent.key`: the arrow keys orbit the camera
+    // (in the same directions as dragging), `+`/`=` zooms in, `-` zooms out and space
+    // toggles pause. Returns whether the key was handled.
+    fn on_key(&mut self, key: &str) -> bool {
+        match key {
+            "ArrowLeft" => self.orbit_camera(KEY_ORBIT_STEP, 0.0),
+            "ArrowRight" => self.orbit_camera(-KEY_ORBIT_STEP, 0.0),
+            "ArrowUp" => self.orbit_camera(0.0, -KEY_ORBIT_STEP),
+            "ArrowDown" => self.orbit_camera(0.0, KEY_ORBIT_STEP),
+            "+" | "=" => self.zoom(-1.0),
+            "-" => self.zoom(1.0),
+            " " => self.set_paused(!self.is_paused()),
+            _ => return false,
+        }
+        true
+    }
+
+    fn set_drag_sensitivity(&mut self, radians_per_pixel: f32) {
+        self.drag_sensitivity = radians_per_pixel;
+    }
+
+    fn set_billboard_mode(&mut self, mode: BillboardMode) {
+        self.billboard_mode = mode;
+    }
+
+    // Camera up axis in world space (second row of the view rotation)
+    fn camera_up(&self) -> Vec3 {
+        let m = &self.view_matrix.data;
+        Vec3::new(m[1], m[5], m[9])
+    }
+
+    fn set_eccentricity_animation(&mut self, amplitude: f32, rate: f32) {
+        // Keep both semi-axes positive
+        self.orbit_effects.eccentricity_amplitude = amplitude.clamp(0.0, 0.9);
+        self.orbit_effects.eccentricity_rate = rate;
+    }
+
+    fn set_letter_bounce(&mut self, interval: f32, height: f32) {
+        self.orbit_effects.bounce_interval = interval.max(0.0);
+        self.orbit_effects.bounce_height = height.max(0.0);
+        self.update_bounce_floor();
+    }
+
+    // Keep bouncing glyph quads (0.3 half-size) clear of the sphere surface
+    fn update_bounce_floor(&mut self) {
+        self.orbit_effects.bounce_floor = self.sphere_radius + 0.3;
+    }
+
+    fn set_orbit_resonance(&mut self, amplitude: f32, period: f32) {
+        self.orbit_effects.resonance_amplitude = amplitude.max(0.0);
+        self.orbit_effects.resonance_period = period.max(0.0);
+    }
+
+    // Advance the animation clocks by `dt` seconds of wall-clock time
+    fn advance(&mut self, dt: f32) {
+        self.advance_camera_intro(dt);
+        self.advance_camera_flight(dt);
+        let (letter_dt, sphere_dt) = self.speeds.step(dt);
+        self.letter_clock.advance(letter_dt);
+        self.sphere_clock.advance(sphere_dt);
+        self.follow_focused_letter(dt);
+        if self.letter_clock.running {
+            // Pulses and explosions play forward even while time runs backwards
+            let effect_dt = letter_dt.abs();
+            self.advance_radius_pulse(effect_dt);
+            if let Some(explosion) = &mut self.explosion {
+                explosion.elapsed += effect_dt;
+            }
+        }
+    }
+
+    // Runs on letter time, so it slows, speeds up and pauses with the letters
+    fn advance_radius_pulse(&mut self, dt: f32) {
+        let Some(pulse) = &mut self.radius_pulse else {
+            return;
+        };
+        pulse.elapsed += dt;
+        self.orbit_effects.pulse_radius = pulse.target;
+        self.orbit_effects.pulse_level = pulse.level();
+        if pulse.elapsed >= pulse.duration {
+            self.radius_pulse = None;
+            self.orbit_effects.pulse_level = 0.0;
+        }
+    }
+
+    // Fly letters outward and fade them; they stay scattered until `reassemble`
+    fn explode(&mut self, duration: f32) {
+        let mut rng = Rng::new(self.seed);
+        self.scatter = self
+            .letters
+            .iter()
+            .map(|_| {
+                let distance = rng.range(0.4, 1.0) * EXPLODE_DISTANCE;
+                let lift = rng.range(-0.5, 0.5) * EXPLODE_DISTANCE;
+                (distance, lift)
+            })
+            .collect();
+        self.scatter_to(1.0, duration);
+    }
+
+    // Reverses over the same duration the explosion used
+    fn reassemble(&mut self) {
+        if let Some(duration) = self.explosion.as_ref().map(|e| e.duration) {
+            self.scatter_to(0.0, duration);
+        }
+    }
+
+    // Start easing from wherever the current explosion is, so reversing mid-way is smooth
+    fn scatter_to(&mut self, level: f32, duration: f32) {
+        let from_level = self.explosion.as_ref().map_or(0.0, Explosion::level);
+        self.explosion = Some(Explosion {
+            from_level,
+            to_level: level,
+            elapsed: 0.0,
+            duration: duration.max(0.01),
+        });
+    }
+
+    fn pulse_radius(&mut self, target: f32, duration: f32) {
+        self.radius_pulse = Some(RadiusPulse {
+            target: target.max(0.0),
+            from_level: self.orbit_effects.pulse_level,
+            elapsed: 0.0,
+            duration: duration.max(0.01),
+        });
+    }
+
+    fn set_time_scale(&mut self, scale: f32) {
+        self.speeds
+            .time_scale
+            .retarget(scale, self.speed_ramp_duration);
+    }
+
+    fn set_time_scale_immediate(&mut self, scale: f32) {
+        self.speeds.time_scale.retarget(scale, 0.0);
+    }
+
+    fn set_orbit_speed(&mut self, multiplier: f32) {
+        self.speeds.orbit = multiplier;
+    }
+
+    fn set_sphere_spin_speed(&mut self, multiplier: f32) {
+        self.speeds.sphere_spin = multiplier;
+    }
+
+    fn set_speed_ramp_duration(&mut self, seconds: f32) {
+        self.speed_ramp_duration = seconds.max(0.0);
+    }
+
+    // Runs in real time, independent of animation speed and pausing
+    fn advance_camera_intro(&mut self, dt: f32) {
+        let Some(intro) = &mut self.camera_intro else {
+            return;
+        };
+        intro.elapsed += dt;
+        let t = if intro.duration > 0.0 {
+            intro.elapsed / intro.duration
+        } else {
+            1.0
+        };
+        self.camera_pos = intro.from.lerp(intro.to, ease_out_cubic(t));
+        if t >= 1.0 {
+            self.camera_intro = None;
+        }
+        self.sync_camera_orbit();
+        self.update_view_matrix();
+    }
+
+    // Fly in from far along the current view direction; disabling lands immediately
+    fn set_camera_intro(&mut self, enabled: bool, duration: f32) {
+        if let Some(intro) = self.camera_intro.take() {
+            self.camera_pos = intro.to;
+        }
+        if enabled {
+            let rest = self.camera_pos;
+            let offset = rest.sub(self.camera_target).scale(CAMERA_INTRO_DISTANCE);
+            self.camera_intro = Some(CameraIntro {
+                from: self.camera_target.add(offset),
+                to: rest,
+                elapsed: 0.0,
+                duration: duration.max(0.0),
+            });
+        }
+        self.update_view_matrix();
+    }
+
+    fn set_depth_of_field(
+        &mut self,
+        enabled: bool,
+        focus: f32,
+        strength: f32,
+    ) -> Result<(), RenderError> {
+        self.dof_focus = focus.max(0.0);
+        self.dof_strength = strength.max(0.0);
+        if let Some(target) = self.dof_target.take() {
+            target.delete(&self.gl);
+        }
+        if enabled {
+            if self.gl_version == GlVersion::WebGl1 {
+                self.gl
+                    .get_extension("WEBGL_depth_texture")
+                    .ok()
+                    .flatten()
+                    .ok_or_else(|| {
+                        RenderError::Unsupported(
+                            "Depth of field needs the WEBGL_depth_texture extension".into(),
+                        )
+                    })?;
+            }
+            let (width, height) = self.canvas_size();
+            self.dof_target = Some(OffscreenTarget::new(
+                &self.gl,
+                self.gl_version,
+                width,
+                height,
+                self.msaa_samples,
+            )?);
+        }
+        Ok(())
+    }
+
+    // Keep the offscreen target matched to the canvas; drops it if reallocation fails
+    fn resize_dof_target(&mut self) -> Result<(), RenderError> {
+        let size = self.canvas_size();
+        let Some(target) = &self.dof_target else {
+            return Ok(());
+        };
+        if (target.width, target.height) == size {
+            return Ok(());
+        }
+        if let Some(target) = self.dof_target.take() {
+            target.delete(&self.gl);
+        }
+        self.dof_target = Some(OffscreenTarget::new(
+            &self.gl,
+            self.gl_version,
+            size.0,
+            size.1,
+            self.msaa_samples,
+        )?);
+        Ok(())
+    }
+
+    fn set_bloom(
+        &mut self,
+        enabled: bool,
+        threshold: f32,
+        intensity: f32,
+    ) -> Result<(), RenderError> {
+        self.bloom_threshold = threshold.clamp(0.0, 1.0);
+        self.bloom_intensity = intensity.max(0.0);
+        if let Some(targets) = self.bloom_targets.take() {
+            targets.delete(&self.gl);
+        }
+        if enabled {
+            let (width, height) = self.canvas_size();
+            self.bloom_targets = Some(BloomTargets::new(
+                &self.gl,
+                width,
+                height,
+                self.msaa_samples,
+            )?);
+        }
+        Ok(())
+    }
+
+    // Keep the bloom buffers matched to the canvas; drops them if reallocation fails
+    fn resize_bloom_targets(&mut self) -> Result<(), RenderError> {
+        let size = self.canvas_size();
+        let Some(targets) = &self.bloom_targets else {
+            return Ok(());
+        };
+        if (targets.width, targets.height) == size {
+            return Ok(());
+        }
+        if let Some(targets) = self.bloom_targets.take() {
+            targets.delete(&self.gl);
+        }
+        self.bloom_targets = Some(BloomTargets::new(
+            &self.gl,
+            size.0,
+            size.1,
+            self.msaa_samples,
+        )?);
+        Ok(())
+    }
+
+    fn set_stereo(&mut self, enabled: bool, eye_separation: f32) {
+        self.stereo = enabled;
+        self.eye_separation = eye_separation.max(0.0);
+    }
+
+    fn set_near_fade(&mut self, band: f32) {
+        self.near_fade_band = band.max(0.0);
+    }
+
+    // Letter opacity from its distance to the camera, 0 at the near plane
+    fn near_fade_opacity(&self, pos: Vec3) -> f32 {
+        if self.near_fade_band <= 0.0 {
+            return 1.0;
+        }
+        let d = pos.sub(self.camera_pos);
+        ((d.length() - CAMERA_NEAR) / self.near_fade_band).clamp(0.0, 1.0)
+    }
+
+    fn set_trails(&mut self, enabled: bool) {
+        self.trail_length = if enabled { DEFAULT_TRAIL_LENGTH } else { 0 };
+    }
+
+    fn set_trail_length(&mut self, samples: u32) {
+        self.trail_length = samples.min(MAX_TRAIL_LENGTH);
+    }
+
+    fn set_show_axes(&mut self, show: bool) -> Result<(), RenderError> {
+        if show && self.axes.is_none() {
+            self.axes = Some(AxesHelper::new(&self.gl, self.gl_version)?);
+        }
+        self.show_axes = show;
+        Ok(())
+    }
+
+    // Regenerate the star field with `count` stars (capped at MAX_STARS); 0 removes it
+    fn set_stars(&mut self, count: u32) -> Result<(), RenderError> {
+        if let Some(stars) = self.stars.take() {
+            stars.delete(&self.gl);
+        }
+        if count > 0 {
+            let count = count.min(MAX_STARS);
+            self.stars = Some(StarField::new(
+                &self.gl,
+                self.gl_version,
+                count,
+                self.star_seed,
+            )?);
+        }
+        Ok(())
+    }
+
+    fn set_star_seed(&mut self, seed: u32) -> Result<(), RenderError> {
+        self.star_seed = seed;
+        match &self.stars {
+            Some(stars) => self.set_stars(stars.count as u32),
+            None => Ok(()),
+        }
+    }
+
+    fn set_vignette(&mut self, strength: f32, radius: f32) {
+        self.vignette_strength = strength.clamp(0.0, 1.0);
+        self.vignette_radius = radius.clamp(0.0, 0.99);
+    }
+
+    fn set_letters_paused(&mut self, paused: bool) {
+        self.letter_clock.running = !paused;
+    }
+
+    fn set_sphere_paused(&mut self, paused: bool) {
+        self.sphere_clock.running = !paused;
+    }
+
+    // Freeze letters and sphere together; frames keep drawing the frozen scene
+    fn set_paused(&mut self, paused: bool) {
+        self.set_letters_paused(paused);
+        self.set_sphere_paused(paused);
+    }
+
+    fn is_paused(&self) -> bool {
+        !self.letter_clock.running && !self.sphere_clock.running
+    }
+
+    // Jump both animation clocks to `seconds` of animation time; paused clocks stay paused.
+    // Negative times are valid, since reversed speeds run the clocks below zero.
+    fn set_time(&mut self, seconds: f32) -> Result<(), RenderError> {
+        if !seconds.is_finite() {
+            return Err(RenderError::InvalidArgument(format!(
+                "Animation time must be finite, got {seconds}"
+            )));
+        }
+        self.letter_clock.elapsed = seconds;
+        self.sphere_clock.elapsed = seconds;
+        Ok(())
+    }
+
+    // Draw the frame at the current clock time. Reads no wall clock, so after `set_time`
+    // the same time always renders the same frame.
+    fn render_current(&mut self) {
+        self.refresh_glyph_atlas();
+        if let Err(err) = self.resize_dof_target() {
+            web_sys::console::error_1(&format!("Depth of field disabled: {err}").into());
+        }
+        if let Err(err) = self.resize_bloom_targets() {
+            web_sys::console::error_1(&format!("Bloom disabled: {err}").into());
+        }
+        if self.stereo {
+            self.render_stereo();
+        } else {
+            self.draw_frame();
+        }
+    }
+
+    // Encode the canvas as a PNG data URL. The frame is redrawn first: without
+    // `preserveDrawingBuffer` the browser clears the drawing buffer once a frame is
+    // composited, and reading it back afterwards gives a blank image.
+    fn capture_data_url(&mut self) -> Result<String, JsValue> {
+        self.render_current();
+        self.canvas.to_data_url()
+    }
+
+    // Rebuild the glyph atlas when a letter's glyph isn't in it yet, i.e. after the text,
+    // colors or glyph style changed. Runs before a frame binds its framebuffers, since
+    // the copy goes through a scratch one. Only the instanced pass samples the atlas.
+    fn refresh_glyph_atlas(&mut self) {
+        if self.instancing.is_none() {
+            return;
+        }
+        let packable = |letter: &&OrbitingLetter| letter.texture.size.is_some();
+        let stale = match &self.glyph_atlas {
+            Some(atlas) => self
+                .letters
+                .iter()
+                .filter(packable)
+                .any(|letter| !atlas.covers(&letter.texture)),
+            None => true,
+        };
+        if !stale {
+            return;
+        }
+        let mut glyphs: Vec<Rc<GlyphTexture>> = Vec::new();
+        for letter in self.letters.iter().filter(packable) {
+            if !glyphs
+                .iter()
+                .any(|glyph| Rc::ptr_eq(glyph, &letter.texture))
+            {
+                glyphs.push(letter.texture.clone());
+            }
+        }
+        if let Some(atlas) = self.glyph_atlas.take() {
+            atlas.delete(&self.gl);
+        }
+        let atlas = GlyphAtlas::build(&self.gl, &glyphs, self.texture_cache.anisotropy)
+            .unwrap_or_else(|err| {
+                web_sys::console::error_1(&format!("Glyph atlas disabled: {err}").into());
+                GlyphAtlas::empty(&glyphs)
+            });
+        self.glyph_atlas = Some(atlas);
+    }
+
+    fn draw_frame(&self) {
+        // Bloom picks up the scene after depth of field has composited it
+        let output = self.bloom_targets.as_ref().map(|b| &b.scene.framebuffer);
+        if let Some(target) = &self.dof_target {
+            self.gl
+                .bind_framebuffer(GL::FRAMEBUFFER, Some(target.scene_framebuffer()));
+            self.draw_scene();
+            target.resolve(&self.gl);
+            self.gl.bind_framebuffer(GL::FRAMEBUFFER, output);
+            self.draw_depth_of_field(target);
+        } else if let Some(targets) = &self.bloom_targets {
+            self.gl
+                .bind_framebuffer(GL::FRAMEBUFFER, Some(targets.scene_framebuffer()));
+            self.draw_scene();
+            targets.resolve(&self.gl);
+        } else {
+            self.gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+            self.draw_scene();
+        }
+        if let Some(targets) = &self.bloom_targets {
+            self.draw_bloom(targets);
+        }
+        if self.vignette_strength > 0.0 {
+            self.draw_vignette();
+        }
+    }
+
+    // Draw the scene at `time` seconds on both clocks into a `width` x `height` buffer
+    // and read it back as RGBA bytes. Rows come bottom-up, as WebGL reads them: the
+    // first `width * 4` bytes are the bottom row. Stereo, depth of field and bloom, which
+    // are tied to the canvas size, are left out; the vignette is included.
+    fn render_to_buffer(
+        &mut self,
+        time: f32,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, RenderError> {
+        let (width, height) = (width.clamp(1, 4096), height.clamp(1, 4096));
+        self.refresh_glyph_atlas();
+        let gl = &self.gl;
+
+        let color = gl
+            .create_texture()
+            .ok_or_else(|| RenderError::ContextLost("Failed to create texture".into()))?;
+        gl.bind_texture(GL::TEXTURE_2D, Some(&color));
+        let allocated = gl
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                GL::TEXTURE_2D,
+                0,
+                GL::RGBA as i32,
+                width as i32,
+                height as i32,
+                0,
+                GL::RGBA,
+                GL::UNSIGNED_BYTE,
+                None,
+            );
+        gl.tex_parameteri(GL::TEXTURE_2D, GL::TEXTURE_MIN_FILTER, GL::NEAREST as i32);
+        // Renderbuffer depth works on WebGL 1 without WEBGL_depth_texture
+        let depth = gl.create_renderbuffer();
+        let framebuffer = gl.create_framebuffer();
+        let delete = |gl: &GL| {
+            gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+            gl.delete_framebuffer(framebuffer.as_ref());
+            gl.delete_renderbuffer(depth.as_ref());
+            gl.delete_texture(Some(&color));
+        };
+        if allocated.is_err() {
+            delete(gl);
+            return Err(RenderError::TextureUpload(
+                "Failed to allocate snapshot texture".into(),
+            ));
+        }
+        let (Some(depth_buffer), Some(_)) = (&depth, &framebuffer) else {
+            delete(gl);
+            return Err(RenderError::ContextLost(
+                "Failed to create snapshot framebuffer".into(),
+            ));
+        };
+        gl.bind_renderbuffer(GL::RENDERBUFFER, Some(depth_buffer));
+        gl.renderbuffer_storage(
+            GL::RENDERBUFFER,
+            GL::DEPTH_COMPONENT16,
+            width as i32,
+            height as i32,
+        );
+        gl.bind_framebuffer(GL::FRAMEBUFFER, framebuffer.as_ref());
+        gl.framebuffer_texture_2d(
+            GL::FRAMEBUFFER,
+            GL::COLOR_ATTACHMENT0,
+            GL::TEXTURE_2D,
+            Some(&color),
+            0,
+        );
+        gl.framebuffer_renderbuffer(
+            GL::FRAMEBUFFER,
+            GL::DEPTH_ATTACHMENT,
+            GL::RENDERBUFFER,
+            depth.as_ref(),
+        );
+        let status = gl.check_framebuffer_status(GL::FRAMEBUFFER);
+        if status != GL::FRAMEBUFFER_COMPLETE {
+            delete(gl);
+            return Err(RenderError::Unsupported(format!(
+                "Snapshot framebuffer incomplete (status {status:#x})"
+            )));
+        }
+
+        let clocks = (self.letter_clock.elapsed, self.sphere_clock.elapsed);
+        let projection = self.projection_matrix;
+        self.letter_clock.elapsed = time;
+        self.sphere_clock.elapsed = time;
+        let aspect = width as f32 / height as f32;
+        self.projection_matrix = self.projection(aspect);
+        self.gl.viewport(0, 0, width as i32, height as i32);
+
+        self.draw_scene();
+        if self.vignette_strength > 0.0 {
+            self.draw_vignette();
+        }
+
+        let mut pixels = vec![0; (width * height * 4) as usize];
+        let read = self.gl.read_pixels_with_opt_u8_array(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            GL::RGBA,
+            GL::UNSIGNED_BYTE,
+            Some(&mut pixels),
+        );
+
+        (self.letter_clock.elapsed, self.sphere_clock.elapsed) = clocks;
+        self.projection_matrix = projection;
+        let (canvas_width, canvas_height) = self.canvas_size();
+        self.gl
+            .viewport(0, 0, canvas_width as i32, canvas_height as i32);
+        delete(&self.gl);
+
+        read.map_err(|_| RenderError::TextureUpload("Failed to read snapshot pixels".into()))?;
+        Ok(pixels)
+    }
+
+    // Left and right eye in the two canvas halves, each from a camera shifted sideways
+    // along the view's right axis. The cameras stay parallel, converging at infinity.
+    fn render_stereo(&mut self) {
+        let (width, height) = self.canvas_size();
+        let half = (width / 2).max(1) as i32;
+        let height = height.max(1) as i32;
+
+        let camera_pos = self.camera_pos;
+        let camera_target = self.camera_target;
+        let projection = self.projection_matrix;
+        let m = &self.view_matrix.data;
+        let right = Vec3::new(m[0], m[4], m[8]);
+
+        let aspect = half as f32 / height as f32;
+        self.projection_matrix = self.projection(aspect);
+
+        // Scissor so each eye's clear leaves the other half alone
+        self.gl.enable(GL::SCISSOR_TEST);
+        for (side, x) in [(-0.5, 0), (0.5, half)] {
+            let shift = right.scale(side * self.eye_separation);
+            self.camera_pos = camera_pos.add(shift);
+            self.camera_target = camera_target.add(shift);
+            self.update_view_matrix();
+            self.gl.viewport(x, 0, half, height);
+            self.gl.scissor(x, 0, half, height);
+            self.draw_frame();
+        }
+        self.gl.disable(GL::SCISSOR_TEST);
+
+        self.camera_pos = camera_pos;
+        self.camera_target = camera_target;
+        self.projection_matrix = projection;
+        self.update_view_matrix();
+        self.gl.viewport(0, 0, width as i32, height);
+    }
+
+    fn draw_scene(&self) {
+        let gl = &self.gl;
+        let letter_time = self.letter_clock.elapsed;
+        let sphere_time = self.sphere_clock.elapsed;
+
+        if self.flat_2d {
+            gl.disable(GL::DEPTH_TEST);
+        } else {
+            gl.enable(GL::DEPTH_TEST);
+        }
+
+        gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
+
+        if let Some(stars) = &self.stars {
+            self.draw_stars(stars);
+        }
+
+        // First, so the sphere and letters depth test against the lines
+        if let (true, Some(axes)) = (self.show_axes, &self.axes) {
+            self.draw_axes(axes);
+        }
+
+        if let (TextMode::Ribbon, Some(ribbon)) = (self.text_mode, &self.ribbon) {
+            self.draw_sphere(sphere_time);
+            self.draw_ribbon(ribbon, letter_time);
+            return;
+        }
+
+        let scatter_level = self.explosion.as_ref().map_or(0.0, Explosion::level);
+        let mut draws: Vec<LetterDraw> = self
+            .letters
+            .iter()
+            .enumerate()
+            .map(|(i, letter)| LetterDraw {
+                position: self.letter_position(i, letter_time, scatter_level),
+                glyph: &letter.texture,
+                color: &letter.color,
+                opacity: 1.0 - scatter_level,
+                size: 0.6,
+            })
+            .collect();
+        draws.extend(self.sprites.iter().map(|sprite| LetterDraw {
+            position: sprite.position,
+            glyph: &sprite.texture,
+            color: &sprite.color,
+            opacity: 1.0,
+            size: sprite.scale,
+        }));
+
+        // A translucent sphere needs every letter blended back to front, with the sphere
+        // slotted in at its own depth below
+        let sphere_translucent = self.edge_fade > 0.0;
+        if sphere_translucent {
+            draws.sort_by(|a, b| {
+                self.view_depth(a.position)
+                    .total_cmp(&self.view_depth(b.position))
+            });
+        } else if self.flat_2d || !self.alpha_test_only {
+            // Paint the farthest letters first. Blended glyph edges still write depth, so a
+            // near letter drawn first would hide the far one behind its transparent margin
+            // and leave a halo; in flat mode letters layer purely by draw order anyway.
+            // Alpha-test-only letters have no partial edges and need no order.
+            let camera_pos = self.camera_pos;
+            let distance_sq = |pos: Vec3| {
+                let d = pos.sub(camera_pos);
+                d.dot(d)
+            };
+            draws.sort_by(|a, b| distance_sq(b.position).total_cmp(&distance_sq(a.position)));
+        }
+
+        // A translucent sphere edge only shows letters behind it if they are drawn first
+        if self.interleaved_draw || sphere_translucent {
+            // Letters behind the sphere's center go first, the rest after the sphere
+            let center_depth = self.view_depth(self.sphere_offset);
+            let (behind, in_front): (Vec<LetterDraw>, Vec<LetterDraw>) = draws
+                .into_iter()
+                .partition(|draw| self.view_depth(draw.position) < center_depth);
+            self.draw_letters(&behind);
+            self.draw_sphere(sphere_time);
+            self.draw_trails(letter_time, scatter_level);
+            self.draw_letters(&in_front);
+        } else {
+            self.draw_sphere(sphere_time);
+            self.draw_trails(letter_time, scatter_level);
+            self.draw_letters(&draws);
+        }
+    }
+
+    // Where letter `index` is drawn at `time`: its orbit, pushed out by the scatter and
+    // nudged toward the camera by its z offset
+    fn letter_position(&self, index: usize, time: f32, scatter_level: f32) -> Vec3 {
+        let letter = &self.letters[index];
+        let mut orbit = letter.position_at(time, &self.orbit_effects);
+        if scatter_level > 0.0 {
+            let (distance, lift) = self.scatter.get(index).copied().unwrap_or_default();
+            let outward = orbit.normalize().scale(distance * scatter_level);
+            orbit = orbit
+                .add(outward)
+                .add(Vec3::new(0.0, lift * scatter_level, 0.0));
+        }
+        let toward_camera = self.camera_pos.sub(orbit).normalize();
+        orbit.add(toward_camera.scale(letter.z_offset))
+    }
+
+    // Index of the letter billboard under CSS pixel (`screen_x`, `screen_y`) of the canvas
+    // at letter time `time`, the one nearest the camera when several overlap
+    fn pick(&self, screen_x: f32, screen_y: f32, time: f32) -> Option<usize> {
+        if let (TextMode::Ribbon, Some(_)) = (self.text_mode, &self.ribbon) {
+            return None;
+        }
+        let width = self.canvas.client_width() as f32;
+        let height = self.canvas.client_height() as f32;
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        let ndc_x = screen_x / width * 2.0 - 1.0;
+        let ndc_y = 1.0 - screen_y / height * 2.0;
+
+        // The click ray runs from the near plane to the far plane
+        let inverse = self
+            .projection_matrix
+            .multiply(self.view_matrix)
+            .inverse()?;
+        let unproject = |z: f32| {
+            let ndc = Vec4 {
+                x: ndc_x,
+                y: ndc_y,
+                z,
+                w: 1.0,
+            };
+            inverse.transform_vec4(ndc).perspective_divide()
+        };
+        let near = unproject(-1.0)?;
+        let dir = unproject(1.0)?.sub(near).normalize();
+
+        let scatter_level = self.explosion.as_ref().map_or(0.0, Explosion::level);
+        let camera_up = self.camera_up();
+        (0..self.letters.len())
+            .filter_map(|i| {
+                let position = self.letter_position(i, time, scatter_level);
+                let model = self.letter_model(position, &self.letters[i].texture, camera_up, 0.6);
+                ray_hits_quad(&model, near, dir).map(|distance| (i, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    // View-space Z of a world position; more negative is farther from the camera
+    fn view_depth(&self, pos: Vec3) -> f32 {
+        let m = &self.view_matrix.data;
+        m[2] * pos.x + m[6] * pos.y + m[10] * pos.z + m[14]
+    }
+
+    fn draw_sphere(&self, time: f32) {
+        let gl = &self.gl;
+
+        let o = self.sphere_offset;
+        let model_matrix = Mat4::translation(o.x, o.y, o.z)
+            .multiply(Mat4::scale_uniform(self.sphere_scale))
+            .multiply(self.sphere_tilt)
+            .multiply(Mat4::rotation_y(self.sphere_rotation(time)));
+
+        gl.use_program(Some(&self.sphere_program));
+        let SphereLocations {
+            u_model,
+            u_normal_matrix,
+            u_view,
+            u_projection,
+            u_light_pos,
+            u_light_color,
+            u_color_pole,
+            u_color_equator,
+            u_view_pos,
+            u_edge_fade,
+            u_texture,
+            u_textured,
+            a_position,
+            a_normal,
+            u_fog_color,
+            u_fog_density,
+            u_srgb,
+            u_ambient,
+            u_diffuse_strength,
+            u_specular_strength,
+            u_shininess,
+            a_uv,
+        } = &self.sphere_locations;
+        let (a_position, a_normal, a_uv) = (*a_position, *a_normal, *a_uv);
+
+        gl.uniform_matrix4fv_with_f32_array(u_model.as_ref(), false, &model_matrix.data);
+        gl.uniform_matrix3fv_with_f32_array(
+            u_normal_matrix.as_ref(),
+            false,
+            &model_matrix.normal_matrix(),
+        );
+        gl.uniform_matrix4fv_with_f32_array(u_view.as_ref(), false, &self.view_matrix.data);
+        gl.uniform_matrix4fv_with_f32_array(
+            u_projection.as_ref(),
+            false,
+            &self.projection_matrix.data,
+        );
+        let (positions, colors) =
+            light_uniforms(&self.light_positions(), &self.sphere_appearance.lights);
+        gl.uniform3fv_with_f32_array(u_light_pos.as_ref(), &positions);
+        gl.uniform3fv_with_f32_array(u_light_color.as_ref(), &colors);
+        let pole = self.sphere_appearance.color_pole;
+        let equator = self.sphere_appearance.color_equator;
+        gl.uniform3f(u_color_pole.as_ref(), pole.x, pole.y, pole.z);
+        gl.uniform3f(u_color_equator.as_ref(), equator.x, equator.y, equator.z);
+        gl.uniform3f(
+            u_view_pos.as_ref(),
+            self.camera_pos.x,
+            self.camera_pos.y,
+            self.camera_pos.z,
+        );
+        gl.uniform1f(u_edge_fade.as_ref(), self.edge_fade);
+        gl.uniform1f(u_srgb.as_ref(), if self.srgb { 1.0 } else { 0.0 });
+        let [ambient, diffuse, specular, shininess] = self.material.uniforms();
+        gl.uniform1f(u_ambient.as_ref(), ambient);
+        gl.uniform1f(u_diffuse_strength.as_ref(), diffuse);
+        gl.uniform1f(u_specular_strength.as_ref(), specular);
+        gl.uniform1f(u_shininess.as_ref(), shininess);
+        self.apply_fog(u_fog_color, u_fog_density);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.sphere_vertex_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_position);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.sphere_normal_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_normal, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_normal);
+
+        // Meshes without UVs keep their gradient even when a texture is set
+        let textured = match (&self.sphere_texture, &self.sphere_uv_buffer) {
+            (Some(texture), Some(uv_buffer)) => {
+                gl.bind_buffer(GL::ARRAY_BUFFER, Some(uv_buffer));
+                gl.vertex_attrib_pointer_with_i32(a_uv, 2, GL::FLOAT, false, 0, 0);
+                gl.enable_vertex_attrib_array(a_uv);
+                gl.active_texture(GL::TEXTURE0);
+                gl.bind_texture(GL::TEXTURE_2D, Some(texture));
+                true
+            }
+            _ => false,
+        };
+        gl.uniform1i(u_texture.as_ref(), 0);
+        gl.uniform1f(u_textured.as_ref(), if textured { 1.0 } else { 0.0 });
+
+        // Push the sphere back in depth so labels lying on its surface don't z-fight
+        let polygon_offset = self.polygon_offset_factor != 0.0 || self.polygon_offset_units != 0.0;
+        if polygon_offset {
+            gl.enable(GL::POLYGON_OFFSET_FILL);
+            gl.polygon_offset(self.polygon_offset_factor, self.polygon_offset_units);
+        }
+
+        // Without depth testing the far hemisphere would paint over the near one, so cull it.
+        // Sphere triangles wind clockwise when seen from outside.
+        if self.flat_2d {
+            gl.enable(GL::CULL_FACE);
+            gl.cull_face(GL::FRONT);
+        }
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.sphere_index_buffer));
+        gl.draw_elements_with_i32(
+            GL::TRIANGLES,
+            self.sphere_index_count,
+            self.sphere_index_type,
+            0,
+        );
+
+        if self.flat_2d {
+            gl.disable(GL::CULL_FACE);
+        }
+        if polygon_offset {
+            gl.disable(GL::POLYGON_OFFSET_FILL);
+        }
+        if textured {
+            gl.disable_vertex_attrib_array(a_uv);
+        }
+    }
+
+    fn draw_letters(&self, draws: &[LetterDraw]) {
+        let camera_up = self.camera_up();
+        let mut dots = Vec::new();
+        let mut quads = Vec::new();
+
+        for draw in draws {
+            if self.letter_lod {
+                let size_px = self.projected_size_px(draw.position, draw.size);
+                if size_px < self.lod_threshold_px {
+                    dots.push((draw, size_px));
+                    continue;
+                }
+            }
+
+            let opacity = draw.opacity * self.near_fade_opacity(draw.position);
+            if opacity <= 0.0 {
+                continue;
+            }
+
+            quads.push(LetterQuad {
+                model: self.letter_model(draw.position, draw.glyph, camera_up, draw.size),
+                opacity,
+                glyph: draw.glyph,
+            });
+        }
+
+        if self.alpha_test_only {
+            self.gl.disable(GL::BLEND);
+        }
+        self.draw_quad_batch(&quads);
+        if self.alpha_test_only {
+            self.gl.enable(GL::BLEND);
+        }
+
+        if !dots.is_empty() {
+            self.draw_dots(&dots);
+        }
+    }
+
+    // Billboard for `glyph` at `position`, `size` world units tall for a plain glyph cell
+    fn letter_model(
+        &self,
+        position: Vec3,
+        glyph: &GlyphTexture,
+        camera_up: Vec3,
+        size: f32,
+    ) -> Mat4 {
+        let scale = size * glyph.extent;
+        let model = match self.billboard_mode {
+            BillboardMode::WorldUp => Mat4::billboard(position, self.camera_pos, scale),
+            BillboardMode::ScreenUp => {
+                Mat4::billboard_with_up(position, self.camera_pos, camera_up, scale)
+            }
+        };
+        // Fit non-square sprites inside the square footprint by shrinking the short side
+        let aspect = glyph.aspect.get();
+        if glyph.widen {
+            model.multiply(Mat4::scale(aspect, 1.0, 1.0))
+        } else if aspect > 1.0 {
+            model.multiply(Mat4::scale(1.0, 1.0 / aspect, 1.0))
+        } else if aspect < 1.0 {
+            model.multiply(Mat4::scale(aspect, 1.0, 1.0))
+        } else {
+            model
+        }
+    }
+
+    fn draw_quad_batch(&self, quads: &[LetterQuad]) {
+        match &self.instancing {
+            Some(instancing) => self.draw_quads_instanced(instancing, quads),
+            None => self.draw_quads(quads),
+        }
+    }
+
+    // Fading, shrinking ghosts of each glyph at its recent orbit positions. They don't
+    // write depth, so they never hide the letters or each other.
+    fn draw_trails(&self, time: f32, scatter_level: f32) {
+        // Scattered letters have left their orbits, so past orbit points would be wrong
+        if self.trail_length == 0 || scatter_level > 0.0 {
+            return;
+        }
+        let camera_up = self.camera_up();
+        let steps = self.trail_length as f32 + 1.0;
+        let mut quads = Vec::new();
+        for letter in &self.letters {
+            for step in 1..=self.trail_length {
+                let fade = 1.0 - step as f32 / steps;
+                let orbit =
+                    letter.position_at(time - step as f32 * TRAIL_SPACING, &self.orbit_effects);
+                let toward_camera = self.camera_pos.sub(orbit).normalize();
+                let position = orbit.add(toward_camera.scale(letter.z_offset));
+                let opacity = TRAIL_OPACITY * fade * self.near_fade_opacity(position);
+                if opacity <= 0.0 {
+                    continue;
+                }
+                quads.push(LetterQuad {
+                    model: self.letter_model(
+                        position,
+                        &letter.texture,
+                        camera_up,
+                        0.6 * (0.5 + 0.5 * fade),
+                    ),
+                    opacity,
+                    glyph: &letter.texture,
+                });
+            }
+        }
+        self.gl.depth_mask(false);
+        self.draw_quad_batch(&quads);
+        self.gl.depth_mask(true);
+    }
+
+    // One draw call per letter
+    fn draw_quads(&self, quads: &[LetterQuad]) {
+        let gl = &self.gl;
+
+        gl.use_program(Some(&self.text_program));
+
+        let locations = &self.text_locations;
+        let TextLocations {
+            u_model,
+            u_view,
+            u_projection,
+            u_texture,
+            u_alpha_threshold,
+            u_opacity,
+            ..
+        } = locations;
+
+        gl.uniform_matrix4fv_with_f32_array(u_view.as_ref(), false, &self.view_matrix.data);
+        gl.uniform_matrix4fv_with_f32_array(
+            u_projection.as_ref(),
+            false,
+            &self.projection_matrix.data,
+        );
+        gl.uniform1i(u_texture.as_ref(), 0);
+        gl.uniform1f(u_alpha_threshold.as_ref(), self.text_alpha_threshold());
+        self.apply_fog(&locations.u_fog_color, &locations.u_fog_density);
+
+        let (a_position, a_uv) = (locations.a_position, locations.a_uv);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_vertex_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_position);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_uv_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_uv, 2, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_uv);
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.quad_index_buffer));
+        gl.active_texture(GL::TEXTURE0);
+
+        for quad in quads {
+            gl.uniform1f(u_opacity.as_ref(), quad.opacity);
+            gl.uniform_matrix4fv_with_f32_array(u_model.as_ref(), false, &quad.model.data);
+            gl.bind_texture(GL::TEXTURE_2D, Some(&quad.glyph.texture));
+            gl.draw_elements_with_i32(GL::TRIANGLES, 6, GL::UNSIGNED_SHORT, 0);
+        }
+    }
+
+    // One draw call per run of neighboring letters that sample the same texture, in the
+    // given back-to-front order: blended letters write depth, so reordering them would
+    // bring back the edge halos that sorting removes. Letter glyphs all come from the
+    // atlas, so a pass is a single call; only image sprites, free sprites and glyphs the
+    // atlas had no room for split it. The default "[wasm-text-sphere]" went from 18 calls
+    // per pass (the built-in palette gives each letter its own texture) to 1, and a
+    // 500-letter string from 500 to 1; trails add one more pass.
+    fn draw_quads_instanced(&self, instancing: &Instancing, quads: &[LetterQuad]) {
+        let gl = &self.gl;
+        let locations = &self.text_instanced_locations;
+
+        let atlas = self.glyph_atlas.as_ref();
+        let atlas_rect = |quad: &LetterQuad| atlas?.rect(quad.glyph);
+        let data: Vec<f32> = quads
+            .iter()
+            .flat_map(|quad| {
+                let rect = atlas_rect(quad).unwrap_or(FULL_UV_RECT);
+                quad.model
+                    .data
+                    .into_iter()
+                    .chain([quad.opacity])
+                    .chain(rect)
+            })
+            .collect();
+
+        gl.use_program(Some(&self.text_instanced_program));
+        let TextLocations {
+            u_view,
+            u_projection,
+            u_texture,
+            u_alpha_threshold,
+            ..
+        } = locations;
+        gl.uniform_matrix4fv_with_f32_array(u_view.as_ref(), false, &self.view_matrix.data);
+        gl.uniform_matrix4fv_with_f32_array(
+            u_projection.as_ref(),
+            false,
+            &self.projection_matrix.data,
+        );
+        gl.uniform1i(u_texture.as_ref(), 0);
+        gl.uniform1f(u_alpha_threshold.as_ref(), self.text_alpha_threshold());
+        self.apply_fog(&locations.u_fog_color, &locations.u_fog_density);
+
+        let (a_position, a_uv) = (locations.a_position, locations.a_uv);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_vertex_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_position);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_uv_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_uv, 2, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_uv);
+
+        let instance_attribs = &locations.instance_attribs;
+        update_buffer(gl, &self.instance_buffer, &data);
+        for &(location, _) in instance_attribs {
+            gl.enable_vertex_attrib_array(location);
+            instancing.divisor(location, 1);
+        }
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.quad_index_buffer));
+        gl.active_texture(GL::TEXTURE0);
+
+        let stride = (INSTANCE_STRIDE * 4) as i32;
+        // Atlas glyphs share key 0, which no glyph address can be
+        let texture_key = |quad: &LetterQuad| match atlas_rect(quad) {
+            Some(_) => 0,
+            None => glyph_address(quad.glyph),
+        };
+        for (first, len) in consecutive_runs(quads, texture_key) {
+            // Point the instance attributes at this run's first instance
+            let mut offset = (first * INSTANCE_STRIDE * 4) as i32;
+            for &(location, size) in instance_attribs {
+                gl.vertex_attrib_pointer_with_i32(location, size, GL::FLOAT, false, stride, offset);
+                offset += size * 4;
+            }
+            let texture = match (texture_key(&quads[first]), atlas) {
+                (0, Some(atlas)) => atlas.texture.as_ref(),
+                _ => Some(&quads[first].glyph.texture),
+            };
+            gl.bind_texture(GL::TEXTURE_2D, texture);
+            instancing.draw_quads(len as i32);
+        }
+
+        // Other programs expect plain per-vertex attributes
+        for &(location, _) in instance_attribs {
+            instancing.divisor(location, 0);
+            gl.disable_vertex_attrib_array(location);
+        }
+    }
+
+    fn draw_ribbon(&self, ribbon: &Ribbon, time: f32) {
+        let gl = &self.gl;
+
+        let positions = ribbon_positions(
+            &self.letters[0],
+            self.letters.len(),
+            time,
+            &self.orbit_effects,
+            RIBBON_SEGMENTS,
+        );
+        update_buffer(gl, &ribbon.position_buffer, &positions);
+
+        gl.use_program(Some(&self.text_program));
+
+        let locations = &self.text_locations;
+        let TextLocations {
+            u_model,
+            u_view,
+            u_projection,
+            u_texture,
+            u_alpha_threshold,
+            u_opacity,
+            ..
+        } = locations;
+
+        // Positions are already in world space
+        let identity = Mat4::identity();
+        gl.uniform_matrix4fv_with_f32_array(u_model.as_ref(), false, &identity.data);
+        gl.uniform_matrix4fv_with_f32_array(u_view.as_ref(), false, &self.view_matrix.data);
+        gl.uniform_matrix4fv_with_f32_array(
+            u_projection.as_ref(),
+            false,
+            &self.projection_matrix.data,
+        );
+        gl.uniform1i(u_texture.as_ref(), 0);
+        gl.uniform1f(u_alpha_threshold.as_ref(), self.text_alpha_threshold());
+        self.apply_fog(&locations.u_fog_color, &locations.u_fog_density);
+        gl.uniform1f(u_opacity.as_ref(), 1.0);
+
+        let (a_position, a_uv) = (locations.a_position, locations.a_uv);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&ribbon.position_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_position);
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&ribbon.uv_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_uv, 2, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_uv);
+
+        gl.active_texture(GL::TEXTURE0);
+        gl.bind_texture(GL::TEXTURE_2D, Some(&ribbon.texture));
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&ribbon.index_buffer));
+        gl.draw_elements_with_i32(GL::TRIANGLES, ribbon.index_count, GL::UNSIGNED_SHORT, 0);
+    }
+
+    // Multiplies the finished frame's color by a radial falloff. Alpha is left alone so a
+    // transparent background stays transparent instead of turning dark.
+    fn draw_vignette(&self) {
+        let gl = &self.gl;
+
+        gl.use_program(Some(&self.vignette_program));
+        let locations = &self.vignette_locations;
+        gl.uniform1f(locations.u_strength.as_ref(), self.vignette_strength);
+        gl.uniform1f(locations.u_radius.as_ref(), self.vignette_radius);
+
+        let a_position = locations.a_position;
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_vertex_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_position);
+
+        gl.disable(GL::DEPTH_TEST);
+        gl.blend_func_separate(GL::ZERO, GL::SRC_COLOR, GL::ZERO, GL::ONE);
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.quad_index_buffer));
+        gl.draw_elements_with_i32(GL::TRIANGLES, 6, GL::UNSIGNED_SHORT, 0);
+
+        gl.blend_func_separate(
+            GL::SRC_ALPHA,
+            GL::ONE_MINUS_SRC_ALPHA,
+            GL::ONE,
+            GL::ONE_MINUS_SRC_ALPHA,
+        );
+        if !self.flat_2d {
+            gl.enable(GL::DEPTH_TEST);
+        }
+    }
+
+    // Composites the offscreen scene into the current viewport with depth-based blur
+    fn draw_depth_of_field(&self, target: &OffscreenTarget) {
+        let gl = &self.gl;
+        let locations = &self.dof_locations;
+
+        gl.use_program(Some(&self.dof_program));
+        gl.uniform1i(locations.u_color.as_ref(), 0);
+        gl.uniform1i(locations.u_depth.as_ref(), 1);
+        gl.uniform2f(
+            locations.u_resolution.as_ref(),
+            target.width as f32,
+            target.height as f32,
+        );
+        gl.uniform1f(locations.u_near.as_ref(), CAMERA_NEAR);
+        gl.uniform1f(locations.u_far.as_ref(), CAMERA_FAR);
+        gl.uniform1f(locations.u_focus.as_ref(), self.dof_focus);
+        gl.uniform1f(locations.u_strength.as_ref(), self.dof_strength);
+
+        gl.active_texture(GL::TEXTURE1);
+        gl.bind_texture(GL::TEXTURE_2D, Some(&target.depth));
+        gl.active_texture(GL::TEXTURE0);
+        gl.bind_texture(GL::TEXTURE_2D, Some(&target.color));
+
+        let a_position = locations.a_position;
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_vertex_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_position);
+
+        // The composite replaces the frame outright
+        gl.disable(GL::DEPTH_TEST);
+        gl.disable(GL::BLEND);
+        gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
+
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.quad_index_buffer));
+        gl.draw_elements_with_i32(GL::TRIANGLES, 6, GL::UNSIGNED_SHORT, 0);
+
+        gl.enable(GL::BLEND);
+        if !self.flat_2d {
+            gl.enable(GL::DEPTH_TEST);
+        }
+    }
+
+    // Bright pass into `ping`, horizontal blur into `pong`, vertical blur back into `ping`,
+    // then the scene plus glow onto the default framebuffer
+    fn draw_bloom(&self, targets: &BloomTargets) {
+        let gl = &self.gl;
+        let (width, height) = (targets.width as f32, targets.height as f32);
+
+        gl.disable(GL::DEPTH_TEST);
+        gl.disable(GL::BLEND);
+        gl.active_texture(GL::TEXTURE0);
+
+        let locations = &self.bloom_bright_locations;
+        gl.use_program(Some(&self.bloom_bright_program));
+        gl.uniform1i(locations.u_color.as_ref(), 0);
+        gl.uniform2f(locations.u_resolution.as_ref(), width, height);
+        gl.uniform1f(locations.u_threshold.as_ref(), self.bloom_threshold);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, Some(&targets.ping.framebuffer));
+        gl.bind_texture(GL::TEXTURE_2D, Some(&targets.scene.color));
+        self.draw_fullscreen_quad(locations.a_position);
+
+        let locations = &self.bloom_blur_locations;
+        gl.use_program(Some(&self.bloom_blur_program));
+        gl.uniform1i(locations.u_color.as_ref(), 0);
+        gl.uniform2f(locations.u_resolution.as_ref(), width, height);
+        for (source, dest, direction) in [
+            (&targets.ping, &targets.pong, (BLOOM_SPREAD, 0.0)),
+            (&targets.pong, &targets.ping, (0.0, BLOOM_SPREAD)),
+        ] {
+            gl.uniform2f(locations.u_direction.as_ref(), direction.0, direction.1);
+            gl.bind_framebuffer(GL::FRAMEBUFFER, Some(&dest.framebuffer));
+            gl.bind_texture(GL::TEXTURE_2D, Some(&source.color));
+            self.draw_fullscreen_quad(locations.a_position);
+        }
+
+        let locations = &self.bloom_composite_locations;
+        gl.use_program(Some(&self.bloom_composite_program));
+        gl.uniform1i(locations.u_color.as_ref(), 0);
+        gl.uniform1i(locations.u_bloom.as_ref(), 1);
+        gl.uniform2f(locations.u_resolution.as_ref(), width, height);
+        gl.uniform1f(locations.u_intensity.as_ref(), self.bloom_intensity);
+        gl.bind_framebuffer(GL::FRAMEBUFFER, None);
+        gl.active_texture(GL::TEXTURE1);
+        gl.bind_texture(GL::TEXTURE_2D, Some(&targets.ping.color));
+        gl.active_texture(GL::TEXTURE0);
+        gl.bind_texture(GL::TEXTURE_2D, Some(&targets.scene.color));
+        self.draw_fullscreen_quad(locations.a_position);
+
+        gl.enable(GL::BLEND);
+        if !self.flat_2d {
+            gl.enable(GL::DEPTH_TEST);
+        }
+    }
+
+    // Without depth writes, so everything drawn afterwards covers the stars
+    fn draw_stars(&self, stars: &StarField) {
+        let gl = &self.gl;
+        gl.use_program(Some(&stars.program));
+        gl.uniform_matrix4fv_with_f32_array(stars.u_view.as_ref(), false, &self.view_matrix.data);
+        gl.uniform_matrix4fv_with_f32_array(
+            stars.u_projection.as_ref(),
+            false,
+            &self.projection_matrix.data,
+        );
+        // Sizes are in CSS pixels, 1-2 from faint to bright
+        let scale = self.pixel_ratio() * self.resolution_scale;
+        gl.uniform1f(stars.u_point_scale.as_ref(), scale);
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&stars.buffer));
+        let stride = 4 * 4;
+        gl.vertex_attrib_pointer_with_i32(stars.a_position, 3, GL::FLOAT, false, stride, 0);
+        gl.enable_vertex_attrib_array(stars.a_position);
+        gl.vertex_attrib_pointer_with_i32(stars.a_brightness, 1, GL::FLOAT, false, stride, 3 * 4);
+        gl.enable_vertex_attrib_array(stars.a_brightness);
+        gl.depth_mask(false);
+        gl.draw_arrays(GL::POINTS, 0, stars.count);
+        gl.depth_mask(true);
+        // Other programs read a different attribute layout
+        gl.disable_vertex_attrib_array(stars.a_brightness);
+    }
+
+    fn draw_axes(&self, axes: &AxesHelper) {
+        let gl = &self.gl;
+        gl.use_program(Some(&axes.program));
+        gl.uniform_matrix4fv_with_f32_array(axes.u_view.as_ref(), false, &self.view_matrix.data);
+        gl.uniform_matrix4fv_with_f32_array(
+            axes.u_projection.as_ref(),
+            false,
+            &self.projection_matrix.data,
+        );
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&axes.buffer));
+        let stride = 6 * 4;
+        gl.vertex_attrib_pointer_with_i32(axes.a_position, 3, GL::FLOAT, false, stride, 0);
+        gl.enable_vertex_attrib_array(axes.a_position);
+        gl.vertex_attrib_pointer_with_i32(axes.a_color, 3, GL::FLOAT, false, stride, 3 * 4);
+        gl.enable_vertex_attrib_array(axes.a_color);
+        gl.draw_arrays(GL::LINES, 0, 6);
+        // Other programs read colors from a different attribute layout
+        gl.disable_vertex_attrib_array(axes.a_color);
+    }
+
+    // Covers the current viewport with the unit quad
+    fn draw_fullscreen_quad(&self, a_position: u32) {
+        let gl = &self.gl;
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_vertex_buffer));
+        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(a_position);
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.quad_index_buffer));
+        gl.draw_elements_with_i32(GL::TRIANGLES, 6, GL::UNSIGNED_SHORT, 0);
+    }
+
+    fn apply_fog(
+        &self,
+        color: &Option<WebGlUniformLocation>,
+        density: &Option<WebGlUniformLocation>,
+    ) {
+        let [r, g, b, _] = self.background;
+        let fog = self.fog_color.unwrap_or(Vec3::new(r, g, b));
+        self.gl.uniform3f(color.as_ref(), fog.x, fog.y, fog.z);
+        self.gl.uniform1f(density.as_ref(), self.fog_density);
+    }
+
+    // LOD path: every tiny letter in one GL::POINTS call with no texture binds
+    fn draw_dots(&self, dots: &[(&LetterDraw, f32)]) {
+        let gl = &self.gl;
+
+        // Glyphs fill roughly 70% of their cell
+        let mut data = Vec::with_capacity(dots.len() * 7);
+        for (draw, size_px) in dots {
+            let [r, g, b] = parse_hex_color(draw.color).unwrap_or([1.0, 1.0, 1.0]);
+            let p = draw.position;
+            data.extend_from_slice(&[p.x, p.y, p.z, r, g, b, (size_px * 0.7).max(1.0)]);
+        }
+
+        gl.use_program(Some(&self.dot_program));
+
+        let DotLocations {
+            u_view,
+            u_projection,
+            a_position,
+            a_color,
+            a_size,
+            u_fog_color,
+            u_fog_density,
+        } = &self.dot_locations;
+        let (a_position, a_color, a_size) = (*a_position, *a_color, *a_size);
+        self.apply_fog(u_fog_color, u_fog_density);
+        gl.uniform_matrix4fv_with_f32_array(u_view.as_ref(), false, &self.view_matrix.data);
+        gl.uniform_matrix4fv_with_f32_array(
+            u_projection.as_ref(),
+            false,
+            &self.projection_matrix.data,
+        );
+
+        update_buffer(gl, &self.dot_buffer, &data);
+        let stride = 7 * 4;
+        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, stride, 0);
+        gl.enable_vertex_attrib_array(a_position);
+        gl.vertex_attrib_pointer_with_i32(a_color, 3, GL::FLOAT, false, stride, 3 * 4);
+        gl.enable_vertex_attrib_array(a_color);
+        gl.vertex_attrib_pointer_with_i32(a_size, 1, GL::FLOAT, false, stride, 6 * 4);
+        gl.enable_vertex_attrib_array(a_size);
+
+        gl.draw_arrays(GL::POINTS, 0, dots.len() as i32);
+
+        // The other programs use two attributes; don't leave a third pointing at this buffer
+        gl.disable_vertex_attrib_array(a_position);
+        gl.disable_vertex_attrib_array(a_color);
+        gl.disable_vertex_attrib_array(a_size);
+    }
+}
+
+// Release every GPU resource the scene owns, so recreating it doesn't leak. Page
+// listeners only hold the App weakly, so this runs once `WasmSphere::destroy` or `free`
+// has released the last handle (including the one `sphere()` hands out).
+impl Drop for App {
+    fn drop(&mut self) {
+        // A lost context has already freed everything, and reports errors for each call
+        if self.gl.is_context_lost() {
+            return;
+        }
+
+        self.delete_ribbon();
+        self.clear_sprites();
+        if let Some(target) = self.dof_target.take() {
+            target.delete(&self.gl);
+        }
+        if let Some(targets) = self.bloom_targets.take() {
+            targets.delete(&self.gl);
+        }
+        if let Some(axes) = self.axes.take() {
+            axes.delete(&self.gl);
+        }
+        if let Some(stars) = self.stars.take() {
+            stars.delete(&self.gl);
+        }
+        if let Some(buffer) = self.sphere_uv_buffer.take() {
+            self.gl.delete_buffer(Some(&buffer));
+        }
+        if let Some(texture) = self.sphere_texture.take() {
+            self.gl.delete_texture(Some(&texture));
+        }
+
+        let gl = &self.gl;
+        for program in [
+            &self.sphere_program,
+            &self.text_program,
+            &self.text_instanced_program,
+            &self.dot_program,
+            &self.vignette_program,
+            &self.dof_program,
+            &self.bloom_bright_program,
+            &self.bloom_blur_program,
+            &self.bloom_composite_program,
+        ] {
+            gl.delete_program(Some(program));
+        }
+        for buffer in [
+            &self.dot_buffer,
+            &self.instance_buffer,
+            &self.sphere_vertex_buffer,
+            &self.sphere_normal_buffer,
+            &self.sphere_index_buffer,
+            &self.quad_vertex_buffer,
+            &self.quad_uv_buffer,
+            &self.quad_index_buffer,
+        ] {
+            gl.delete_buffer(Some(buffer));
+        }
+
+        if let Some(atlas) = self.glyph_atlas.take() {
+            atlas.delete(gl);
+        }
+        // Letters share textures through the cache, so deleting the cache covers them all
+        self.letters.clear();
+        let cache = std::mem::take(&mut self.texture_cache);
+        for glyph in cache.textures.values().chain(cache.images.values()) {
+            gl.delete_texture(Some(&glyph.texture));
+        }
+
+        let error = gl.get_error();
+        if error != GL::NO_ERROR {
+            web_sys::console::error_1(
+                &format!("WebGL error 0x{error:04x} while releasing scene resources").into(),
+            );
+        }
+    }
+}
+
+// JS handle to the running scene
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct WasmSphere {
+    app: Rc<RefCell<App>>,
+    render_loop: Rc<RenderLoop>,
+}
+
+#[wasm_bindgen]
+impl WasmSphere {
+    /// Creates a scene on the canvas with id `canvas_id` without starting its render
+    /// loop. Drive it with `render` from the host's own frame callback, or call `start`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas_id: &str) -> Result<WasmSphere, JsValue> {
+        create_scene(canvas_id)
+    }
+
+    /// Advances the animation to `time_ms` (a requestAnimationFrame-style timestamp) and
+    /// draws one frame. Meant for hosts that run their own loop instead of `start`.
+    pub fn render(&self, time_ms: f64) {
+        render_frame(&self.app, time_ms);
+    }
+
+    /// Stops the render loop entirely; `start` resumes without an animation jump.
+    pub fn stop(&self) {
+        self.render_loop.stop();
+    }
+
+    /// Restarts the render loop after `stop`.
+    pub fn start(&self) {
+        self.render_loop.start();
+    }
+
+    /// Stops rendering while the canvas is scrolled out of view (IntersectionObserver).
+    /// Only the built-in loop is paused: it resumes on coming back into view if `start`
+    /// was called, and hosts driving `render` themselves are left alone.
+    pub fn set_pause_when_offscreen(&self, enabled: bool) -> Result<(), JsValue> {
+        self.render_loop.set_pause_when_offscreen(enabled)
+    }
+
+    /// Stops the scene and removes its page listeners, then frees this handle. The WebGL
+    /// resources are released once every other handle to the scene (from `sphere()` or
+    /// `clone`) is freed too; `free()` alone does the same for the last handle.
+    pub fn destroy(self) {
+        self.render_loop.teardown();
+        SPHERE.with(|s| {
+            let mut s = s.borrow_mut();
+            if s.as_ref().is_some_and(|s| Rc::ptr_eq(&s.app, &self.app)) {
+                *s = None;
+            }
+        });
+    }
+
+    /// Where world point (`x`, `y`, `z`) lands on the canvas as `[x, y]` in CSS pixels from
+    /// its top-left corner, e.g. for positioning HTML overlays; undefined when the point
+    /// is behind the camera.
+    pub fn project_to_screen(&self, x: f32, y: f32, z: f32) -> Option<Vec<f32>> {
+        let (sx, sy) = self.app.borrow().project_to_screen(Vec3::new(x, y, z))?;
+        Some(vec![sx, sy])
+    }
+
+    /// Current canvas backing-store size as `[width, height]` in device pixels.
+    pub fn get_canvas_size(&self) -> Vec<u32> {
+        let (width, height) = self.app.borrow().canvas_size();
+        vec![width, height]
+    }
+
+    /// Alias of `set_time_scale`.
+    pub fn set_speed(&self, speed: f32) {
+        self.app.borrow_mut().set_time_scale(speed);
+    }
+
+    /// Sets the overall animation speed (1 = normal), easing in over the ramp duration so
+    /// there is no visible snap. 0 freezes the animation (independently of `set_paused`);
+    /// negative values run it backwards.
+    pub fn set_time_scale(&self, scale: f32) {
+        self.app.borrow_mut().set_time_scale(scale);
+    }
+
+    /// Like `set_time_scale` but takes effect at once, without smoothing.
+    pub fn set_time_scale_immediate(&self, scale: f32) {
+        self.app.borrow_mut().set_time_scale_immediate(scale);
+    }
+
+    /// Multiplies how fast the letters orbit (1 = normal, negative reverses), on top of
+    /// the overall speed. Changes apply smoothly from the current positions.
+    pub fn set_orbit_speed(&self, multiplier: f32) {
+        self.app.borrow_mut().set_orbit_speed(multiplier);
+    }
+
+    /// Multiplies how fast the sphere spins (1 = normal, negative reverses), on top of the
+    /// overall speed.
+    pub fn set_sphere_spin_speed(&self, multiplier: f32) {
+        self.app.borrow_mut().set_sphere_spin_speed(multiplier);
+    }
+
+    /// Seconds over which `set_time_scale` changes take effect (0.5 by default); 0
+    /// applies them instantly.
+    pub fn set_speed_ramp_duration(&self, seconds: f32) {
+        self.app.borrow_mut().set_speed_ramp_duration(seconds);
+    }
+
+    /// Makes the camera follow the letter at `index`, watching it from just outside its
+    /// orbit so it stays legible as it moves; the camera eases after it rather than
+    /// snapping. `undefined` returns to the orbit camera as it was, while dragging or
+    /// zooming stops following and carries on from the current view.
+    pub fn focus_letter(&self, index: Option<usize>) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .focus_letter(index)
+            .map_err(JsValue::from)
+    }
+
+    /// Swings the camera around its target to `azimuth` (radians about Y, from +Z toward
+    /// +X) and `elevation` over `duration` seconds, keeping its distance. Dragging or
+    /// zooming stops the swing where it is.
+    pub fn animate_camera_to(&self, azimuth: f32, elevation: f32, duration: f32) {
+        self.app
+            .borrow_mut()
+            .animate_camera_to(azimuth, elevation, duration);
+    }
+
+    /// Plays a one-off camera fly-in over `duration` seconds, easing out to the current
+    /// camera position; disabling it mid-flight jumps to the end.
+    pub fn set_camera_intro(&self, enabled: bool, duration: f32) {
+        self.app.borrow_mut().set_camera_intro(enabled, duration);
+    }
+
+    /// Freezes or resumes the whole animation; the scene keeps rendering while paused.
+    pub fn set_paused(&self, paused: bool) {
+        self.app.borrow_mut().set_paused(paused);
+    }
+
+    /// True while both the letters and the sphere are paused.
+    pub fn is_paused(&self) -> bool {
+        self.app.borrow().is_paused()
+    }
+
+    /// Freezes the orbiting letters; the sphere keeps spinning.
+    pub fn pause_letters(&self) {
+        self.app.borrow_mut().set_letters_paused(true);
+    }
+
+    /// Resumes the letters from where they were paused.
+    pub fn resume_letters(&self) {
+        self.app.borrow_mut().set_letters_paused(false);
+    }
+
+    /// Freezes the sphere's spin; the letters keep orbiting.
+    pub fn pause_sphere(&self) {
+        self.app.borrow_mut().set_sphere_paused(true);
+    }
+
+    /// Resumes the sphere's spin from where it was paused.
+    pub fn resume_sphere(&self) {
+        self.app.borrow_mut().set_sphere_paused(false);
+    }
+
+    /// Removes all orbiting letters and frees their glyph textures.
+    pub fn clear_letters(&self) {
+        self.app.borrow_mut().clear_letters();
+    }
+
+    /// Replaces the orbiting letters with `text`, one glyph per character, evenly spaced on
+    /// the default orbit. Textures no longer used are freed; an empty string clears all.
+    pub fn set_text(&self, text: &str) -> Result<(), JsValue> {
+        self.app.borrow_mut().set_text(text).map_err(JsValue::from)
+    }
+
+    /// Replaces all letters with one per config, each with its own text, color and orbit.
+    /// Letters without a phase are spaced evenly, so configs with only `text` reproduce
+    /// the built-in layout.
+    pub fn set_letters(&self, configs: Vec<OrbitingLetterConfig>) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_letters(&configs)
+            .map_err(JsValue::from)
+    }
+
+    /// Recolors the letters with `colors` (CSS color strings), cycled in order, and uses
+    /// them instead of the built-in palette for text set later. Blank entries keep the
+    /// built-in color for their position; an empty list restores the built-in palette.
+    /// Rings and letters that bring their own colors override it.
+    pub fn set_colors(&self, colors: Vec<String>) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_colors(colors)
+            .map_err(JsValue::from)
+    }
+
+    /// Replaces all letters with one ring per descriptor, each with its own text, radius,
+    /// inclination, speed and colors. Letter indices run through the rings in order.
+    pub fn set_rings(&self, rings: Vec<RingConfig>) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_rings(&rings)
+            .map_err(JsValue::from)
+    }
+
+    /// Deals the characters (or words, in word label mode) of `text` round-robin onto
+    /// `rings`, ignoring their own `text`: with two rings of opposite `speed`, alternate
+    /// letters counter-rotate and interlock.
+    pub fn set_text_across_rings(&self, text: &str, rings: Vec<RingConfig>) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_text_across_rings(text, &rings)
+            .map_err(JsValue::from)
+    }
+
+    /// Turns the whole scene (sphere and letters) by `angle` radians about the vertical
+    /// axis, so it starts in a different pose. Default 0.
+    pub fn set_initial_rotation(&self, angle: f32) {
+        self.app.borrow_mut().set_initial_rotation(angle);
+    }
+
+    /// Like `set_initial_rotation` with an angle picked from `seed`; the same seed always
+    /// gives the same pose.
+    pub fn set_random_initial_rotation(&self, seed: u32) {
+        self.app.borrow_mut().set_random_initial_rotation(seed);
+    }
+
+    /// Moves the sphere mesh to (`x`, `y`, `z`) and scales it by `scale`, without
+    /// regenerating it. The letters keep orbiting the origin.
+    pub fn set_sphere_transform(&self, x: f32, y: f32, z: f32, scale: f32) {
+        self.app
+            .borrow_mut()
+            .set_sphere_transform(Vec3::new(x, y, z), scale);
+    }
+
+    /// Tilts the sphere's spin axis by `x` radians about the X axis, then `z` about Z.
+    pub fn set_sphere_tilt(&self, x: f32, z: f32) {
+        self.app.borrow_mut().set_sphere_tilt(x, z);
+    }
+
+    /// Tilts the sphere's spin axis by `angle` radians about an arbitrary axis; a zero
+    /// axis removes the tilt.
+    pub fn set_sphere_tilt_axis(&self, x: f32, y: f32, z: f32, angle: f32) {
+        self.app
+            .borrow_mut()
+            .set_sphere_tilt_axis(Vec3::new(x, y, z), angle);
+    }
+
+    /// Wraps a loaded image (e.g. an equirectangular earth map) around the sphere in place
+    /// of its color gradient; lighting still applies. Only the UV sphere mesh is textured.
+    /// A failed upload (e.g. a cross-origin image without CORS) keeps the current texture.
+    pub fn set_sphere_texture(&self, image: &HtmlImageElement) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_sphere_texture(image)
+            .map_err(JsValue::from)
+    }
+
+    /// Removes the sphere texture, restoring the color gradient.
+    pub fn clear_sphere_texture(&self) {
+        self.app.borrow_mut().clear_sphere_texture();
+    }
+
+    /// Sets the sphere radius in world units, regenerating its mesh.
+    pub fn set_sphere_radius(&self, radius: f32) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_sphere_radius(radius)
+            .map_err(JsValue::from)
+    }
+
+    /// Draws the default sphere at the center.
+    pub fn use_sphere_mesh(&self) -> Result<(), JsValue> {
+        self.set_central_mesh(CentralMesh::Sphere)
+    }
+
+    /// Replaces the sphere with a flat disc facing the camera.
+    pub fn use_disc_mesh(&self, radius: f32, segments: u32) -> Result<(), JsValue> {
+        self.set_central_mesh(CentralMesh::Disc { radius, segments })
+    }
+
+    /// Draws the sphere as a subdivided icosahedron (0-6 subdivisions; 3 is smooth) for
+    /// evenly sized triangles. `use_sphere_mesh` goes back to the latitude/longitude mesh.
+    pub fn use_icosphere_mesh(&self, subdivisions: u32) -> Result<(), JsValue> {
+        self.set_central_mesh(CentralMesh::Icosphere { subdivisions })
+    }
+
+    /// Replaces the sphere with a flat ring (annulus) facing the camera.
+    pub fn use_ring_mesh(&self, inner: f32, outer: f32, segments: u32) -> Result<(), JsValue> {
+        self.set_central_mesh(CentralMesh::Ring {
+            inner,
+            outer,
+            segments,
+        })
+    }
+
+    /// `Flat` gives the central mesh a faceted, low-poly look with one normal per
+    /// triangle; `Smooth` (the default) interpolates them. Flat shading needs three
+    /// vertices per triangle, so very detailed spheres may fail on WebGL 1.
+    pub fn set_shading(&self, shading: Shading) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_shading(shading)
+            .map_err(JsValue::from)
+    }
+
+    /// Derives sphere segment counts from the radius instead of the fixed 32x64.
+    pub fn set_auto_tessellation(&self, enabled: bool) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_auto_tessellation(enabled)
+            .map_err(JsValue::from)
+    }
+
+    /// Sets how many latitude and longitude segments the sphere is built from (32x64 by
+    /// default): fewer for low-end devices, more for smoother silhouettes. Each count is
+    /// at least 3, and longitude is trimmed to cap the mesh at (lat + 1) * (lon + 1) =
+    /// 2^20 vertices, or 65536 on WebGL 1 without `OES_element_index_uint`.
+    /// Turns auto tessellation off; takes effect whenever the UV sphere is drawn.
+    pub fn set_sphere_detail(&self, lat: u32, lon: u32) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_sphere_detail(lat, lon)
+            .map_err(JsValue::from)
+    }
+
+    /// Overrides orbit parameters and/or color of the letter at `index`. Nothing changes
+    /// if the call fails, e.g. for a negative radius or a non-finite value.
+    pub fn set_letter(&self, index: usize, params: LetterParams) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_letter(index, params)
+            .map_err(JsValue::from)
+    }
+
+    /// Adds an image from `url` to the orbit, sized to fit a glyph and keeping its aspect
+    /// ratio. It stays invisible until the image loads; cross-origin images need CORS.
+    pub fn add_image_sprite(&self, url: &str, params: LetterParams) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .add_image_sprite(url, params)
+            .map_err(JsValue::from)
+    }
+
+    /// Places a loaded image at `(x, y, z)`, always facing the camera and `scale` world
+    /// units tall (a letter is 0.6). Sprites are sorted and drawn together with the letters.
+    pub fn add_sprite(
+        &self,
+        image: &HtmlImageElement,
+        x: f32,
+        y: f32,
+        z: f32,
+        scale: f32,
+    ) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .add_sprite(SpriteSource::Image(image), Vec3::new(x, y, z), scale)
+            .map_err(JsValue::from)
+    }
+
+    /// Places a flat square of a CSS hex color (e.g. `"#FF8800"`) at `(x, y, z)`, like
+    /// `add_sprite`.
+    pub fn add_color_sprite(
+        &self,
+        color: &str,
+        x: f32,
+        y: f32,
+        z: f32,
+        scale: f32,
+    ) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .add_sprite(SpriteSource::Color(color), Vec3::new(x, y, z), scale)
+            .map_err(JsValue::from)
+    }
+
+    /// Removes every sprite added with `add_sprite` or `add_color_sprite`.
+    pub fn clear_sprites(&self) {
+        self.app.borrow_mut().clear_sprites();
+    }
+
+    /// Index of the letter drawn under (`x`, `y`), in CSS pixels from the canvas' top-left
+    /// corner (a mouse event's `offsetX`/`offsetY`), or undefined when none is. Overlapping
+    /// letters resolve to the one nearest the camera.
+    pub fn pick(&self, x: f32, y: f32) -> Option<usize> {
+        let app = self.app.borrow();
+        app.pick(x, y, app.letter_clock.elapsed)
+    }
+
+    /// Returns every letter's orbit as `[radius, inclination, phase, angular_velocity]`
+    /// packed back to back.
+    pub fn get_orbit_params(&self) -> Vec<f32> {
+        self.app.borrow().orbit_params()
+    }
+
+    /// Replaces every letter's orbit from the layout returned by `get_orbit_params`;
+    /// the length must be exactly 4 floats per letter, radii non-negative and every value
+    /// finite. Nothing changes if the call fails.
+    pub fn set_orbit_params(&self, data: &[f32]) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_orbit_params(data)
+            .map_err(JsValue::from)
+    }
+
+    /// Bakes a drop shadow into each glyph; pass no color to remove it.
+    /// Blur and offsets are in glyph canvas pixels (glyphs are ~90px tall), each capped
+    /// at 80.
+    pub fn set_glyph_shadow(
+        &self,
+        color: Option<String>,
+        blur: f32,
+        offset_x: f32,
+        offset_y: f32,
+    ) -> Result<(), JsValue> {
+        let shadow = color.map(|color| GlyphShadow {
+            color,
+            blur,
+            offset_x,
+            offset_y,
+        });
+        self.app
+            .borrow_mut()
+            .set_glyph_shadow(shadow)
+            .map_err(JsValue::from)
+    }
+
+    /// Outlines each glyph in `color`, `width` glyph canvas pixels wide (up to 160), drawn
+    /// under the fill; pass no color or a zero width to remove it.
+    pub fn set_glyph_outline(&self, color: Option<String>, width: f32) -> Result<(), JsValue> {
+        let outline = color.map(|color| GlyphOutline { color, width });
+        self.app
+            .borrow_mut()
+            .set_glyph_outline(outline)
+            .map_err(JsValue::from)
+    }
+
+    /// Rasterizes glyphs in `family` at `weight` (e.g. "bold", "300") and `size_px`, up to
+    /// 128. Glyphs are redrawn again once the font finishes loading, if it was not yet.
+    pub fn set_font(&self, family: &str, weight: &str, size_px: u32) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_font(family, weight, size_px)
+            .map_err(JsValue::from)?;
+        let font = self.app.borrow().glyph_style.font.clone();
+        let fonts = self.app.borrow().document.fonts();
+        rerasterize_after(&self.app, &fonts.load(&font));
+        Ok(())
+    }
+
+    /// Sets each letter's inclination to `base + variation * sin(phase)` (radians).
+    /// Variation 0 gives a flat ring tilted by `base`. Letters and image sprites given
+    /// an explicit inclination (ring or letter configs, `set_letter`, `set_orbit_params`)
+    /// keep it; letters added later without one follow the distribution.
+    pub fn set_inclination_distribution(&self, base: f32, variation: f32) {
+        self.app
+            .borrow_mut()
+            .set_inclination_distribution(base, variation);
+    }
+
+    /// Shows or hides a fading trail of ghost glyphs behind each letter.
+    pub fn set_trails(&self, enabled: bool) {
+        self.app.borrow_mut().set_trails(enabled);
+    }
+
+    /// Sets how many ghost glyphs each trail has (up to 32); 0 turns trails off.
+    pub fn set_trail_length(&self, samples: u32) {
+        self.app.borrow_mut().set_trail_length(samples);
+    }
+
+    /// Draws letters smaller than `threshold_px` on screen as single colored dots.
+    pub fn set_letter_lod(&self, enabled: bool, threshold_px: f32) {
+        self.app.borrow_mut().set_letter_lod(enabled, threshold_px);
+    }
+
+    /// Sets a single sphere color (RGB, 0-1), replacing any gradient.
+    pub fn set_sphere_color(&self, r: f32, g: f32, b: f32) {
+        self.app.borrow_mut().set_sphere_color(Vec3::new(r, g, b));
+    }
+
+    /// Sets the clear color (RGBA, 0-1). Alpha below 1 blends the scene over the page.
+    pub fn set_background(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.app.borrow_mut().set_background([r, g, b, a]);
+    }
+
+    /// Places sphere light `index` (0 or 1) at (`x`, `y`, `z`) with color (`r`, `g`, `b`,
+    /// 0-1). Light 0 is white at (5, 5, 5); light 1 starts black, i.e. off.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_light(
+        &self,
+        index: usize,
+        x: f32,
+        y: f32,
+        z: f32,
+        r: f32,
+        g: f32,
+        b: f32,
+    ) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_light(index, Vec3::new(x, y, z), Vec3::new(r, g, b))
+            .map_err(JsValue::from)
+    }
+
+    /// Moves the sphere's first point light, in world space; defaults to (5, 5, 5).
+    pub fn set_light_position(&self, x: f32, y: f32, z: f32) {
+        self.app.borrow_mut().set_light_position(Vec3::new(x, y, z));
+    }
+
+    /// `Headlamp` moves the first light to the camera, so whichever side you orbit to is
+    /// lit; `Fixed` (the default) keeps it at its world position.
+    pub fn set_light_mode(&self, mode: LightMode) {
+        self.app.borrow_mut().set_light_mode(mode);
+    }
+
+    /// Shades the sphere from `equator` to `pole` color by latitude (RGB, 0-1).
+    pub fn set_sphere_gradient(
+        &self,
+        pole_r: f32,
+        pole_g: f32,
+        pole_b: f32,
+        equator_r: f32,
+        equator_g: f32,
+        equator_b: f32,
+    ) {
+        self.app.borrow_mut().set_sphere_gradient(
+            Vec3::new(pole_r, pole_g, pole_b),
+            Vec3::new(equator_r, equator_g, equator_b),
+        );
+    }
+
+    /// Draws letters without blending, discarding texels below `threshold` alpha instead.
+    /// Glyph edges become hard but draw order stops mattering, which is cheaper on weak GPUs.
+    pub fn set_alpha_test_only(&self, enabled: bool, threshold: f32) {
+        self.app
+            .borrow_mut()
+            .set_alpha_test_only(enabled, threshold);
+    }
+
+    /// Blurs whatever lies away from `focus` world units in front of the camera, more so
+    /// with higher `strength` (blur per world unit of defocus; 0.5 is a good start).
+    /// Fails if the browser lacks depth textures (WEBGL_depth_texture).
+    pub fn set_depth_of_field(&self, dof: bool, focus: f32, strength: f32) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_depth_of_field(dof, focus, strength)
+            .map_err(JsValue::from)
+    }
+
+    /// Makes bright letters glow: everything brighter than `threshold` (0-1 of the
+    /// brightest channel) is blurred and added back scaled by `intensity`. Costs three
+    /// extra fullscreen passes per frame while enabled.
+    pub fn set_bloom(&self, bloom: bool, threshold: f32, intensity: f32) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_bloom(bloom, threshold, intensity)
+            .map_err(JsValue::from)
+    }
+
+    /// Renders the scene twice side by side for cardboard-style viewers, with the eyes
+    /// `eye_separation` world units apart (the sphere has radius 1; 0.1 is a good start).
+    pub fn set_stereo(&self, stereo: bool, eye_separation: f32) {
+        self.app.borrow_mut().set_stereo(stereo, eye_separation);
+    }
+
+    /// Fades letters out over `band` world units before they reach the camera's near
+    /// plane, instead of clipping abruptly; 0 turns it off.
+    pub fn set_near_fade(&self, band: f32) {
+        self.app.borrow_mut().set_near_fade(band);
+    }
+
+    /// Draws the world X, Y and Z axes through the origin in red, green and blue, hidden
+    /// where the sphere is in front, to help when tuning orbit inclinations and phases.
+    pub fn set_show_axes(&self, show: bool) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_show_axes(show)
+            .map_err(JsValue::from)
+    }
+
+    /// Fills the background with `count` stars (up to 100000; 0 removes them) that turn
+    /// with the camera but never get closer, always behind the sphere and letters.
+    pub fn set_stars(&self, count: u32) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_stars(count)
+            .map_err(JsValue::from)
+    }
+
+    /// Picks the star layout; the same seed gives the same sky on every load.
+    pub fn set_star_seed(&self, seed: u32) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_star_seed(seed)
+            .map_err(JsValue::from)
+    }
+
+    /// Darkens the canvas edges by up to `strength` (0-1, 0 is off), starting at `radius`
+    /// (0-1 of the center-to-corner distance).
+    pub fn set_vignette(&self, strength: f32, radius: f32) {
+        self.app.borrow_mut().set_vignette(strength, radius);
+    }
+
+    /// Wall-clock duration of the most recent frame in milliseconds, 0 before the second frame.
+    pub fn last_frame_ms(&self) -> f64 {
+        self.app.borrow().frame_stats.last_frame_ms()
+    }
+
+    /// Frames per second averaged over the last 60 frames, 0 before the second frame.
+    pub fn average_fps(&self) -> f64 {
+        self.app.borrow().frame_stats.average_fps()
+    }
+
+    /// Sets the animation time to `seconds` on both the letter and sphere clocks, as if
+    /// that much animation had played. Use with `render_current` (or the running loop,
+    /// which carries on from there) to show a specific frame. Any finite time works,
+    /// including the negative ones reversed speeds reach.
+    pub fn set_time(&self, seconds: f32) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_time(seconds)
+            .map_err(JsValue::from)
+    }
+
+    /// Draws the frame at the current animation time without advancing it, so
+    /// `set_time(t)` followed by `render_current()` is reproducible for any `t` in seconds.
+    pub fn render_current(&self) {
+        self.app.borrow_mut().render_current();
+        run_after_render(&self.app);
+    }
+
+    /// Captures the current frame as a `data:image/png;base64,...` URL, e.g. for an
+    /// `<img>` or a download link. The frame is redrawn right before the capture, so the
+    /// image is never blank even without `data-preserve-drawing-buffer` on the canvas;
+    /// that attribute is only needed when reading the canvas outside this method (such
+    /// as `canvas.toDataURL()` from JS).
+    pub fn to_data_url(&self) -> Result<String, JsValue> {
+        self.app.borrow_mut().capture_data_url()
+    }
+
+    /// Renders one frame at `time` seconds into an offscreen `width` x `height` buffer
+    /// and returns its RGBA bytes, bottom row first, for snapshot tests. The canvas and
+    /// the running animation are left untouched.
+    pub fn render_to_buffer(&self, time: f32, width: u32, height: u32) -> Result<Vec<u8>, JsValue> {
+        self.app
+            .borrow_mut()
+            .render_to_buffer(time, width, height)
+            .map_err(JsValue::from)
+    }
+
+    /// Whether glyph textures get anisotropic filtering, i.e. the browser offers
+    /// EXT_texture_filter_anisotropic.
+    pub fn anisotropic_filtering(&self) -> bool {
+        self.app.borrow().texture_cache.anisotropy.is_some()
+    }
+
+    /// 2 when rendering through WebGL 2, 1 when the browser only offered WebGL 1.
+    pub fn webgl_version(&self) -> u32 {
+        match self.app.borrow().gl_version {
+            GlVersion::WebGl1 => 1,
+            GlVersion::WebGl2 => 2,
+        }
+    }
+
+    /// The canvas the scene renders into.
+    pub fn get_canvas(&self) -> HtmlCanvasElement {
+        self.app.borrow().canvas.clone()
+    }
+
+    /// Calls `callback(gl)` with the WebGL context after every frame so the host can draw
+    /// overlays; pass nothing to remove it. The scene rebinds its programs, buffers and
+    /// textures every frame, but the callback must restore anything it changes that the
+    /// scene sets only once (depth test, blend func, clear color, viewport) and disable
+    /// any vertex attribute arrays it enables.
+    pub fn set_on_after_render(&self, callback: Option<js_sys::Function>) {
+        self.app.borrow_mut().set_after_render(callback);
+    }
+
+    /// Resizes the backing store to `width` x `height` CSS pixels (times the device pixel
+    /// ratio) and updates the viewport and aspect. Window resizes are handled already; call
+    /// this when the canvas is resized some other way.
+    pub fn resize(&self, width: u32, height: u32) {
+        self.app.borrow_mut().resize(width, height);
+    }
+
+    /// Renders at `scale` (0.1-1) of the canvas' device-pixel size and lets the browser
+    /// upscale.
+    pub fn set_resolution_scale(&self, scale: f32) {
+        self.app.borrow_mut().set_resolution_scale(scale);
+    }
+
+    /// Renders `ratio` device pixels per CSS pixel instead of the display's
+    /// `devicePixelRatio`; 1 makes the backing store exactly the canvas' CSS size, and 0
+    /// goes back to following the display. Capped by `set_max_pixel_ratio`.
+    pub fn set_pixel_ratio(&self, ratio: f32) {
+        self.app.borrow_mut().set_pixel_ratio(ratio);
+    }
+
+    /// Caps the pixel ratio (default 2), so 3x phone displays don't render a huge canvas.
+    pub fn set_max_pixel_ratio(&self, max: f32) {
+        self.app.borrow_mut().set_max_pixel_ratio(max);
+    }
+
+    /// Transparent border in pixels around each glyph texture (default 4) to avoid edge bleed.
+    pub fn set_glyph_padding(&self, padding: u32) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_glyph_padding(padding)
+            .map_err(JsValue::from)
+    }
+
+    /// Depth bias (see `glPolygonOffset`) applied to the sphere; 0, 0 disables it.
+    pub fn set_polygon_offset(&self, factor: f32, units: f32) {
+        self.app.borrow_mut().set_polygon_offset(factor, units);
+    }
+
+    /// Disables depth testing so the sphere and letters layer by draw order only.
+    pub fn set_flat_2d(&self, flat_2d: bool) {
+        self.app.borrow_mut().set_flat_2d(flat_2d);
+    }
+
+    /// Spins the sphere in the letters' direction at `ratio` times their mean speed.
+    pub fn set_sphere_lock(&self, locked: bool, ratio: f32) {
+        self.app.borrow_mut().set_sphere_lock(locked, ratio);
+    }
+
+    /// Fades the sphere's silhouette into the background, 0 (off) to 1.
+    /// The sphere is alpha blended while this is on, so letters behind it are
+    /// drawn before the sphere (as with `set_interleaved_draw`) to show through.
+    pub fn set_edge_fade(&self, strength: f32) {
+        self.app.borrow_mut().set_edge_fade(strength);
+    }
+
+    /// Sets how the sphere takes light: `shininess` is the highlight exponent (at least
+    /// 1; higher is tighter and glossier), `specular_strength` scales the highlight (0 is
+    /// matte) and `diffuse_strength` the shading facing each light. Defaults are 32, 0.3
+    /// and 0.7.
+    pub fn set_material(&self, shininess: f32, specular_strength: f32, diffuse_strength: f32) {
+        self.app
+            .borrow_mut()
+            .set_material(shininess, specular_strength, diffuse_strength);
+    }
+
+    /// Sets the light the sphere gets everywhere, even facing away from every light
+    /// (0.15 by default; 0 leaves the dark side black).
+    pub fn set_ambient(&self, ambient: f32) {
+        self.app.borrow_mut().set_ambient(ambient);
+    }
+
+    /// Lights the sphere in linear space: its colors and texture are decoded from sRGB,
+    /// lit, and gamma-encoded (1/2.2) for display, so falloff and highlights look
+    /// physically right and lit colors match their hex values. Midtones come out
+    /// noticeably brighter than with it off (the default). Letters are untouched: glyph
+    /// textures are already sRGB and unlit.
+    pub fn set_srgb(&self, srgb: bool) {
+        self.app.borrow_mut().set_srgb(srgb);
+    }
+
+    /// Fades the scene into `color` (a CSS hex string, or the background when omitted)
+    /// with distance: each pixel keeps `exp(-density * depth)` of its own color, depth
+    /// in world units from the camera. Around 0.05 is subtle; 0.2 is thick.
+    pub fn set_fog(&self, fog: bool, color: Option<String>, density: f32) -> Result<(), JsValue> {
+        let color = match color {
+            Some(color) => {
+                let [r, g, b] = parse_hex_color(&color).ok_or_else(|| {
+                    RenderError::InvalidArgument(format!("Invalid fog color {color}"))
+                })?;
+                Some(Vec3::new(r, g, b))
+            }
+            None => None,
+        };
+        self.app.borrow_mut().set_fog(fog, color, density);
+        Ok(())
+    }
+
+    /// Draws letters behind the sphere before it and letters in front after it.
+    pub fn set_interleaved_draw(&self, interleaved: bool) {
+        self.app.borrow_mut().set_interleaved_draw(interleaved);
+    }
+
+    /// Point the camera looks at (and later orbits around), in world units.
+    pub fn set_camera_target(&self, x: f32, y: f32, z: f32) {
+        self.app.borrow_mut().set_camera_target(Vec3::new(x, y, z));
+    }
+
+    /// Rolls the camera `radians` about its line of sight; 0 keeps the world upright.
+    pub fn set_camera_roll(&self, radians: f32) {
+        self.app.borrow_mut().set_camera_roll(radians);
+    }
+
+    /// Orbits the camera as if the mouse was dragged by `dx`, `dy` pixels. Dragging on
+    /// the canvas already does this.
+    pub fn on_pointer_drag(&self, dx: f32, dy: f32) {
+        self.app.borrow_mut().on_pointer_drag(dx, dy);
+    }
+
+    /// Moves the camera `notches` wheel notches away from the target (negative moves
+    /// closer), 10% per notch. The scroll wheel over the canvas already does this.
+    pub fn zoom(&self, notches: f32) {
+        self.app.borrow_mut().zoom(notches);
+    }
+
+    /// Radians of camera orbit per pixel dragged (default 0.005).
+    pub fn set_drag_sensitivity(&self, radians_per_pixel: f32) {
+        self.app
+            .borrow_mut()
+            .set_drag_sensitivity(radians_per_pixel);
+    }
+
+    /// Orbits one billboard per character or per word in text set afterwards by
+    /// `set_text` and `set_rings`; letters already orbiting are unchanged.
+    pub fn set_label_mode(&self, mode: LabelMode) {
+        self.app.borrow_mut().set_label_mode(mode);
+    }
+
+    /// Draws the text as individual glyph billboards or as one continuous ribbon.
+    pub fn set_text_mode(&self, mode: TextMode) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_text_mode(mode)
+            .map_err(JsValue::from)
+    }
+
+    /// Switches between the perspective camera and an orthographic one that frames the
+    /// same view at the camera target without foreshortening. Zoom still works in both.
+    pub fn set_projection_mode(&self, mode: ProjectionMode) {
+        self.app.borrow_mut().set_projection_mode(mode);
+    }
+
+    /// Chooses whether letters stay upright in the world or on screen.
+    pub fn set_billboard_mode(&self, mode: BillboardMode) {
+        self.app.borrow_mut().set_billboard_mode(mode);
+    }
+
+    /// Makes the ring breathe between circular and elliptical; amplitude 0 keeps it static.
+    pub fn set_eccentricity_animation(&self, amplitude: f32, rate: f32) {
+        self.app
+            .borrow_mut()
+            .set_eccentricity_animation(amplitude, rate);
+    }
+
+    /// Scatters the letters outward while fading them out over `duration` seconds. They
+    /// stay hidden until `reassemble`. The scatter pattern is the same every time.
+    pub fn explode(&self, duration: f32) {
+        self.app.borrow_mut().explode(duration);
+    }
+
+    /// Brings exploded letters back onto their exact orbits, taking as long as `explode`.
+    pub fn reassemble(&self) {
+        self.app.borrow_mut().reassemble();
+    }
+
+    /// Swells every letter's orbit to `target_radius` and back over `duration` seconds.
+    /// Triggering again mid-pulse restarts it from the current size.
+    pub fn pulse_radius(&self, target_radius: f32, duration: f32) {
+        self.app.borrow_mut().pulse_radius(target_radius, duration);
+    }
+
+    /// Every `interval` seconds each letter drops up to `height` toward the sphere and
+    /// bounces back out, staggered around the ring. Height 0 turns it off.
+    pub fn set_letter_bounce(&self, interval: f32, height: f32) {
+        self.app.borrow_mut().set_letter_bounce(interval, height);
+    }
+
+    /// Makes letters bunch into clusters and spread out again every `period` seconds.
+    /// Amplitude is the peak angle shift in radians; 0 keeps even spacing.
+    pub fn set_orbit_resonance(&self, amplitude: f32, period: f32) {
+        self.app.borrow_mut().set_orbit_resonance(amplitude, period);
+    }
+}
+
+impl WasmSphere {
+    fn set_central_mesh(&self, mesh: CentralMesh) -> Result<(), JsValue> {
+        self.app
+            .borrow_mut()
+            .set_central_mesh(mesh)
+            .map_err(JsValue::from)
+    }
+}
+
+thread_local! {
+    static SPHERE: RefCell<Option<WasmSphere>> = const { RefCell::new(None) };
+}
+
+/// Returns a handle to the scene started by the module, if it is running.
+#[wasm_bindgen]
+pub fn sphere() -> Option<WasmSphere> {
+    SPHERE.with(|s| s.borrow().clone())
+}
+
+// One animation step and draw at `timestamp` milliseconds
+fn render_frame(app: &Rc<RefCell<App>>, timestamp: f64) {
+    {
+        let mut app = app.borrow_mut();
+        let frame_ms = app
+            .last_timestamp
+            .replace(timestamp)
+            .map(|last| (timestamp - last).max(0.0));
+        if let Some(frame_ms) = frame_ms {
+            app.frame_stats.record(frame_ms);
+        }
+        app.advance((frame_ms.unwrap_or(0.0) / 1000.0) as f32);
+        app.render_current();
+    }
+    run_after_render(app);
+}
+
+// Call the host's after-render hook with the app not borrowed at all, so the hook can
+// call back into the scene (setters, `render_current`) without a BorrowMutError
+fn run_after_render(app: &RefCell<App>) {
+    let (callback, gl) = {
+        let app = app.borrow();
+        (app.after_render.clone(), app.gl.clone())
+    };
+    if let Some(callback) = callback
+        && let Err(err) = callback.call1(&JsValue::NULL, &gl)
+    {
+        web_sys::console::error_2(&"on_after_render callback failed:".into(), &err);
+    }
+}
+
+type FrameCallback = Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>>;
+
+fn request_animation_frame(f: &Closure<dyn FnMut(f64)>) -> i32 {
+    web_sys::window()
+        .unwrap()
+        .request_animation_frame(f.as_ref().unchecked_ref())
+        .unwrap()
+}
+
+type VisibilityObserver = (
+    web_sys::IntersectionObserver,
+    Closure<dyn FnMut(js_sys::Array)>,
+);
+
+// A page event listener, removed from its target when dropped
+struct EventListener {
+    target: web_sys::EventTarget,
+    event: &'static str,
+    callback: Box<dyn AsRef<JsValue>>,
+}
+
+impl EventListener {
+    fn new<T: ?Sized + 'static>(
+        target: &web_sys::EventTarget,
+        event: &'static str,
+        callback: Closure<T>,
+    ) -> Result<Self, JsValue> {
+        target.add_event_listener_with_callback(event, callback.as_ref().unchecked_ref())?;
+        Ok(Self {
+            target: target.clone(),
+            event,
+            callback: Box::new(callback),
+        })
+    }
+}
+
+impl Drop for EventListener {
+    fn drop(&mut self) {
+        let callback = (*self.callback).as_ref().unchecked_ref();
+        let _ = self
+            .target
+            .remove_event_listener_with_callback(self.event, callback);
+    }
+}
+
+// requestAnimationFrame loop driving the App, plus the page listeners feeding it input.
+// Stopping cancels the pending frame so no work at all is scheduled until it is started
+// again. Everything registered on the page holds the App weakly, so dropping the loop
+// and the last handle releases the scene.
+struct RenderLoop {
+    app: Rc<RefCell<App>>,
+    callback: FrameCallback,
+    pending_frame: Cell<Option<i32>>,
+    // Set by `start` and `stop`; coming back into view only resumes a running loop
+    running: Cell<bool>,
+    offscreen: Cell<bool>,
+    visibility_observer: RefCell<Option<VisibilityObserver>>,
+    listeners: RefCell<Vec<EventListener>>,
+}
+
+impl RenderLoop {
+    fn new(app: Rc<RefCell<App>>, listeners: Vec<EventListener>) -> Rc<Self> {
+        let render_loop = Rc::new(Self {
+            app,
+            callback: Rc::new(RefCell::new(None)),
+            pending_frame: Cell::new(None),
+            running: Cell::new(false),
+            offscreen: Cell::new(false),
+            visibility_observer: RefCell::new(None),
+            listeners: RefCell::new(listeners),
+        });
+
+        let weak = Rc::downgrade(&render_loop);
+        *render_loop.callback.borrow_mut() = Some(Closure::new(move |timestamp: f64| {
+            if let Some(render_loop) = weak.upgrade() {
+                render_loop.frame(timestamp);
+            }
+        }));
+
+        render_loop
+    }
+
+    fn frame(&self, timestamp: f64) {
+        self.pending_frame.set(None);
+        render_frame(&self.app, timestamp);
+        self.schedule();
+    }
+
+    fn schedule(&self) {
+        if let Some(callback) = self.callback.borrow().as_ref() {
+            self.pending_frame
+                .set(Some(request_animation_frame(callback)));
+        }
+    }
+
+    fn start(&self) {
+        self.running.set(true);
+        self.resume();
+    }
+
+    fn stop(&self) {
+        self.running.set(false);
+        self.pause();
+    }
+
+    // Schedule frames again if the host wants the loop running and the canvas is in view
+    fn resume(&self) {
+        if self.running.get() && !self.offscreen.get() && self.pending_frame.get().is_none() {
+            self.schedule();
+        }
+    }
+
+    fn pause(&self) {
+        if let Some(id) = self.pending_frame.take() {
+            if let Some(window) = web_sys::window() {
+                let _ = window.cancel_animation_frame(id);
+            }
+            // Resume from the next frame's timestamp instead of jumping over the gap
+            self.app.borrow_mut().last_timestamp = None;
+        }
+    }
+
+    // Stop the loop while the canvas is scrolled out of view
+    fn set_pause_when_offscreen(self: &Rc<Self>, enabled: bool) -> Result<(), JsValue> {
+        if let Some((observer, _)) = self.visibility_observer.borrow_mut().take() {
+            observer.disconnect();
+        }
+        if !enabled {
+            self.offscreen.set(false);
+            self.resume();
+            return Ok(());
+        }
+
+        let weak = Rc::downgrade(self);
+        let callback = Closure::new(move |entries: js_sys::Array| {
+            let Some(render_loop) = weak.upgrade() else {
+                return;
+            };
+            // A batch can hold several changes for the canvas (e.g. after a fast scroll);
+            // the last one is where it is now
+            let Some(entry) = entries.iter().last() else {
+                return;
+            };
+            let visible = entry
+                .unchecked_into::<web_sys::IntersectionObserverEntry>()
+                .is_intersecting();
+            render_loop.offscreen.set(!visible);
+            if visible {
+                render_loop.resume();
+            } else {
+                render_loop.pause();
+            }
+        });
+        let observer = web_sys::IntersectionObserver::new(callback.as_ref().unchecked_ref())?;
+        observer.observe(&self.app.borrow().canvas);
+        *self.visibility_observer.borrow_mut() = Some((observer, callback));
+        Ok(())
+    }
+
+    // Detach the scene from the page: no more frames, input or visibility callbacks
+    fn teardown(&self) {
+        self.stop();
+        if let Some((observer, _)) = self.visibility_observer.borrow_mut().take() {
+            observer.disconnect();
+        }
+        self.listeners.borrow_mut().clear();
+    }
+}
+
+impl Drop for RenderLoop {
+    fn drop(&mut self) {
+        self.teardown();
+    }
+}
+
+// Drag on the canvas to orbit the camera and scroll over it to zoom. Moves and releases
+// are tracked on the window so a drag keeps going when the pointer leaves the canvas.
+fn add_camera_controls(
+    window: &web_sys::Window,
+    app: &Rc<RefCell<App>>,
+) -> Result<Vec<EventListener>, JsValue> {
+    let last_pos: Rc<Cell<Option<(i32, i32)>>> = Rc::new(Cell::new(None));
+
+    let on_down = {
+        let last_pos = last_pos.clone();
+        Closure::<dyn FnMut(web_sys::MouseEvent)>::new(move |event: web_sys::MouseEvent| {
+            last_pos.set(Some((event.client_x(), event.client_y())));
+        })
+    };
+    let on_move = {
+        let last_pos = last_pos.clone();
+        let app = Rc::downgrade(app);
+        Closure::<dyn FnMut(web_sys::MouseEvent)>::new(move |event: web_sys::MouseEvent| {
+            let Some(app) = app.upgrade() else {
+                return;
+            };
+            if let Some((x, y)) = last_pos.get() {
+                let (new_x, new_y) = (event.client_x(), event.client_y());
+                last_pos.set(Some((new_x, new_y)));
+                app.borrow_mut()
+                    .on_pointer_drag((new_x - x) as f32, (new_y - y) as f32);
+            }
+        })
+    };
+    let on_up = Closure::<dyn FnMut()>::new(move || last_pos.set(None));
+    let on_wheel = {
+        let app = Rc::downgrade(app);
+        Closure::<dyn FnMut(web_sys::WheelEvent)>::new(move |event: web_sys::WheelEvent| {
+            let Some(app) = app.upgrade() else {
+                return;
+            };
+            // Keep the page from scrolling under the canvas
+            event.prevent_default();
+            let notches = match event.delta_mode() {
+                web_sys::WheelEvent::DOM_DELTA_PIXEL => event.delta_y() / 100.0,
+                web_sys::WheelEvent::DOM_DELTA_LINE => event.delta_y() / 3.0,
+                _ => event.delta_y(),
+            };
+            app.borrow_mut().zoom(notches as f32);
+        })
+    };
+
+    let canvas = app.borrow().canvas.clone();
+    Ok(vec![
+        EventListener::new(&canvas, "mousedown", on_down)?,
+        EventListener::new(window, "mousemove", on_move)?,
+        EventListener::new(window, "mouseup", on_up)?,
+        EventListener::new(&canvas, "wheel", on_wheel)?,
+    ])
+}
+
+// Arrow keys, +/- and space drive the scene (see `App::on_key`) while the canvas has
+// focus, so typing elsewhere on the page is unaffected. Keys held with a modifier are
+// left to the browser, and handled keys don't scroll the page.
+fn add_keyboard_controls(app: &Rc<RefCell<App>>) -> Result<EventListener, JsValue> {
+    let on_key = {
+        let app = Rc::downgrade(app);
+        Closure::<dyn FnMut(web_sys::KeyboardEvent)>::new(move |event: web_sys::KeyboardEvent| {
+            let Some(app) = app.upgrade() else {
+                return;
+            };
+            if event.ctrl_key() || event.meta_key() || event.alt_key() {
+                return;
+            }
+            if app.borrow_mut().on_key(&event.key()) {
+                event.prevent_default();
+            }
+        })
+    };
+    let canvas = app.borrow().canvas.clone();
+    // A canvas only takes focus (by click or Tab) with a tabindex; keep the page's own
+    if !canvas.has_attribute("tabindex") {
+        canvas.set_attribute("tabindex", "0")?;
+    }
+    EventListener::new(&canvas, "keydown", on_key)
+}
+
+// Most a press can move and still count as a click rather than a drag
+const CLICK_SLOP_PX: i32 = 4;
+
+// Clicking a letter dispatches a `letterclick` event on the canvas whose `detail` is
+// `{ index, character }`; image sprites report an empty character. Drags are not clicks.
+fn add_letter_picking(app: &Rc<RefCell<App>>) -> Result<Vec<EventListener>, JsValue> {
+    let down_pos: Rc<Cell<Option<(i32, i32)>>> = Rc::new(Cell::new(None));
+
+    let on_down = {
+        let down_pos = down_pos.clone();
+        Closure::<dyn FnMut(web_sys::MouseEvent)>::new(move |event: web_sys::MouseEvent| {
+            down_pos.set(Some((event.client_x(), event.client_y())));
+        })
+    };
+    let on_click = {
+        let app = Rc::downgrade(app);
+        Closure::<dyn FnMut(web_sys::MouseEvent)>::new(move |event: web_sys::MouseEvent| {
+            let Some(app) = app.upgrade() else {
+                return;
+            };
+            if let Some((x, y)) = down_pos.take() {
+                let moved = (event.client_x() - x)
+                    .abs()
+                    .max((event.client_y() - y).abs());
+                if moved > CLICK_SLOP_PX {
+                    return;
+                }
+            }
+            // Dispatch with the app not borrowed at all: listeners run synchronously and may
+            // call back into the scene (setters, `focus_letter`) without a BorrowMutError
+            let (canvas, index, glyph) = {
+                let app = app.borrow();
+                let time = app.letter_clock.elapsed;
+                let Some(index) = app.pick(event.offset_x() as f32, event.offset_y() as f32, time)
+                else {
+                    return;
+                };
+                (app.canvas.clone(), index, app.letters[index].glyph.clone())
+            };
+            if let Err(err) = dispatch_letter_click(&canvas, index, &glyph) {
+                web_sys::console::error_2(&"Failed to dispatch letterclick:".into(), &err);
+            }
+        })
+    };
+
+    let canvas = app.borrow().canvas.clone();
+    Ok(vec![
+        EventListener::new(&canvas, "mousedown", on_down)?,
+        EventListener::new(&canvas, "click", on_click)?,
+    ])
+}
+
+fn dispatch_letter_click(
+    canvas: &HtmlCanvasElement,
+    index: usize,
+    character: &str,
+) -> Result<(), JsValue> {
+    let detail = js_sys::Object::new();
+    js_sys::Reflect::set(&detail, &"index".into(), &(index as u32).into())?;
+    js_sys::Reflect::set(&detail, &"character".into(), &character.into())?;
+    let init = web_sys::CustomEventInit::new();
+    init.set_detail(&detail);
+    let event = web_sys::CustomEvent::new_with_event_init_dict("letterclick", &init)?;
+    canvas.dispatch_event(&event)?;
+    Ok(())
+}
+
+// Redraw the glyphs once `promise` settles, typically a font load. Skipped if the style
+// moved on in the meantime, since that change rasterized its own glyphs.
+fn rerasterize_after(app: &Rc<RefCell<App>>, promise: &js_sys::Promise) {
+    let style_key = app.borrow().glyph_style.cache_key();
+    // Weak, so a scene destroyed before the font arrives isn't kept alive by it
+    let app = Rc::downgrade(app);
+    let callback = Closure::once_into_js(move || {
+        let Some(app) = app.upgrade() else {
+            return;
+        };
+        let mut app = app.borrow_mut();
+        if app.glyph_style.cache_key() != style_key {
+            return;
+        }
+        if let Err(err) = app.rerasterize_glyphs() {
+            web_sys::console::error_1(&err.to_string().into());
+        }
+    });
+    // `finally` runs for failed loads too, which still settle on a fallback font
+    if let Ok(finally) = js_sys::Reflect::get(promise, &"finally".into()) {
+        let _ = finally
+            .unchecked_into::<js_sys::Function>()
+            .call1(promise, &callback);
+    }
+}
+
+// Context creation attributes, read from the canvas so the host page can choose them:
+// `data-antialias="false"` turns multisampling off (offscreen passes included) and
+// `data-preserve-drawing-buffer` keeps the last frame readable for screenshots (at some
+// cost on tiled GPUs)
+struct ContextOptions {
+    antialias: bool,
+    preserve_drawing_buffer: bool,
+    // Always requested, so a translucent background shows the page behind the canvas
+    alpha: bool,
+}
+
+impl ContextOptions {
+    fn from_canvas(canvas: &HtmlCanvasElement) -> Self {
+        let attribute = |name: &str| canvas.get_attribute(name);
+        Self {
+            antialias: attribute("data-antialias").is_none_or(|value| value != "false"),
+            preserve_drawing_buffer: attribute("data-preserve-drawing-buffer")
+                .is_some_and(|value| value != "false"),
+            alpha: true,
+        }
+    }
+
+    fn to_js(&self) -> Result<js_sys::Object, JsValue> {
+        let attributes = js_sys::Object::new();
+        js_sys::Reflect::set(&attributes, &"antialias".into(), &self.antialias.into())?;
+        js_sys::Reflect::set(&attributes, &"alpha".into(), &self.alpha.into())?;
+        js_sys::Reflect::set(
+            &attributes,
+            &"preserveDrawingBuffer".into(),
+            &self.preserve_drawing_buffer.into(),
+        )?;
+        Ok(attributes)
+    }
+}
+
+// WebGL 2 methods are a superset of WebGL 1's, so either context works as a `GL`.
+// Attributes are requests: a browser without multisampling still hands back a context,
+// just an aliased one.
+fn get_gl_context(
+    canvas: &HtmlCanvasElement,
+    options: &ContextOptions,
+) -> Result<(js_sys::Object, GlVersion), JsValue> {
+    let attributes = options.to_js()?;
+    if let Some(context) = canvas.get_context_with_context_options("webgl2", &attributes)? {
+        return Ok((context, GlVersion::WebGl2));
+    }
+    if let Some(context) = canvas.get_context_with_context_options("webgl", &attributes)? {
+        return Ok((context, GlVersion::WebGl1));
+    }
+    // Some drivers refuse any context with the requested attributes; settle for defaults
+    let context = canvas
+        .get_context("webgl")?
+        .ok_or("Neither WebGL 2 nor WebGL 1 is available")?;
+    Ok((context, GlVersion::WebGl1))
+}
+
+// Build the scene on the canvas with id `canvas_id`, wired to resize and camera input
+// but with its render loop not yet started
+fn create_scene(canvas_id: &str) -> Result<WasmSphere, JsValue> {
+    console_error_panic_hook::set_once();
+
+    let window = web_sys::window().ok_or("No window")?;
+    let document = window.document().ok_or("No document")?;
+    let canvas = document
+        .get_element_by_id(canvas_id)
+        .ok_or_else(|| format!("No canvas with id \"{canvas_id}\""))?
+        .dyn_into::<HtmlCanvasElement>()?;
+
+    let width = window.inner_width()?.as_f64().unwrap() as u32;
+    let height = window.inner_height()?.as_f64().unwrap() as u32;
+    canvas.set_width(width);
+    canvas.set_height(height);
+
+    let options = ContextOptions::from_canvas(&canvas);
+    let (context, gl_version) = get_gl_context(&canvas, &options)?;
+    let gl = context.unchecked_into::<GL>();
+
+    gl.viewport(0, 0, width as i32, height as i32);
+
+    let app = Rc::new(RefCell::new(
+        App::new(
+            gl,
+            gl_version,
+            canvas,
+            &document,
+            width,
+            height,
+            options.antialias,
+        )
+        .map_err(JsValue::from)?,
+    ));
+
+    app.borrow_mut().update_canvas_size();
+
+    // The first glyphs are usually drawn before the web font arrives
+    rerasterize_after(&app, &document.fonts().ready()?);
+
+    let on_resize = {
+        let app = Rc::downgrade(&app);
+        Closure::<dyn FnMut()>::new(move || {
+            if let Some(app) = app.upgrade() {
+                app.borrow_mut().update_canvas_size();
+            }
+        })
+    };
+    let mut listeners = vec![EventListener::new(&window, "resize", on_resize)?];
+    listeners.extend(add_camera_controls(&window, &app)?);
+    listeners.push(add_keyboard_controls(&app)?);
+    listeners.extend(add_letter_picking(&app)?);
+
+    let render_loop = RenderLoop::new(app.clone(), listeners);
+    Ok(WasmSphere { app, render_loop })
+}
+
+// Put a failed start on the page as well as the console, so visitors on unsupported
+// browsers see why instead of a blank canvas. The message goes into `#error` when the
+// page has one, otherwise into a new `#error` element at the end of the body.
+#[cfg(feature = "auto-start")]
+fn report_startup_error(err: &JsValue) {
+    web_sys::console::error_2(&"Failed to start the scene:".into(), err);
+    // RenderErrors arrive as JS Errors named after their variant
+    let (name, detail) = match err.dyn_ref::<js_sys::Error>() {
+        Some(error) => (String::from(error.name()), String::from(error.message())),
+        None => (
+            String::new(),
+            err.as_string().unwrap_or_else(|| format!("{err:?}")),
+        ),
+    };
+    let summary = match name.as_str() {
+        "ShaderCompile" | "ProgramLink" => "Your graphics driver could not build the shaders.",
+        "ContextLost" => "The WebGL context was lost while starting up.",
+        "Unsupported" => "Your browser lacks a graphics feature this page needs.",
+        _ if detail.contains("WebGL") => "Your browser does not support WebGL.",
+        _ => "The 3D scene could not be started.",
+    };
+
+    let Some(document) = web_sys::window().and_then(|w| w.document()) else {
+        return;
+    };
+    let element = match document.get_element_by_id("error") {
+        Some(element) => element,
+        None => {
+            let Some(body) = document.body() else {
+                return;
+            };
+            let Ok(element) = document.create_element("div") else {
+                return;
+            };
+            element.set_id("error");
+            if body.append_child(&element).is_err() {
+                return;
+            }
+            element
+        }
+    };
+    element.set_text_content(Some(&format!("{summary}\n\n{detail}")));
+}
+
+// With the `auto-start` feature the module starts a scene on `#canvas` as soon as it
+// loads; without it, hosts create their own through `WasmSphere::new`
+#[cfg(feature = "auto-start")]
+#[wasm_bindgen(start)]
+pub fn main() -> Result<(), JsValue> {
+    let sphere = create_scene("canvas").inspect_err(report_startup_error)?;
+    sphere.render_loop.start();
+    SPHERE.with(|s| *s.borrow_mut() = Some(sphere));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A letter with only orbit parameters; the texture is never touched by the orbit math
+    fn orbiting_letter(
+        radius: f32,
+        inclination: f32,
+        phase: f32,
+        angular_velocity: f32,
+        eccentricity: f32,
+    ) -> OrbitingLetter {
+        OrbitingLetter {
+            glyph: "A".into(),
+            color: "#fff".into(),
+            texture: Rc::new(GlyphTexture {
+                texture: JsValue::NULL.unchecked_into(),
+                extent: 1.0,
+                aspect: Cell::new(1.0),
+                widen: true,
+                size: Some((GLYPH_CELL, GLYPH_CELL)),
+            }),
+            radius,
+            inclination,
+            phase,
+            angular_velocity,
+            eccentricity,
+            z_offset: 0.0,
+            sprite: false,
+            inherits_inclination: true,
+        }
+    }
+
+    const EPSILON: f32 = 1e-5;
+
+    #[track_caller]
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < EPSILON, "{a} != {b}");
+    }
+
+    #[track_caller]
+    fn assert_mat4_close(a: &Mat4, b: &Mat4) {
+        for (x, y) in a.data.iter().zip(&b.data) {
+            assert_close(*x, *y);
+        }
+    }
+
+    // The largest style the setters allow: padding, shadow and outline all at their caps
+    fn widest_glyph_style() -> GlyphStyle {
+        GlyphStyle {
+            padding: GLYPH_CELL,
+            shadow: Some(GlyphShadow {
+                color: "#000".into(),
+                blur: MAX_SHADOW_BLUR,
+                offset_x: -MAX_SHADOW_OFFSET,
+                offset_y: MAX_SHADOW_OFFSET,
+            }),
+            outline: Some(GlyphOutline {
+                color: "#fff".into(),
+                width: MAX_OUTLINE_WIDTH,
+            }),
+            ..GlyphStyle::default()
+        }
+    }
+
+    #[test]
+    fn outlined_glyph_canvas_fits_label_width() {
+        let style = widest_glyph_style();
+        assert!(GLYPH_CELL + 2 * style.margin() <= MAX_LABEL_WIDTH);
+        for text_width in [0.0, 90.0, 5000.0] {
+            let (width, height, shrink) = glyph_canvas_size(text_width, style.margin());
+            assert!(width >= height && width <= MAX_LABEL_WIDTH);
+            assert!(shrink > 0.0 && shrink <= 1.0);
+        }
+    }
+
+    #[test]
+    fn inverse_of_identity_is_identity() {
+        let identity = Mat4::identity();
+        assert_close(identity.determinant(), 1.0);
+        assert_mat4_close(
+            &identity.inverse().expect("identity is invertible"),
+            &identity,
+        );
     }
-"#;
 
-const TEXT_VERTEX_SHADER: &str = r#"
-    attribute vec3 a_position;
-    attribute vec2 a_uv;
-    uniform mat4 u_model;
-    uniform mat4 u_view;
-    uniform mat4 u_projection;
-    varying vec2 v_uv;
-    void main() {
-        v_uv = a_uv;
-        gl_Position = u_projection * u_view * u_model * vec4(a_position, 1.0);
+    #[test]
+    fn matrix_times_inverse_is_identity() {
+        let m = Mat4::translation(1.0, -2.0, 3.0)
+            .multiply(Mat4::rotation_y(0.7))
+            .multiply(Mat4::rotation_x(-0.3));
+        let inverse = m.inverse().expect("rotation + translation is invertible");
+        assert_mat4_close(&m.multiply(inverse), &Mat4::identity());
+        assert_mat4_close(&inverse.multiply(m), &Mat4::identity());
     }
-"#;
 
-const TEXT_FRAGMENT_SHADER: &str = r#"
-    precision mediump float;
-    varying vec2 v_uv;
-    uniform sampler2D u_texture;
-    void main() {
-        vec4 texColor = texture2D(u_texture, v_uv);
-        if (texColor.a < 0.1) discard;
-        gl_FragColor = texColor;
+    #[test]
+    fn singular_matrix_has_no_inverse() {
+        assert_close(Mat4::scale(2.0, 3.0, 4.0).determinant(), 24.0);
+        let flattened = Mat4::scale(1.0, 0.0, 1.0);
+        assert_close(flattened.determinant(), 0.0);
+        assert!(flattened.inverse().is_none());
     }
-"#;
 
-// WebGL helpers
-fn compile_shader(gl: &GL, shader_type: u32, source: &str) -> Result<WebGlShader, String> {
-    let shader = gl
-        .create_shader(shader_type)
-        .ok_or("Unable to create shader")?;
-    gl.shader_source(&shader, source);
-    gl.compile_shader(&shader);
-    if gl
-        .get_shader_parameter(&shader, GL::COMPILE_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
-        Ok(shader)
-    } else {
-        Err(gl
-            .get_shader_info_log(&shader)
-            .unwrap_or_else(|| "Unknown shader error".into()))
+    // An asymmetric matrix, so a transposed product would show
+    fn sample_matrix() -> Mat4 {
+        Mat4 {
+            data: std::array::from_fn(|i| (i as f32 * 0.37).sin() + 0.1 * i as f32),
+        }
     }
-}
 
-fn link_program(gl: &GL, vert: &WebGlShader, frag: &WebGlShader) -> Result<WebGlProgram, String> {
-    let program = gl.create_program().ok_or("Unable to create program")?;
-    gl.attach_shader(&program, vert);
-    gl.attach_shader(&program, frag);
-    gl.link_program(&program);
-    if gl
-        .get_program_parameter(&program, GL::LINK_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
-        Ok(program)
-    } else {
-        Err(gl
-            .get_program_info_log(&program)
-            .unwrap_or_else(|| "Unknown program error".into()))
+    #[test]
+    fn identity_is_neutral_for_multiply() {
+        let m = sample_matrix();
+        assert_mat4_close(&Mat4::identity().multiply(m), &m);
+        assert_mat4_close(&m.multiply(Mat4::identity()), &m);
     }
-}
 
-fn create_buffer(gl: &GL, data: &[f32]) -> Result<WebGlBuffer, String> {
-    let buffer = gl.create_buffer().ok_or("Failed to create buffer")?;
-    gl.bind_buffer(GL::ARRAY_BUFFER, Some(&buffer));
-    unsafe {
-        let array = js_sys::Float32Array::view(data);
-        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &array, GL::STATIC_DRAW);
+    #[test]
+    fn multiply_is_associative() {
+        let a = sample_matrix();
+        let b = Mat4::rotation_x(0.4).multiply(Mat4::translation(0.5, 0.0, -1.0));
+        let c = Mat4::scale(1.0, 2.0, 0.5);
+        assert_mat4_close(&a.multiply(b).multiply(c), &a.multiply(b.multiply(c)));
     }
-    Ok(buffer)
-}
 
-fn create_index_buffer(gl: &GL, data: &[u16]) -> Result<WebGlBuffer, String> {
-    let buffer = gl.create_buffer().ok_or("Failed to create index buffer")?;
-    gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&buffer));
-    unsafe {
-        let array = js_sys::Uint16Array::view(data);
-        gl.buffer_data_with_array_buffer_view(GL::ELEMENT_ARRAY_BUFFER, &array, GL::STATIC_DRAW);
+    #[test]
+    fn composed_rotations_add_angles() {
+        let composed = Mat4::rotation_z(0.3).multiply(Mat4::rotation_z(0.9));
+        assert_mat4_close(&composed, &Mat4::rotation_z(1.2));
+        let quarter_turns = Mat4::rotation_y(PI / 2.0).multiply(Mat4::rotation_y(PI / 2.0));
+        assert_mat4_close(&quarter_turns, &Mat4::rotation_y(PI));
     }
-    Ok(buffer)
-}
 
-// Application state
-struct App {
-    gl: GL,
-    sphere_program: WebGlProgram,
-    text_program: WebGlProgram,
-    sphere_vertex_buffer: WebGlBuffer,
-    sphere_normal_buffer: WebGlBuffer,
-    sphere_index_buffer: WebGlBuffer,
-    sphere_index_count: i32,
-    quad_vertex_buffer: WebGlBuffer,
-    quad_uv_buffer: WebGlBuffer,
-    quad_index_buffer: WebGlBuffer,
-    letters: Vec<OrbitingLetter>,
-    camera_pos: Vec3,
-    view_matrix: Mat4,
-    projection_matrix: Mat4,
-}
+    fn transform_point(m: &Mat4, p: Vec3) -> Vec3 {
+        let v = m.transform_vec4(Vec4::from_vec3(p, 1.0));
+        Vec3::new(v.x, v.y, v.z)
+    }
 
-impl App {
-    fn new(gl: GL, document: &web_sys::Document, width: u32, height: u32) -> Result<Self, String> {
-        // Compile shaders
-        let sphere_vert = compile_shader(&gl, GL::VERTEX_SHADER, SPHERE_VERTEX_SHADER)?;
-        let sphere_frag = compile_shader(&gl, GL::FRAGMENT_SHADER, SPHERE_FRAGMENT_SHADER)?;
-        let sphere_program = link_program(&gl, &sphere_vert, &sphere_frag)?;
+    #[track_caller]
+    fn assert_vec3_close(a: Vec3, b: Vec3) {
+        assert_close(a.x, b.x);
+        assert_close(a.y, b.y);
+        assert_close(a.z, b.z);
+    }
 
-        let text_vert = compile_shader(&gl, GL::VERTEX_SHADER, TEXT_VERTEX_SHADER)?;
-        let text_frag = compile_shader(&gl, GL::FRAGMENT_SHADER, TEXT_FRAGMENT_SHADER)?;
-        let text_program = link_program(&gl, &text_vert, &text_frag)?;
+    #[test]
+    fn quarter_turns_map_axes() {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let quarter = PI / 2.0;
+        assert_vec3_close(transform_point(&Mat4::rotation_z(quarter), x), y);
+        assert_vec3_close(transform_point(&Mat4::rotation_x(quarter), y), z);
+        assert_vec3_close(transform_point(&Mat4::rotation_y(quarter), z), x);
+        // Each leaves its own axis alone
+        assert_vec3_close(transform_point(&Mat4::rotation_z(quarter), z), z);
+        assert_vec3_close(transform_point(&Mat4::rotation_x(quarter), x), x);
+        assert_vec3_close(transform_point(&Mat4::rotation_y(quarter), y), y);
+    }
 
-        // Generate sphere
-        let (sphere_verts, sphere_normals, sphere_indices) = generate_sphere(1.0, 32, 64);
-        let sphere_vertex_buffer = create_buffer(&gl, &sphere_verts)?;
-        let sphere_normal_buffer = create_buffer(&gl, &sphere_normals)?;
-        let sphere_index_buffer = create_index_buffer(&gl, &sphere_indices)?;
-        let sphere_index_count = sphere_indices.len() as i32;
+    #[test]
+    fn translation_offsets_points_but_not_directions() {
+        let m = Mat4::translation(1.0, -2.0, 0.5);
+        let p = Vec3::new(3.0, 4.0, 5.0);
+        assert_vec3_close(transform_point(&m, p), Vec3::new(4.0, 2.0, 5.5));
+        let d = m.transform_vec4(Vec4::from_vec3(p, 0.0));
+        assert_vec3_close(Vec3::new(d.x, d.y, d.z), p);
+    }
 
-        // Generate quad for text billboards
-        let (quad_verts, quad_uvs, quad_indices) = generate_quad();
-        let quad_vertex_buffer = create_buffer(&gl, &quad_verts)?;
-        let quad_uv_buffer = create_buffer(&gl, &quad_uvs)?;
-        let quad_index_buffer = create_index_buffer(&gl, &quad_indices)?;
+    #[test]
+    fn scale_stretches_each_axis() {
+        let m = Mat4::scale(2.0, 3.0, -1.0);
+        let p = Vec3::new(1.0, 1.0, 4.0);
+        assert_vec3_close(transform_point(&m, p), Vec3::new(2.0, 3.0, -4.0));
+        let uniform = Mat4::scale_uniform(0.5);
+        assert_vec3_close(transform_point(&uniform, p), Vec3::new(0.5, 0.5, 2.0));
+    }
 
-        // Create letter textures and orbits for "[wasm-text-sphere]"
-        // Letters orbit clockwise (negative velocity) and are evenly spaced
-        let text = "[wasm-text-sphere]";
-        let colors = [
-            "#FF6B6B", // Red
-            "#4ECDC4", // Teal
-            "#45B7D1", // Blue
-            "#96CEB4", // Green
-            "#FFEAA7", // Yellow
-            "#DDA0DD", // Plum
-            "#98D8C8", // Mint
-            "#F7DC6F", // Gold
-            "#BB8FCE", // Purple
-            "#85C1E9", // Light blue
-            "#F8B500", // Orange
-            "#00CED1", // Dark cyan
-            "#FF69B4", // Hot pink
-            "#7FFF00", // Chartreuse
-            "#FFB6C1", // Light pink
-            "#40E0D0", // Turquoise
-            "#FF6347", // Tomato
-            "#9370DB", // Medium purple
-        ];
+    #[test]
+    fn ortho_maps_box_corners_to_ndc_cube() {
+        let (left, right, bottom, top, near, far) = (-3.0, 1.0, -0.5, 2.0, 0.1, 100.0);
+        let m = Mat4::ortho(left, right, bottom, top, near, far);
+        for (x, ndc_x) in [(left, -1.0), (right, 1.0)] {
+            for (y, ndc_y) in [(bottom, -1.0), (top, 1.0)] {
+                // The camera looks down -Z: the near plane maps to -1 and the far to +1
+                for (z, ndc_z) in [(-near, -1.0), (-far, 1.0)] {
+                    let clip = m.transform_vec4(Vec4::from_vec3(Vec3::new(x, y, z), 1.0));
+                    let ndc = clip.perspective_divide().expect("ortho keeps w = 1");
+                    assert_close(clip.w, 1.0);
+                    assert_vec3_close(ndc, Vec3::new(ndc_x, ndc_y, ndc_z));
+                }
+            }
+        }
+    }
 
-        let char_count = text.chars().count();
-        let mut letters = Vec::new();
+    #[test]
+    fn zero_angle_quat_is_identity_matrix() {
+        let q = Quat::from_axis_angle(Vec3::new(0.3, -1.0, 2.0), 0.0);
+        assert_mat4_close(&q.to_mat4(), &Mat4::identity());
+    }
 
-        for (i, ch) in text.chars().enumerate() {
-            let phase = -PI / 2.0 - (i as f32 * 2.0 * PI / char_count as f32);
-            let color = colors[i % colors.len()];
-            let letter_str = ch.to_string();
-            let texture = create_text_texture(&gl, document, &letter_str, color)?;
+    #[test]
+    fn slerp_hits_both_endpoints() {
+        let a = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 0.3);
+        let b = Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 2.1);
+        assert_mat4_close(&a.slerp(b, 0.0).to_mat4(), &a.to_mat4());
+        assert_mat4_close(&a.slerp(b, 1.0).to_mat4(), &b.to_mat4());
+        // Halfway about a shared axis is half the angle
+        let c = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 1.5);
+        assert_mat4_close(&a.slerp(c, 0.5).to_mat4(), &Mat4::rotation_y(0.9));
+    }
 
-            letters.push(OrbitingLetter {
-                texture,
-                radius: 2.2,
-                inclination: 0.12,
-                phase,
-                angular_velocity: 0.3,
-            });
-        }
+    #[test]
+    fn quat_product_matches_matrix_product() {
+        let (axis_a, axis_b) = (Vec3::new(1.0, 2.0, -0.5), Vec3::new(0.0, -1.0, 3.0));
+        let q = Quat::from_axis_angle(axis_a, 0.8).mul(Quat::from_axis_angle(axis_b, -1.3));
+        let m = Mat4::rotation_axis(axis_a, 0.8).multiply(Mat4::rotation_axis(axis_b, -1.3));
+        assert_mat4_close(&q.to_mat4(), &m);
+        let v = Vec3::new(0.2, -0.7, 1.1);
+        assert_vec3_close(q.rotate(v), transform_point(&m, v));
+    }
 
-        // Camera setup
-        let camera_pos = Vec3::new(0.0, 0.5, 5.0);
-        let target = Vec3::new(0.0, 0.0, 0.0);
-        let up = Vec3::new(0.0, 1.0, 0.0);
-        let view_matrix = Mat4::look_at(camera_pos, target, up);
+    #[test]
+    fn vec3_length_and_lerp() {
+        assert_close(Vec3::new(3.0, 4.0, 12.0).length(), 13.0);
+        let (a, b) = (Vec3::new(1.0, -2.0, 0.5), Vec3::new(-3.0, 6.0, 2.5));
+        assert_vec3_close(a.lerp(b, 0.0), a);
+        assert_vec3_close(a.lerp(b, 1.0), b);
+        assert_vec3_close(a.lerp(b, 0.5), Vec3::new(-1.0, 2.0, 1.5));
+    }
 
-        let aspect = width as f32 / height as f32;
-        let projection_matrix = Mat4::perspective(PI / 4.0, aspect, 0.1, 100.0);
+    #[test]
+    fn vec3_operators_match_methods() {
+        let (a, b) = (Vec3::new(1.0, -2.0, 0.5), Vec3::new(-3.0, 6.0, 2.5));
+        assert_vec3_close(a - b, a.sub(b));
+        assert_vec3_close(a + b, a.add(b));
+        assert_vec3_close(a * 2.0, a.scale(2.0));
+        assert_vec3_close(-a, Vec3::new(-1.0, 2.0, -0.5));
+    }
 
-        // WebGL state
-        gl.enable(GL::DEPTH_TEST);
-        gl.enable(GL::BLEND);
-        gl.blend_func(GL::SRC_ALPHA, GL::ONE_MINUS_SRC_ALPHA);
-        gl.clear_color(0.05, 0.05, 0.1, 1.0);
+    #[test]
+    fn from_vec3_keeps_components() {
+        let v = Vec4::from_vec3(Vec3::new(1.0, -2.0, 3.5), 1.0);
+        assert_eq!((v.x, v.y, v.z, v.w), (1.0, -2.0, 3.5, 1.0));
+        assert_vec3_close(
+            v.perspective_divide().expect("w = 1"),
+            Vec3::new(1.0, -2.0, 3.5),
+        );
+    }
 
-        Ok(Self {
-            gl,
-            sphere_program,
-            text_program,
-            sphere_vertex_buffer,
-            sphere_normal_buffer,
-            sphere_index_buffer,
-            sphere_index_count,
-            quad_vertex_buffer,
-            quad_uv_buffer,
-            quad_index_buffer,
-            letters,
-            camera_pos,
-            view_matrix,
-            projection_matrix,
-        })
+    #[test]
+    fn perspective_divide_of_projected_point() {
+        // 90 degree field of view, so x and y are not scaled before the divide
+        let m = Mat4::perspective(PI / 2.0, 1.0, 1.0, 3.0);
+        let clip = m.transform_vec4(Vec4::from_vec3(Vec3::new(0.5, -0.25, -2.0), 1.0));
+        assert_close(clip.w, 2.0);
+        let ndc = clip
+            .perspective_divide()
+            .expect("point is in front of the camera");
+        assert_vec3_close(ndc, Vec3::new(0.25, -0.125, 0.5));
+        // A point on the near plane lands on the near face of the NDC cube
+        let near = m.transform_vec4(Vec4::from_vec3(Vec3::new(0.0, 0.0, -1.0), 1.0));
+        assert_close(near.perspective_divide().expect("w = 1").z, -1.0);
+        // A point in the camera plane has no projection
+        let eye = m.transform_vec4(Vec4::from_vec3(Vec3::new(1.0, 1.0, 0.0), 1.0));
+        assert!(eye.perspective_divide().is_none());
     }
 
-    fn render(&self, time: f32) {
-        let gl = &self.gl;
+    #[test]
+    fn icosphere_vertices_lie_on_radius() {
+        let radius = 1.7;
+        for subdivisions in 0..=3 {
+            let (vertices, normals, indices) = generate_icosphere(radius, subdivisions);
+            assert_eq!(indices.len(), 20 * 4usize.pow(subdivisions) * 3);
+            assert_eq!(normals.len(), vertices.len());
+            for p in vertices.chunks(3) {
+                assert_close(Vec3::new(p[0], p[1], p[2]).length(), radius);
+            }
+            assert!(indices.iter().all(|&i| (i as usize) < vertices.len() / 3));
+        }
+    }
 
-        gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
+    #[test]
+    fn time_scale_converges_to_target() {
+        let mut speeds = AnimationSpeeds::new();
+        speeds.time_scale.retarget(3.0, DEFAULT_SPEED_RAMP_DURATION);
+        let mut gap = 2.0;
+        // One second of 60 fps frames, twice the ramp
+        for _ in 0..60 {
+            speeds.step(1.0 / 60.0);
+            let next_gap = 3.0 - speeds.time_scale.current();
+            assert!(
+                next_gap <= gap,
+                "the scale must approach the target every frame"
+            );
+            gap = next_gap;
+        }
+        assert_eq!(speeds.time_scale.current(), 3.0);
+    }
 
-        let sphere_rotation = time * 0.1;
-        let model_matrix = Mat4::rotation_y(sphere_rotation);
+    #[test]
+    fn flat_shading_gives_each_triangle_its_own_vertices() {
+        let (vertices, _, indices, uvs) =
+            central_mesh_geometry(CentralMesh::Sphere, 2.0, (8, 12), Shading::Flat);
+        assert_eq!(indices.len(), 8 * 12 * 6);
+        assert_eq!(vertices.len(), indices.len() * 3);
+        assert_eq!(uvs.expect("spheres have UVs").len(), indices.len() * 2);
+        // The icosphere has no zero-area pole triangles, so every facet has a normal
+        let icosphere = CentralMesh::Icosphere { subdivisions: 2 };
+        let (vertices, normals, indices, _) =
+            central_mesh_geometry(icosphere, 2.0, (0, 0), Shading::Flat);
+        assert_eq!(vertices.len(), indices.len() * 3);
+        for (triangle, corners) in normals.chunks(9).zip(vertices.chunks(9)) {
+            assert_eq!(triangle[0..3], triangle[3..6]);
+            assert_eq!(triangle[0..3], triangle[6..9]);
+            // Facets face away from the center
+            let normal = Vec3::new(triangle[0], triangle[1], triangle[2]);
+            assert!(normal.dot(Vec3::new(corners[0], corners[1], corners[2])) > 0.0);
+        }
+        assert!((0..indices.len()).all(|i| indices.get(i) == i));
+    }
 
-        // Draw sphere
-        gl.use_program(Some(&self.sphere_program));
+    #[test]
+    fn smooth_shading_keeps_the_indexed_mesh() {
+        let (vertices, normals, indices, uvs) = generate_sphere(2.0, 8, 12);
+        let (smooth_vertices, smooth_normals, smooth_indices, smooth_uvs) =
+            central_mesh_geometry(CentralMesh::Sphere, 2.0, (8, 12), Shading::Smooth);
+        assert_eq!(smooth_vertices, vertices);
+        assert_eq!(smooth_normals, normals);
+        assert_eq!(smooth_uvs, Some(uvs));
+        let MeshIndices::U16(smooth_indices) = smooth_indices else {
+            panic!("a small sphere fits u16 indices");
+        };
+        assert_eq!(smooth_indices, indices);
+    }
 
-        let u_model = gl.get_uniform_location(&self.sphere_program, "u_model");
-        let u_view = gl.get_uniform_location(&self.sphere_program, "u_view");
-        let u_projection = gl.get_uniform_location(&self.sphere_program, "u_projection");
-        let u_light_pos = gl.get_uniform_location(&self.sphere_program, "u_lightPos");
-        let u_color = gl.get_uniform_location(&self.sphere_program, "u_color");
-        let u_view_pos = gl.get_uniform_location(&self.sphere_program, "u_viewPos");
+    #[test]
+    fn detailed_sphere_switches_to_u32_indices() {
+        let (lat, lon) = clamp_sphere_segments(256, 512, MAX_U32_SPHERE_VERTICES);
+        let (vertices, _, indices, _) =
+            central_mesh_geometry(CentralMesh::Sphere, 1.0, (lat, lon), Shading::Smooth);
+        let vertex_count = vertices.len() / 3;
+        assert!(vertex_count as u32 > MAX_U16_VERTICES);
+        let MeshIndices::U32(indices) = indices else {
+            panic!("{vertex_count} vertices cannot use u16 indices");
+        };
+        assert!(indices.iter().any(|&i| i > u16::MAX as u32));
+        assert!(indices.iter().all(|&i| (i as usize) < vertex_count));
+    }
 
-        gl.uniform_matrix4fv_with_f32_array(u_model.as_ref(), false, &model_matrix.data);
-        gl.uniform_matrix4fv_with_f32_array(u_view.as_ref(), false, &self.view_matrix.data);
-        gl.uniform_matrix4fv_with_f32_array(
-            u_projection.as_ref(),
-            false,
-            &self.projection_matrix.data,
+    #[test]
+    fn mesh_index_counts_match_segments() {
+        for (lat, lon) in [(0, 1), (3, 3), (32, 64), (200, 400)] {
+            let (lat, lon) = clamp_sphere_segments(lat, lon, MAX_U16_VERTICES);
+            assert!(lat >= 3 && lon >= 3 && (lat + 1) * (lon + 1) <= MAX_U16_VERTICES);
+            let (vertices, _, indices, _) = generate_sphere(1.0, lat, lon);
+            assert_eq!(vertices.len() / 3, ((lat + 1) * (lon + 1)) as usize);
+            assert_eq!(indices.len(), (lat * lon * 6) as usize);
+        }
+        // The flat meshes repeat every triangle, reversed, for the back face
+        let (_, _, indices) = generate_disc(1.0, 24);
+        assert_eq!(indices.len(), 24 * 3 * 2);
+        let (_, _, indices) = generate_ring(0.5, 1.0, 24);
+        assert_eq!(indices.len(), 24 * 6 * 2);
+    }
+
+    #[test]
+    fn sphere_has_one_uv_per_vertex() {
+        for (lat, lon) in [(3, 3), (16, 32), (32, 64)] {
+            let (vertices, _, _, uvs) = generate_sphere(1.0, lat, lon);
+            assert_eq!(uvs.len() / 2, vertices.len() / 3);
+            assert!(uvs.iter().all(|uv| (0.0..=1.0).contains(uv)));
+            // The seam column is duplicated: u = 0 and u = 1 share positions
+            let row = (lon + 1) as usize;
+            for lat in 1..lat as usize {
+                let (first, last) = (lat * row, lat * row + row - 1);
+                assert_eq!((uvs[first * 2], uvs[last * 2]), (0.0, 1.0));
+                assert_vec3_close(
+                    Vec3::new(
+                        vertices[first * 3],
+                        vertices[first * 3 + 1],
+                        vertices[first * 3 + 2],
+                    ),
+                    Vec3::new(
+                        vertices[last * 3],
+                        vertices[last * 3 + 1],
+                        vertices[last * 3 + 2],
+                    ),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn circular_orbit_matches_the_original_path() {
+        let letter = orbiting_letter(3.0, 0.12, 0.4, 0.3, 0.0);
+        let effects = OrbitEffects::default();
+        for time in [0.0_f32, 0.5, 2.0, 7.25, 31.0] {
+            // The path before eccentricity: a tilted circle of `radius`
+            let angle = 0.4 + 0.3 * time;
+            let (x, z) = (3.0 * angle.cos(), 3.0 * angle.sin());
+            let expected = Vec3::new(x, z * 0.12_f32.sin(), z * 0.12_f32.cos());
+            assert_vec3_close(letter.position_at(time, &effects), expected);
+        }
+        // An eccentric orbit keeps the semi-major axis but pulls in at a quarter turn
+        let ellipse = orbiting_letter(3.0, 0.0, 0.0, 1.0, 0.6);
+        assert_vec3_close(ellipse.position_at(0.0, &effects), Vec3::new(3.0, 0.0, 0.0));
+        let quarter = ellipse.position_at(PI / 2.0, &effects);
+        assert_close(quarter.z, 3.0 * 0.8);
+    }
+
+    #[test]
+    fn counter_rotating_rings_diverge() {
+        let effects = OrbitEffects::default();
+        let forward = orbiting_letter(3.0, 0.0, 0.0, 0.3, 0.0);
+        let backward = orbiting_letter(3.0, 0.0, 0.0, -0.3, 0.0);
+        let angle_between = |time: f32| {
+            let (a, b) = (
+                forward.position_at(time, &effects),
+                backward.position_at(time, &effects),
+            );
+            (a.dot(b) / (a.length() * b.length()))
+                .clamp(-1.0, 1.0)
+                .acos()
+        };
+        assert_close(angle_between(0.0), 0.0);
+        let mut last = 0.0;
+        for step in 1..=10 {
+            let angle = angle_between(step as f32 * 0.5);
+            assert!(
+                angle > last,
+                "rings must separate at t = {}",
+                step as f32 * 0.5
+            );
+            assert_close(angle, 2.0 * 0.3 * step as f32 * 0.5);
+            last = angle;
+        }
+    }
+
+    #[test]
+    fn css_font_is_well_formed() {
+        assert_eq!(css_font("Outfit", "bold", 90), LETTER_FONT);
+        assert_eq!(
+            css_font("  Fira Sans ", " 600 ", 48),
+            "600 48px 'Fira Sans', sans-serif"
         );
-        gl.uniform3f(u_light_pos.as_ref(), 5.0, 5.0, 5.0);
-        gl.uniform3f(u_color.as_ref(), 0.25, 0.45, 0.75);
-        gl.uniform3f(
-            u_view_pos.as_ref(),
-            self.camera_pos.x,
-            self.camera_pos.y,
-            self.camera_pos.z,
+        assert_eq!(css_font("Georgia, serif", "", 32), "32px Georgia, serif");
+        assert_eq!(
+            css_font("\"Noto Serif\"", "normal", 64),
+            "normal 64px \"Noto Serif\""
+        );
+        assert_eq!(css_font("", "bold", 90), "bold 90px sans-serif");
+        // Sizes stay within the glyph cell
+        assert_eq!(
+            css_font("Outfit", "bold", 0),
+            "bold 1px 'Outfit', sans-serif"
         );
+        assert_eq!(
+            css_font("Outfit", "bold", 4096),
+            "bold 128px 'Outfit', sans-serif"
+        );
+    }
 
-        let a_position = gl.get_attrib_location(&self.sphere_program, "a_position") as u32;
-        let a_normal = gl.get_attrib_location(&self.sphere_program, "a_normal") as u32;
+    #[test]
+    fn two_colors_alternate_across_letters() {
+        let palette = ["#f00", "#00f"];
+        let text = "ABCD";
+        let sprites = vec![false; text.chars().count()];
+        let colors = assign_colors(&palette, &sprites);
+        assert_eq!(
+            colors,
+            [Some("#f00"), Some("#00f"), Some("#f00"), Some("#00f")]
+        );
+        // A sprite keeps its color and the letters after it carry on the cycle
+        let colors = assign_colors(&palette, &[false, true, false, false]);
+        assert_eq!(colors, [Some("#f00"), None, Some("#00f"), Some("#f00")]);
+    }
 
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.sphere_vertex_buffer));
-        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, 0, 0);
-        gl.enable_vertex_attrib_array(a_position);
+    #[test]
+    fn blank_palette_entries_use_builtin_colors() {
+        let palette = resolve_palette(vec![" #abc ".into(), "".into(), "  ".into()]);
+        assert_eq!(palette, ["#abc", LETTER_COLORS[1], LETTER_COLORS[2]]);
+    }
 
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.sphere_normal_buffer));
-        gl.vertex_attrib_pointer_with_i32(a_normal, 3, GL::FLOAT, false, 0, 0);
-        gl.enable_vertex_attrib_array(a_normal);
+    #[test]
+    fn speed_multipliers_move_the_letters() {
+        let letter = orbiting_letter(3.0, 0.0, 0.0, 0.3, 0.0);
+        let effects = OrbitEffects::default();
+        // Where the letter is after a second of 60 fps frames under `speeds`
+        let run = |speeds: &mut AnimationSpeeds| {
+            let (mut letter_clock, mut sphere_clock) = (Clock::new(), Clock::new());
+            for _ in 0..60 {
+                let (letter_dt, sphere_dt) = speeds.step(1.0 / 60.0);
+                letter_clock.advance(letter_dt);
+                sphere_clock.advance(sphere_dt);
+            }
+            (
+                letter.position_at(letter_clock.elapsed, &effects),
+                sphere_clock.elapsed,
+            )
+        };
+        let (normal, spin) = run(&mut AnimationSpeeds::new());
+        assert_vec3_close(normal, letter.position_at(1.0, &effects));
+        assert_close(spin, 1.0);
 
-        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.sphere_index_buffer));
-        gl.draw_elements_with_i32(
-            GL::TRIANGLES,
-            self.sphere_index_count,
-            GL::UNSIGNED_SHORT,
-            0,
-        );
+        let mut fast = AnimationSpeeds::new();
+        fast.orbit = 2.0;
+        fast.sphere_spin = 0.5;
+        let (position, spin) = run(&mut fast);
+        assert_vec3_close(position, letter.position_at(2.0, &effects));
+        assert_close(spin, 0.5);
 
-        // Draw orbiting letters
-        gl.use_program(Some(&self.text_program));
+        // Negative multipliers run the orbit backwards from the same start
+        let mut reversed = AnimationSpeeds::new();
+        reversed.orbit = -1.0;
+        assert_vec3_close(run(&mut reversed).0, letter.position_at(-1.0, &effects));
 
-        let u_model = gl.get_uniform_location(&self.text_program, "u_model");
-        let u_view = gl.get_uniform_location(&self.text_program, "u_view");
-        let u_projection = gl.get_uniform_location(&self.text_program, "u_projection");
-        let u_texture = gl.get_uniform_location(&self.text_program, "u_texture");
+        let mut frozen = AnimationSpeeds::new();
+        frozen.time_scale = SpeedRamp::new(0.0);
+        let (position, spin) = run(&mut frozen);
+        assert_vec3_close(position, letter.position_at(0.0, &effects));
+        assert_close(spin, 0.0);
+    }
 
-        gl.uniform_matrix4fv_with_f32_array(u_view.as_ref(), false, &self.view_matrix.data);
-        gl.uniform_matrix4fv_with_f32_array(
-            u_projection.as_ref(),
-            false,
-            &self.projection_matrix.data,
+    #[test]
+    fn colors_are_clamped_to_unit_range() {
+        let color = Vec3::new(-0.5, 0.25, 3.0).clamp01();
+        assert_vec3_close(color, Vec3::new(0.0, 0.25, 1.0));
+    }
+
+    #[test]
+    fn background_is_clamped_and_premultiplied() {
+        let (stored, clear) = background_colors([1.5, 0.5, -0.2, 0.5]);
+        assert_eq!(stored, [1.0, 0.5, 0.0, 0.5]);
+        assert_eq!(clear, [0.5, 0.25, 0.0, 0.5]);
+        // An opaque background clears to itself
+        assert_eq!(background_colors(BACKGROUND), (BACKGROUND, BACKGROUND));
+    }
+
+    #[test]
+    fn normal_matrix_of_rotation_is_its_upper_left() {
+        let rotation = Mat4::rotation_y(1.1);
+        let normal = rotation.normal_matrix();
+        for column in 0..3 {
+            for row in 0..3 {
+                assert_close(normal[column * 3 + row], rotation.data[column * 4 + row]);
+            }
+        }
+        // Translation doesn't affect normals either
+        let moved = Mat4::translation(4.0, -1.0, 2.0).multiply(rotation);
+        for (a, b) in moved.normal_matrix().iter().zip(normal) {
+            assert_close(*a, b);
+        }
+    }
+
+    #[test]
+    fn normal_matrix_of_arbitrary_axis_rotation_is_its_upper_left() {
+        let rotation = Mat4::rotation_axis(Vec3::new(1.0, -2.0, 0.5), 1.1);
+        let normal = rotation.normal_matrix();
+        for column in 0..3 {
+            for row in 0..3 {
+                assert_close(normal[column * 3 + row], rotation.data[column * 4 + row]);
+            }
+        }
+    }
+
+    #[test]
+    fn bounce_leaves_a_centered_letter_in_place() {
+        let effects = OrbitEffects {
+            bounce_interval: 2.0,
+            bounce_height: 0.5,
+            ..OrbitEffects::default()
+        };
+        assert!(effects.bounce_drop(0.1, 0.0) > 0.0);
+        let centered = orbiting_letter(0.0, 0.0, 0.0, 0.3, 0.0);
+        assert_vec3_close(
+            centered.position_at(0.1, &effects),
+            Vec3::new(0.0, 0.0, 0.0),
         );
-        gl.uniform1i(u_texture.as_ref(), 0);
+        // An orbiting letter still drops toward the center
+        let orbiting = orbiting_letter(3.0, 0.0, 0.0, 0.0, 0.0);
+        assert!(orbiting.position_at(0.1, &effects).length() < 3.0);
+    }
 
-        let a_position = gl.get_attrib_location(&self.text_program, "a_position") as u32;
-        let a_uv = gl.get_attrib_location(&self.text_program, "a_uv") as u32;
+    #[test]
+    fn runs_keep_order_and_merge_only_neighbors() {
+        let keys = [1, 1, 2, 1, 3, 3, 3];
+        assert_eq!(
+            consecutive_runs(&keys, |&k| k),
+            [(0, 2), (2, 1), (3, 1), (4, 3)]
+        );
+        assert!(consecutive_runs(&[] as &[usize], |&k| k).is_empty());
+    }
 
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_vertex_buffer));
-        gl.vertex_attrib_pointer_with_i32(a_position, 3, GL::FLOAT, false, 0, 0);
-        gl.enable_vertex_attrib_array(a_position);
+    #[test]
+    fn default_material_uploads_the_original_constants() {
+        // The shader used to bake ambient 0.15, diffuse 0.7, specular 0.3 and exponent 32
+        assert_eq!(DEFAULT_MATERIAL.uniforms(), [0.15, 0.7, 0.3, 32.0]);
+    }
 
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.quad_uv_buffer));
-        gl.vertex_attrib_pointer_with_i32(a_uv, 2, GL::FLOAT, false, 0, 0);
-        gl.enable_vertex_attrib_array(a_uv);
+    #[test]
+    fn material_setters_clamp_what_they_upload() {
+        let mut material = DEFAULT_MATERIAL;
+        material.set_response(0.0, -1.0, -0.5);
+        material.set_ambient(-0.2);
+        assert_eq!(material.uniforms(), [0.0, 0.0, 0.0, MIN_SHININESS]);
 
-        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.quad_index_buffer));
+        material.set_response(64.0, 0.8, 1.2);
+        material.set_ambient(0.4);
+        assert_eq!(material.uniforms(), [0.4, 1.2, 0.8, 64.0]);
+    }
 
-        for letter in &self.letters {
-            let pos = letter.position_at(time);
-            let letter_model = Mat4::billboard(pos, self.camera_pos, 0.6);
+    #[test]
+    fn light_uniform_arrays_cover_every_light() {
+        let light = Light::new(Vec3::new(5.0, -12.0, 40.0), Vec3::new(2.0, 0.5, -1.0));
+        assert_vec3_close(light.position, Vec3::new(5.0, -12.0, 40.0));
+        assert_vec3_close(light.color, Vec3::new(1.0, 0.5, 0.0));
 
-            gl.uniform_matrix4fv_with_f32_array(u_model.as_ref(), false, &letter_model.data);
+        let mut lights = DEFAULT_LIGHTS;
+        lights[SPHERE_LIGHTS - 1] = light;
+        let (positions, colors) = light_uniforms(&lights.map(|l| l.position), &lights);
+        assert_eq!(positions.len(), SPHERE_LIGHTS * 3);
+        assert_eq!(colors.len(), SPHERE_LIGHTS * 3);
+        assert_eq!(positions[..3], [5.0, 5.0, 5.0]);
+        assert_eq!(colors[colors.len() - 3..], [1.0, 0.5, 0.0]);
+        assert!(
+            sphere_fragment_shader()
+                .starts_with(&format!("#define SPHERE_LIGHTS {SPHERE_LIGHTS}\n"))
+        );
+    }
 
-            gl.active_texture(GL::TEXTURE0);
-            gl.bind_texture(GL::TEXTURE_2D, Some(&letter.texture));
+    #[test]
+    fn screen_up_billboards_follow_camera_roll() {
+        let eye = Vec3::new(1.0, 2.0, 6.0);
+        let target = Vec3::new(0.0, 0.5, 0.0);
+        let roll = 0.7;
+        let view = Mat4::look_at(eye, target, rolled_up(eye, target, roll));
+        let unrolled = Mat4::look_at(eye, target, rolled_up(eye, target, 0.0));
+        let camera_up = |m: &Mat4| Vec3::new(m.data[1], m.data[5], m.data[9]);
+        assert_close(camera_up(&view).dot(camera_up(&unrolled)), roll.cos());
 
-            gl.draw_elements_with_i32(GL::TRIANGLES, 6, GL::UNSIGNED_SHORT, 0);
-        }
+        // A letter on the line of sight: screen-up keeps its up axis on the screen's Y,
+        // while world-up stays tilted by the roll
+        let position = target.sub(eye.sub(target));
+        let up_on_screen = |model: &Mat4| {
+            let up = Vec3::new(model.data[4], model.data[5], model.data[6]);
+            let v = view.transform_vec4(Vec4::from_vec3(up, 0.0));
+            Vec3::new(v.x, v.y, v.z).normalize()
+        };
+        let screen = Mat4::billboard_with_up(position, eye, camera_up(&view), 1.0);
+        assert_vec3_close(up_on_screen(&screen), Vec3::new(0.0, 1.0, 0.0));
+        let world = Mat4::billboard(position, eye, 1.0);
+        assert_close(up_on_screen(&world).y, roll.cos());
     }
-}
 
-fn request_animation_frame(f: &Closure<dyn FnMut(f64)>) {
-    web_sys::window()
-        .unwrap()
-        .request_animation_frame(f.as_ref().unchecked_ref())
-        .unwrap();
-}
+    #[test]
+    fn inclination_distribution_keeps_explicit_inclinations() {
+        let mut letters = vec![
+            orbiting_letter(2.2, 0.12, PI / 2.0, 0.3, 0.0),
+            orbiting_letter(2.2, 0.8, PI / 2.0, 0.3, 0.0),
+        ];
+        letters[1].inherits_inclination = false;
+        apply_inclination_distribution(&mut letters, 0.2, 0.5);
+        assert_close(letters[0].inclination, 0.7);
+        assert_close(letters[1].inclination, 0.8);
+    }
 
-#[wasm_bindgen(start)]
-pub fn main() -> Result<(), JsValue> {
-    console_error_panic_hook::set_once();
+    #[test]
+    fn orbit_values_must_be_finite() {
+        assert!(check_orbit_value("phase", -3.0).is_ok());
+        assert!(check_orbit_value("phase", f32::NAN).is_err());
+        assert!(check_orbit_value("inclination", f32::INFINITY).is_err());
+        assert!(check_orbit_radius(0.0).is_ok());
+        assert!(check_orbit_radius(-0.5).is_err());
+        assert!(check_orbit_radius(f32::NAN).is_err());
+    }
 
-    let window = web_sys::window().ok_or("No window")?;
-    let document = window.document().ok_or("No document")?;
-    let canvas = document
-        .get_element_by_id("canvas")
-        .ok_or("No canvas")?
-        .dyn_into::<HtmlCanvasElement>()?;
+    #[test]
+    fn orbit_params_round_trip_preserves_the_scene() {
+        let mut letters = vec![
+            orbiting_letter(2.2, 0.12, 0.5, 0.3, 0.0),
+            orbiting_letter(3.0, 0.4, 1.5, -0.2, 0.0),
+        ];
+        let data = orbit_params(&letters);
+        apply_orbit_params(&mut letters, &data).unwrap();
+        assert_eq!(orbit_params(&letters), data);
+        assert!(letters.iter().all(|l| l.inherits_inclination));
 
-    let width = window.inner_width()?.as_f64().unwrap() as u32;
-    let height = window.inner_height()?.as_f64().unwrap() as u32;
-    canvas.set_width(width);
-    canvas.set_height(height);
+        // Only the letter whose inclination changed leaves the distribution
+        let mut tilted = data.clone();
+        tilted[ORBIT_PARAM_STRIDE + 1] = 0.9;
+        apply_orbit_params(&mut letters, &tilted).unwrap();
+        assert!(letters[0].inherits_inclination);
+        assert!(!letters[1].inherits_inclination);
 
-    let gl = canvas
-        .get_context("webgl")?
-        .ok_or("WebGL not supported")?
-        .dyn_into::<GL>()?;
+        // A bad value anywhere rejects the whole call
+        let mut bad = data.clone();
+        bad[0] = 5.0;
+        bad[ORBIT_PARAM_STRIDE] = -1.0;
+        assert!(apply_orbit_params(&mut letters, &bad).is_err());
+        bad[ORBIT_PARAM_STRIDE] = 1.0;
+        bad[ORBIT_PARAM_STRIDE + 2] = f32::NAN;
+        assert!(apply_orbit_params(&mut letters, &bad).is_err());
+        assert_eq!(orbit_params(&letters), tilted);
+    }
 
-    gl.viewport(0, 0, width as i32, height as i32);
+    #[test]
+    fn repeated_glyphs_share_textures_only_with_one_color() {
+        let text: Vec<String> = "MISSISSIPPI".chars().map(String::from).collect();
+        let style = GlyphStyle::default();
+        let sprites = vec![false; text.len()];
+        let distinct = |palette: &[&str]| {
+            let keys: std::collections::HashSet<GlyphKey> = text
+                .iter()
+                .zip(assign_colors(palette, &sprites))
+                .map(|(glyph, color)| glyph_key(glyph, color.unwrap(), &style))
+                .collect();
+            keys.len()
+        };
+        assert_eq!(distinct(&["#FFFFFF"]), 4);
+        // The built-in palette colors every position differently, so nothing is shared
+        assert_eq!(distinct(&LETTER_COLORS), text.len());
+    }
 
-    let app = Rc::new(RefCell::new(
-        App::new(gl, &document, width, height).map_err(|e| JsValue::from_str(&e))?,
-    ));
+    #[test]
+    fn speed_ramp_has_no_jumps() {
+        let mut speeds = AnimationSpeeds::new();
+        speeds.time_scale.retarget(3.0, 1.0);
+        // Retargeting keeps the speed where it was
+        assert_close(speeds.time_scale.current(), 1.0);
+        let frame = 1.0 / 60.0;
+        let mut last = speeds.time_scale.current();
+        for _ in 0..30 {
+            speeds.step(frame);
+            let current = speeds.time_scale.current();
+            assert!((current - last).abs() < 0.1, "{last} -> {current} jumped");
+            last = current;
+        }
+
+        // Mid-ramp, a new target starts from the current speed
+        speeds.time_scale.retarget(0.0, 1.0);
+        assert_close(speeds.time_scale.current(), last);
+        for _ in 0..60 {
+            speeds.step(frame);
+            let current = speeds.time_scale.current();
+            assert!((current - last).abs() < 0.1, "{last} -> {current} jumped");
+            last = current;
+        }
+        speeds.step(frame);
+        assert_eq!(speeds.time_scale.current(), 0.0);
 
-    let f: Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>> = Rc::new(RefCell::new(None));
-    let g = f.clone();
-    let app_clone = app.clone();
+        // A zero duration applies the new speed at once
+        speeds.time_scale.retarget(2.0, 0.0);
+        assert_eq!(speeds.time_scale.current(), 2.0);
+    }
 
-    *g.borrow_mut() = Some(Closure::new(move |timestamp: f64| {
-        let time = (timestamp / 1000.0) as f32;
-        app_clone.borrow().render(time);
-        request_animation_frame(f.borrow().as_ref().unwrap());
-    }));
+    #[test]
+    fn sphere_setters_store_clamped_values() {
+        let mut appearance = SphereAppearance::new();
+        appearance.set_color(Vec3::new(-0.5, 0.25, 3.0));
+        assert_vec3_close(appearance.color_pole, Vec3::new(0.0, 0.25, 1.0));
+        assert_vec3_close(appearance.color_equator, Vec3::new(0.0, 0.25, 1.0));
 
-    request_animation_frame(g.borrow().as_ref().unwrap());
+        appearance.set_light_position(Vec3::new(-3.0, 8.0, 1.5));
+        assert_vec3_close(appearance.lights[0].position, Vec3::new(-3.0, 8.0, 1.5));
+        // Only the key light moves, and its color is untouched
+        assert_vec3_close(appearance.lights[0].color, DEFAULT_LIGHTS[0].color);
+        assert_vec3_close(appearance.lights[1].position, DEFAULT_LIGHTS[1].position);
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        assert!(appearance.set_light(SPHERE_LIGHTS, white, white).is_err());
+    }
 
-    Ok(())
+    #[test]
+    fn glyph_atlas_packs_into_power_of_two_rows() {
+        let ((width, height), origins) = pack_glyph_atlas(&[(100, 50), (100, 60), (20, 10)], 128);
+        assert_eq!((width, height), (128, 128));
+        let gap = GLYPH_ATLAS_GAP;
+        // The small glyph fills out the second row
+        assert_eq!(
+            origins,
+            [
+                Some((0, 0)),
+                Some((0, 50 + gap)),
+                Some((100 + gap, 50 + gap))
+            ]
+        );
+        // Too wide for any row, or past the bottom: left out, the rest still packed
+        let square = (60, 60);
+        let sizes = [(200, 10), square, square, square, square, square];
+        let (_, origins) = pack_glyph_atlas(&sizes, 128);
+        let step = 60 + gap;
+        let placed = [(0, 0), (step, 0), (0, step), (step, step)];
+        assert_eq!(origins[0], None);
+        assert_eq!(origins[1..5], placed.map(Some));
+        assert_eq!(origins[5], None);
+        assert_eq!(pack_glyph_atlas(&[], 128), ((1, 1), vec![]));
+    }
+
+    #[test]
+    fn atlas_draws_default_and_long_strings_in_one_call() {
+        let style = GlyphStyle::default();
+        let cell = glyph_canvas_size(0.6 * GLYPH_CELL as f32, style.margin());
+        let long: String = "the quick brown fox jumps over the lazy dog "
+            .chars()
+            .cycle()
+            .take(500)
+            .collect();
+        for (text, before) in [("[wasm-text-sphere]", 18), (long.as_str(), 500)] {
+            let glyphs: Vec<String> = text.chars().map(String::from).collect();
+            let colors = assign_colors(&LETTER_COLORS, &vec![false; glyphs.len()]);
+            let mut textures: Vec<GlyphKey> = Vec::new();
+            let keys: Vec<usize> = glyphs
+                .iter()
+                .zip(colors)
+                .map(|(glyph, color)| {
+                    let key = glyph_key(glyph, color.unwrap(), &style);
+                    textures.iter().position(|k| *k == key).unwrap_or_else(|| {
+                        textures.push(key);
+                        textures.len() - 1
+                    })
+                })
+                .collect();
+            // One call per texture run without the atlas
+            assert_eq!(consecutive_runs(&keys, |&k| k).len(), before);
+            // Every glyph fits, so they all sample the atlas in one run
+            let sizes = vec![(cell.0, cell.1); textures.len()];
+            let (_, origins) = pack_glyph_atlas(&sizes, MAX_GLYPH_ATLAS_SIZE);
+            assert!(origins.iter().all(Option::is_some));
+            assert_eq!(consecutive_runs(&keys, |_| 0).len(), 1);
+        }
+    }
 }